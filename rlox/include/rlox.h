@@ -0,0 +1,30 @@
+#ifndef RLOX_H
+#define RLOX_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct RloxHandle RloxHandle;
+
+RloxHandle *rlox_new(void);
+void rlox_free(RloxHandle *handle);
+
+/* Returns 0 on success, 65 for scan/parse errors, 70 for runtime errors and
+ * -1 for invalid arguments. */
+int rlox_run(RloxHandle *handle, const char *source);
+
+/* Returns the value of `expr` as a string to be released with
+ * rlox_string_free, or NULL on error. */
+char *rlox_eval(RloxHandle *handle, const char *expr);
+
+/* Returns the message of the last failed call, or NULL. Owned by `handle`. */
+const char *rlox_last_error(const RloxHandle *handle);
+
+void rlox_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,680 @@
+use crate::{
+    generate_ast::{Expr, FunctionStmt, Stmt},
+    number::Numeric,
+    token::{Object, Token},
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AstFormat {
+    SExpr,
+    Json,
+    Dot,
+}
+
+impl AstFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sexpr" => Some(AstFormat::SExpr),
+            "json" => Some(AstFormat::Json),
+            "dot" => Some(AstFormat::Dot),
+            _ => None,
+        }
+    }
+}
+
+pub struct AstPrinter {
+    format: AstFormat,
+}
+
+impl AstPrinter {
+    pub fn new(format: AstFormat) -> Self {
+        Self { format }
+    }
+
+    pub fn print(&self, stmts: &[Stmt]) -> String {
+        match self.format {
+            AstFormat::SExpr => stmts
+                .iter()
+                .map(|stmt| self.sexpr_stmt(stmt))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            AstFormat::Json => self.json_stmts(stmts),
+            AstFormat::Dot => DotGraph::new().print(stmts),
+        }
+    }
+
+    fn sexpr_stmt(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Block(stmt) => self.parenthesize_stmts("block", &stmt.statements),
+            Stmt::Break(_) => "(break)".into(),
+            Stmt::Continue(_) => "(continue)".into(),
+            Stmt::Expression(stmt) => self.parenthesize(";", &[&stmt.expression]),
+            Stmt::Class(stmt) => {
+                let mut builder = format!("(class {}", stmt.name.lexeme);
+                if let Some(superclass) = &stmt.superclass {
+                    builder.push_str(&format!(" < {}", self.sexpr_expr(superclass)));
+                }
+                for method in &stmt.methods {
+                    builder.push(' ');
+                    builder.push_str(&self.sexpr_function(method));
+                }
+                builder.push(')');
+                builder
+            }
+            Stmt::Function(stmt) => self.sexpr_function(stmt),
+            Stmt::If(stmt) => {
+                let mut builder = format!(
+                    "(if {} {}",
+                    self.sexpr_expr(&stmt.condition),
+                    self.sexpr_stmt(&stmt.then_branch)
+                );
+                if let Some(else_branch) = &stmt.else_branch {
+                    builder.push(' ');
+                    builder.push_str(&self.sexpr_stmt(else_branch));
+                }
+                builder.push(')');
+                builder
+            }
+            Stmt::Print(stmt) => self.parenthesize("print", &[&stmt.expression]),
+            Stmt::Return(stmt) => match &stmt.value {
+                Some(value) => self.parenthesize("return", &[value]),
+                None => "(return)".into(),
+            },
+            Stmt::While(stmt) => match &stmt.increment {
+                Some(increment) => format!(
+                    "(while {} {} {})",
+                    self.sexpr_expr(&stmt.condition),
+                    self.sexpr_stmt(&stmt.body),
+                    self.sexpr_expr(increment)
+                ),
+                None => format!(
+                    "(while {} {})",
+                    self.sexpr_expr(&stmt.condition),
+                    self.sexpr_stmt(&stmt.body)
+                ),
+            },
+            Stmt::Var(stmt) => match &stmt.initializer {
+                Some(initializer) => {
+                    self.parenthesize(&format!("var {}", stmt.name.lexeme), &[initializer])
+                }
+                None => format!("(var {})", stmt.name.lexeme),
+            },
+            Stmt::Import(stmt) => format!("(import {})", stmt.name.lexeme),
+            Stmt::Macro(stmt) => {
+                let params = stmt
+                    .params
+                    .iter()
+                    .map(|param| param.lexeme.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let name = format!("macro {} ({})", stmt.name.lexeme, params);
+                self.parenthesize_stmts(&name, &stmt.body)
+            }
+        }
+    }
+
+    fn sexpr_function(&self, stmt: &FunctionStmt) -> String {
+        let params = stmt
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let name = format!("fun {} ({})", stmt.name.lexeme, params);
+        self.parenthesize_stmts(&name, &stmt.body)
+    }
+
+    fn sexpr_expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Assign(expr) => {
+                self.parenthesize(&format!("= {}", expr.name.lexeme), &[&expr.value])
+            }
+            Expr::Binary(expr) => {
+                self.parenthesize(&expr.operator.lexeme, &[&expr.left, &expr.right])
+            }
+            Expr::Call(expr) => {
+                let mut exprs = vec![expr.callee.as_ref()];
+                exprs.extend(expr.arguments.iter());
+                self.parenthesize("call", &exprs)
+            }
+            Expr::Get(expr) => {
+                self.parenthesize(&format!(". {}", expr.name.lexeme), &[&expr.object])
+            }
+            Expr::Grouping(expr) => self.parenthesize("group", &[&expr.expression]),
+            Expr::Interpolation(expr) => {
+                self.parenthesize("interpolate", &expr.parts.iter().collect::<Vec<_>>())
+            }
+            Expr::Literal(expr) => match &expr.value {
+                Object::String(s) => format!("{:?}", s),
+                Object::None => "nil".into(),
+                value => value.to_string(),
+            },
+            Expr::Logical(expr) => {
+                self.parenthesize(&expr.operator.lexeme, &[&expr.left, &expr.right])
+            }
+            Expr::Set(expr) => self.parenthesize(
+                &format!(".= {}", expr.name.lexeme),
+                &[&expr.object, &expr.value],
+            ),
+            Expr::Super(expr) => format!("(super {})", expr.method.lexeme),
+            Expr::This(_) => "this".into(),
+            Expr::Unary(expr) => self.parenthesize(&expr.operator.lexeme, &[&expr.right]),
+            Expr::Variable(expr) => expr.name.lexeme.clone(),
+        }
+    }
+
+    fn parenthesize(&self, name: &str, exprs: &[&Expr]) -> String {
+        let mut builder = format!("({}", name);
+        for expr in exprs {
+            builder.push(' ');
+            builder.push_str(&self.sexpr_expr(expr));
+        }
+        builder.push(')');
+        builder
+    }
+
+    fn parenthesize_stmts(&self, name: &str, stmts: &[Stmt]) -> String {
+        let mut builder = format!("({}", name);
+        for stmt in stmts {
+            builder.push(' ');
+            builder.push_str(&self.sexpr_stmt(stmt));
+        }
+        builder.push(')');
+        builder
+    }
+
+    fn json_stmts(&self, stmts: &[Stmt]) -> String {
+        let stmts = stmts
+            .iter()
+            .map(|stmt| self.json_stmt(stmt))
+            .collect::<Vec<_>>();
+        format!("[{}]", stmts.join(","))
+    }
+
+    fn json_stmt(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Block(stmt) => json_object(
+                "Block",
+                &[("statements", self.json_stmts(&stmt.statements))],
+            ),
+            Stmt::Break(stmt) => json_object("Break", &[("keyword", json_token(&stmt.keyword))]),
+            Stmt::Continue(stmt) => {
+                json_object("Continue", &[("keyword", json_token(&stmt.keyword))])
+            }
+            Stmt::Expression(stmt) => json_object(
+                "Expression",
+                &[("expression", self.json_expr(&stmt.expression))],
+            ),
+            Stmt::Class(stmt) => {
+                let methods = stmt
+                    .methods
+                    .iter()
+                    .map(|method| self.json_function(method))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                json_object(
+                    "Class",
+                    &[
+                        ("name", json_token(&stmt.name)),
+                        (
+                            "superclass",
+                            match &stmt.superclass {
+                                Some(superclass) => self.json_expr(superclass),
+                                None => "null".into(),
+                            },
+                        ),
+                        ("methods", format!("[{}]", methods)),
+                    ],
+                )
+            }
+            Stmt::Function(stmt) => self.json_function(stmt),
+            Stmt::If(stmt) => json_object(
+                "If",
+                &[
+                    ("condition", self.json_expr(&stmt.condition)),
+                    ("then_branch", self.json_stmt(&stmt.then_branch)),
+                    (
+                        "else_branch",
+                        match &stmt.else_branch {
+                            Some(else_branch) => self.json_stmt(else_branch),
+                            None => "null".into(),
+                        },
+                    ),
+                ],
+            ),
+            Stmt::Print(stmt) => {
+                json_object("Print", &[("expression", self.json_expr(&stmt.expression))])
+            }
+            Stmt::Return(stmt) => json_object(
+                "Return",
+                &[(
+                    "value",
+                    match &stmt.value {
+                        Some(value) => self.json_expr(value),
+                        None => "null".into(),
+                    },
+                )],
+            ),
+            Stmt::While(stmt) => json_object(
+                "While",
+                &[
+                    ("condition", self.json_expr(&stmt.condition)),
+                    ("body", self.json_stmt(&stmt.body)),
+                    (
+                        "increment",
+                        match &stmt.increment {
+                            Some(increment) => self.json_expr(increment),
+                            None => "null".into(),
+                        },
+                    ),
+                ],
+            ),
+            Stmt::Var(stmt) => json_object(
+                "Var",
+                &[
+                    ("name", json_token(&stmt.name)),
+                    (
+                        "initializer",
+                        match &stmt.initializer {
+                            Some(initializer) => self.json_expr(initializer),
+                            None => "null".into(),
+                        },
+                    ),
+                ],
+            ),
+            Stmt::Import(stmt) => json_object("Import", &[("name", json_token(&stmt.name))]),
+            Stmt::Macro(stmt) => {
+                let params = stmt
+                    .params
+                    .iter()
+                    .map(json_token)
+                    .collect::<Vec<_>>()
+                    .join(",");
+                json_object(
+                    "Macro",
+                    &[
+                        ("name", json_token(&stmt.name)),
+                        ("params", format!("[{}]", params)),
+                        ("body", self.json_stmts(&stmt.body)),
+                    ],
+                )
+            }
+        }
+    }
+
+    fn json_function(&self, stmt: &FunctionStmt) -> String {
+        let params = stmt
+            .params
+            .iter()
+            .map(json_token)
+            .collect::<Vec<_>>()
+            .join(",");
+        json_object(
+            "Function",
+            &[
+                ("name", json_token(&stmt.name)),
+                ("params", format!("[{}]", params)),
+                ("body", self.json_stmts(&stmt.body)),
+            ],
+        )
+    }
+
+    fn json_expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Assign(expr) => json_object(
+                "Assign",
+                &[
+                    ("name", json_token(&expr.name)),
+                    ("value", self.json_expr(&expr.value)),
+                ],
+            ),
+            Expr::Binary(expr) => json_object(
+                "Binary",
+                &[
+                    ("left", self.json_expr(&expr.left)),
+                    ("operator", json_token(&expr.operator)),
+                    ("right", self.json_expr(&expr.right)),
+                ],
+            ),
+            Expr::Call(expr) => {
+                let arguments = expr
+                    .arguments
+                    .iter()
+                    .map(|arg| self.json_expr(arg))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                json_object(
+                    "Call",
+                    &[
+                        ("callee", self.json_expr(&expr.callee)),
+                        ("arguments", format!("[{}]", arguments)),
+                    ],
+                )
+            }
+            Expr::Get(expr) => json_object(
+                "Get",
+                &[
+                    ("object", self.json_expr(&expr.object)),
+                    ("name", json_token(&expr.name)),
+                ],
+            ),
+            Expr::Grouping(expr) => json_object(
+                "Grouping",
+                &[("expression", self.json_expr(&expr.expression))],
+            ),
+            Expr::Interpolation(expr) => {
+                let parts = expr
+                    .parts
+                    .iter()
+                    .map(|part| self.json_expr(part))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                json_object("Interpolation", &[("parts", format!("[{}]", parts))])
+            }
+            Expr::Literal(expr) => json_object("Literal", &[("value", json_value(&expr.value))]),
+            Expr::Logical(expr) => json_object(
+                "Logical",
+                &[
+                    ("left", self.json_expr(&expr.left)),
+                    ("operator", json_token(&expr.operator)),
+                    ("right", self.json_expr(&expr.right)),
+                ],
+            ),
+            Expr::Set(expr) => json_object(
+                "Set",
+                &[
+                    ("object", self.json_expr(&expr.object)),
+                    ("name", json_token(&expr.name)),
+                    ("value", self.json_expr(&expr.value)),
+                ],
+            ),
+            Expr::Super(expr) => json_object(
+                "Super",
+                &[
+                    ("keyword", json_token(&expr.keyword)),
+                    ("method", json_token(&expr.method)),
+                ],
+            ),
+            Expr::This(expr) => json_object("This", &[("keyword", json_token(&expr.keyword))]),
+            Expr::Unary(expr) => json_object(
+                "Unary",
+                &[
+                    ("operator", json_token(&expr.operator)),
+                    ("right", self.json_expr(&expr.right)),
+                ],
+            ),
+            Expr::Variable(expr) => json_object("Variable", &[("name", json_token(&expr.name))]),
+        }
+    }
+}
+
+struct DotGraph {
+    lines: Vec<String>,
+    nodes: usize,
+}
+
+impl DotGraph {
+    fn new() -> Self {
+        Self {
+            lines: vec![],
+            nodes: 0,
+        }
+    }
+
+    fn print(mut self, stmts: &[Stmt]) -> String {
+        let root = self.node("program");
+        for stmt in stmts {
+            let child = self.stmt(stmt);
+            self.edge(root, child, None);
+        }
+        format!(
+            "digraph ast {{\n  node [shape=box];\n{}\n}}",
+            self.lines.join("\n")
+        )
+    }
+
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+        self.lines
+            .push(format!("  n{} [label={}];", id, json_string(label)));
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: Option<&str>) {
+        match label {
+            Some(label) => self.lines.push(format!(
+                "  n{} -> n{} [label={}];",
+                from,
+                to,
+                json_string(label)
+            )),
+            None => self.lines.push(format!("  n{} -> n{};", from, to)),
+        }
+    }
+
+    fn child_stmt(&mut self, parent: usize, stmt: &Stmt, label: &str) {
+        let child = self.stmt(stmt);
+        self.edge(parent, child, Some(label));
+    }
+
+    fn child_expr(&mut self, parent: usize, expr: &Expr, label: &str) {
+        let child = self.expr(expr);
+        self.edge(parent, child, Some(label));
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> usize {
+        match stmt {
+            Stmt::Block(stmt) => {
+                let id = self.node("Block");
+                for stmt in &stmt.statements {
+                    let child = self.stmt(stmt);
+                    self.edge(id, child, None);
+                }
+                id
+            }
+            Stmt::Expression(stmt) => {
+                let id = self.node("Expression");
+                self.child_expr(id, &stmt.expression, "expression");
+                id
+            }
+            Stmt::Class(stmt) => {
+                let id = self.node(&format!("Class {}", stmt.name.lexeme));
+                if let Some(superclass) = &stmt.superclass {
+                    self.child_expr(id, superclass, "superclass");
+                }
+                for method in &stmt.methods {
+                    let child = self.function(method);
+                    self.edge(id, child, Some("method"));
+                }
+                id
+            }
+            Stmt::Function(stmt) => self.function(stmt),
+            Stmt::If(stmt) => {
+                let id = self.node("If");
+                self.child_expr(id, &stmt.condition, "condition");
+                self.child_stmt(id, &stmt.then_branch, "then");
+                if let Some(else_branch) = &stmt.else_branch {
+                    self.child_stmt(id, else_branch, "else");
+                }
+                id
+            }
+            Stmt::Print(stmt) => {
+                let id = self.node("Print");
+                self.child_expr(id, &stmt.expression, "expression");
+                id
+            }
+            Stmt::Return(stmt) => {
+                let id = self.node("Return");
+                if let Some(value) = &stmt.value {
+                    self.child_expr(id, value, "value");
+                }
+                id
+            }
+            Stmt::While(stmt) => {
+                let id = self.node("While");
+                self.child_expr(id, &stmt.condition, "condition");
+                self.child_stmt(id, &stmt.body, "body");
+                if let Some(increment) = &stmt.increment {
+                    self.child_expr(id, increment, "increment");
+                }
+                id
+            }
+            Stmt::Var(stmt) => {
+                let id = self.node(&format!("Var {}", stmt.name.lexeme));
+                if let Some(initializer) = &stmt.initializer {
+                    self.child_expr(id, initializer, "initializer");
+                }
+                id
+            }
+            Stmt::Break(_) => self.node("Break"),
+            Stmt::Continue(_) => self.node("Continue"),
+            Stmt::Import(stmt) => self.node(&format!("Import {}", stmt.name.lexeme)),
+            Stmt::Macro(stmt) => {
+                let params = stmt
+                    .params
+                    .iter()
+                    .map(|param| param.lexeme.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let id = self.node(&format!("Macro {}({})", stmt.name.lexeme, params));
+                for stmt in &stmt.body {
+                    self.child_stmt(id, stmt, "body");
+                }
+                id
+            }
+        }
+    }
+
+    fn function(&mut self, stmt: &FunctionStmt) -> usize {
+        let params = stmt
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let id = self.node(&format!("Function {}({})", stmt.name.lexeme, params));
+        for stmt in &stmt.body {
+            self.child_stmt(id, stmt, "body");
+        }
+        id
+    }
+
+    fn expr(&mut self, expr: &Expr) -> usize {
+        match expr {
+            Expr::Assign(expr) => {
+                let id = self.node(&format!("Assign {}", expr.name.lexeme));
+                self.child_expr(id, &expr.value, "value");
+                id
+            }
+            Expr::Binary(expr) => {
+                let id = self.node(&format!("Binary {}", expr.operator.lexeme));
+                self.child_expr(id, &expr.left, "left");
+                self.child_expr(id, &expr.right, "right");
+                id
+            }
+            Expr::Call(expr) => {
+                let id = self.node("Call");
+                self.child_expr(id, &expr.callee, "callee");
+                for (i, argument) in expr.arguments.iter().enumerate() {
+                    self.child_expr(id, argument, &format!("arg {}", i));
+                }
+                id
+            }
+            Expr::Get(expr) => {
+                let id = self.node(&format!("Get {}", expr.name.lexeme));
+                self.child_expr(id, &expr.object, "object");
+                id
+            }
+            Expr::Grouping(expr) => {
+                let id = self.node("Grouping");
+                self.child_expr(id, &expr.expression, "expression");
+                id
+            }
+            Expr::Interpolation(expr) => {
+                let id = self.node("Interpolation");
+                for (i, part) in expr.parts.iter().enumerate() {
+                    self.child_expr(id, part, &format!("part {}", i));
+                }
+                id
+            }
+            Expr::Literal(expr) => {
+                let value = match &expr.value {
+                    Object::String(s) => format!("{:?}", s),
+                    Object::None => "nil".into(),
+                    value => value.to_string(),
+                };
+                self.node(&format!("Literal {}", value))
+            }
+            Expr::Logical(expr) => {
+                let id = self.node(&format!("Logical {}", expr.operator.lexeme));
+                self.child_expr(id, &expr.left, "left");
+                self.child_expr(id, &expr.right, "right");
+                id
+            }
+            Expr::Set(expr) => {
+                let id = self.node(&format!("Set {}", expr.name.lexeme));
+                self.child_expr(id, &expr.object, "object");
+                self.child_expr(id, &expr.value, "value");
+                id
+            }
+            Expr::Super(expr) => self.node(&format!("Super {}", expr.method.lexeme)),
+            Expr::This(_) => self.node("This"),
+            Expr::Unary(expr) => {
+                let id = self.node(&format!("Unary {}", expr.operator.lexeme));
+                self.child_expr(id, &expr.right, "right");
+                id
+            }
+            Expr::Variable(expr) => self.node(&format!("Variable {}", expr.name.lexeme)),
+        }
+    }
+}
+
+fn json_object(node: &str, fields: &[(&str, String)]) -> String {
+    let mut builder = format!("{{\"node\":{}", json_string(node));
+    for (name, value) in fields {
+        builder.push_str(&format!(",{}:{}", json_string(name), value));
+    }
+    builder.push('}');
+    builder
+}
+
+fn json_token(token: &Token) -> String {
+    format!(
+        "{{\"lexeme\":{},\"line\":{},\"column\":{}}}",
+        json_string(&token.lexeme),
+        token.line,
+        token.column
+    )
+}
+
+fn json_value(value: &Object) -> String {
+    match value {
+        Object::String(s) => json_string(s),
+        Object::Num(n) if n.is_finite() => n.to_lox_string(),
+        Object::Num(_) => "null".into(),
+        Object::Bool(b) => b.to_string(),
+        Object::Fun(fun) => json_string(&fun.declaration.name.lexeme),
+        Object::Native(native) => json_string(&native.name),
+        Object::Class(_) | Object::Instance(_) => json_string(&value.to_string()),
+        Object::UserData(data) => json_string(data.type_name()),
+        Object::None => "null".into(),
+    }
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
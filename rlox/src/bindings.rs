@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use crate::{
+    generate_ast::{Expr, FunctionStmt, Stmt},
+    token::Token,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BindingKind {
+    Variable,
+    Function,
+    Parameter,
+    Class,
+}
+
+#[cfg_attr(not(feature = "tools"), allow(dead_code))]
+pub struct Declaration {
+    pub token: Token,
+    pub kind: BindingKind,
+    pub signature: String,
+}
+
+pub struct BindingTable {
+    declarations: Vec<Declaration>,
+    occurrences: Vec<(Token, usize)>,
+    scopes: Vec<HashMap<String, usize>>,
+}
+
+impl BindingTable {
+    pub fn new(stmts: &[Stmt]) -> Self {
+        let mut table = Self {
+            declarations: vec![],
+            occurrences: vec![],
+            scopes: vec![HashMap::new()],
+        };
+        for stmt in stmts {
+            match stmt {
+                Stmt::Var(stmt) => table.declare(
+                    &stmt.name,
+                    BindingKind::Variable,
+                    format!("var {}", stmt.name.lexeme),
+                ),
+                Stmt::Function(stmt) => table.declare(
+                    &stmt.name,
+                    BindingKind::Function,
+                    signature(&stmt.name, &stmt.params),
+                ),
+                Stmt::Class(stmt) => table.declare(
+                    &stmt.name,
+                    BindingKind::Class,
+                    format!("class {}", stmt.name.lexeme),
+                ),
+                _ => (),
+            }
+        }
+        table.occurrences.clear();
+        for stmt in stmts {
+            table.stmt(stmt);
+        }
+        table.scopes.clear();
+        table
+    }
+
+    pub fn declaration_at(&self, line: usize, column: usize) -> Option<&Declaration> {
+        self.binding_at(line, column)
+            .map(|index| &self.declarations[index])
+    }
+
+    #[cfg_attr(not(feature = "tools"), allow(dead_code))]
+    pub fn occurrences_at(&self, line: usize, column: usize) -> Vec<&Token> {
+        let Some(index) = self.binding_at(line, column) else {
+            return vec![];
+        };
+        self.occurrences
+            .iter()
+            .filter(|(_, binding)| *binding == index)
+            .map(|(token, _)| token)
+            .collect()
+    }
+
+    fn binding_at(&self, line: usize, column: usize) -> Option<usize> {
+        self.occurrences
+            .iter()
+            .find(|(token, _)| {
+                token.line == line
+                    && token.column <= column
+                    && column < token.column + token.lexeme.chars().count()
+            })
+            .map(|(_, binding)| *binding)
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(stmt) => {
+                self.scopes.push(HashMap::new());
+                for stmt in &stmt.statements {
+                    self.stmt(stmt);
+                }
+                self.scopes.pop();
+            }
+            Stmt::Class(stmt) => {
+                self.declare(
+                    &stmt.name,
+                    BindingKind::Class,
+                    format!("class {}", stmt.name.lexeme),
+                );
+                if let Some(superclass) = &stmt.superclass {
+                    self.expr(superclass);
+                }
+                for method in &stmt.methods {
+                    self.function(method);
+                }
+            }
+            Stmt::Expression(stmt) => self.expr(&stmt.expression),
+            Stmt::Function(stmt) => {
+                self.declare(
+                    &stmt.name,
+                    BindingKind::Function,
+                    signature(&stmt.name, &stmt.params),
+                );
+                self.function(stmt);
+            }
+            Stmt::If(stmt) => {
+                self.expr(&stmt.condition);
+                self.stmt(&stmt.then_branch);
+                if let Some(else_branch) = &stmt.else_branch {
+                    self.stmt(else_branch);
+                }
+            }
+            Stmt::Print(stmt) => self.expr(&stmt.expression),
+            Stmt::Return(stmt) => {
+                if let Some(value) = &stmt.value {
+                    self.expr(value);
+                }
+            }
+            Stmt::While(stmt) => {
+                self.expr(&stmt.condition);
+                self.stmt(&stmt.body);
+            }
+            Stmt::Var(stmt) => {
+                if let Some(initializer) = &stmt.initializer {
+                    self.expr(initializer);
+                }
+                self.declare(
+                    &stmt.name,
+                    BindingKind::Variable,
+                    format!("var {}", stmt.name.lexeme),
+                );
+            }
+            Stmt::Break(_) | Stmt::Continue(_) | Stmt::Import(_) | Stmt::Macro(_) => (),
+        }
+    }
+
+    fn function(&mut self, stmt: &FunctionStmt) {
+        self.scopes.push(HashMap::new());
+        for param in &stmt.params {
+            self.declare(
+                param,
+                BindingKind::Parameter,
+                format!("parameter {}", param.lexeme),
+            );
+        }
+        for stmt in &stmt.body {
+            self.stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign(expr) => {
+                self.expr(&expr.value);
+                self.resolve(&expr.name);
+            }
+            Expr::Binary(expr) => {
+                self.expr(&expr.left);
+                self.expr(&expr.right);
+            }
+            Expr::Call(expr) => {
+                self.expr(&expr.callee);
+                for argument in &expr.arguments {
+                    self.expr(argument);
+                }
+            }
+            Expr::Get(expr) => self.expr(&expr.object),
+            Expr::Grouping(expr) => self.expr(&expr.expression),
+            Expr::Interpolation(expr) => {
+                for part in &expr.parts {
+                    self.expr(part);
+                }
+            }
+            Expr::Literal(_) | Expr::Super(_) | Expr::This(_) => (),
+            Expr::Logical(expr) => {
+                self.expr(&expr.left);
+                self.expr(&expr.right);
+            }
+            Expr::Set(expr) => {
+                self.expr(&expr.object);
+                self.expr(&expr.value);
+            }
+            Expr::Unary(expr) => self.expr(&expr.right),
+            Expr::Variable(expr) => self.resolve(&expr.name),
+        }
+    }
+
+    fn declare(&mut self, name: &Token, kind: BindingKind, signature: String) {
+        let global = self.scopes.len() == 1;
+        let scope = self.scopes.last_mut().expect("scope");
+        let index = match scope.get(&name.lexeme) {
+            Some(index) if global => *index,
+            _ => {
+                self.declarations.push(Declaration {
+                    token: name.clone(),
+                    kind,
+                    signature,
+                });
+                let index = self.declarations.len() - 1;
+                scope.insert(name.lexeme.clone(), index);
+                index
+            }
+        };
+        self.occurrences.push((name.clone(), index));
+    }
+
+    fn resolve(&mut self, name: &Token) {
+        if name.global {
+            return;
+        }
+        let binding = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme));
+        if let Some(index) = binding {
+            self.occurrences.push((name.clone(), *index));
+        }
+    }
+}
+
+fn signature(name: &Token, params: &[Token]) -> String {
+    let params: Vec<&str> = params.iter().map(|param| param.lexeme.as_str()).collect();
+    format!("fun {}({})", name.lexeme, params.join(", "))
+}
@@ -0,0 +1,128 @@
+use std::{
+    io::{BufRead, Write},
+    time::Duration,
+};
+
+use crate::{
+    diagnostic::{ColorChoice, ErrorFormat},
+    interpreter::Interpreter,
+    module::ModuleLoader,
+    options::InterpreterOptions,
+    sync::{DiagnosticSink, MaybeSend, MaybeSync, Reader, Writer},
+    Lox,
+};
+
+pub struct LoxBuilder {
+    options: InterpreterOptions,
+    output: Option<Box<Writer>>,
+    diagnostic_output: Option<Box<Writer>>,
+    diagnostic_sink: Option<Box<DiagnosticSink>>,
+    input: Option<Box<Reader>>,
+    module_loader: Option<Box<dyn ModuleLoader>>,
+    error_format: ErrorFormat,
+    color: Option<ColorChoice>,
+}
+
+impl LoxBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: InterpreterOptions::new(),
+            output: None,
+            diagnostic_output: None,
+            diagnostic_sink: None,
+            input: None,
+            module_loader: None,
+            error_format: ErrorFormat::Human,
+            color: None,
+        }
+    }
+
+    pub fn stdout(mut self, output: impl Write + MaybeSync + 'static) -> Self {
+        self.output = Some(Box::new(output));
+        self
+    }
+
+    pub fn stderr(mut self, output: impl Write + MaybeSync + 'static) -> Self {
+        self.diagnostic_output = Some(Box::new(output));
+        self
+    }
+
+    pub fn diagnostic_sink(mut self, diagnostic_sink: Box<DiagnosticSink>) -> Self {
+        self.diagnostic_sink = Some(diagnostic_sink);
+        self
+    }
+
+    pub fn stdin(mut self, input: impl BufRead + MaybeSend + 'static) -> Self {
+        self.input = Some(Box::new(input));
+        self
+    }
+
+    pub fn sandbox(mut self, options: InterpreterOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.options.max_steps = Some(max_steps);
+        self
+    }
+
+    pub fn max_duration(mut self, max_duration: Duration) -> Self {
+        self.options.max_duration = Some(max_duration);
+        self
+    }
+
+    pub fn with_stdlib(mut self, stdlib: bool) -> Self {
+        self.options.stdlib = stdlib;
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    pub fn module_loader(mut self, module_loader: impl ModuleLoader + 'static) -> Self {
+        self.module_loader = Some(Box::new(module_loader));
+        self
+    }
+
+    pub fn error_format(mut self, error_format: ErrorFormat) -> Self {
+        self.error_format = error_format;
+        self
+    }
+
+    pub fn color(mut self, color: ColorChoice) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn build(self) -> Lox {
+        let mut lox = Lox::new();
+        lox.interpreter = Interpreter::with_options(self.options);
+        if let Some(output) = self.output {
+            lox.set_output(output);
+        }
+        if let Some(diagnostic_output) = self.diagnostic_output {
+            lox.set_diagnostic_output(diagnostic_output);
+        }
+        lox.set_diagnostic_sink(self.diagnostic_sink);
+        if let Some(input) = self.input {
+            lox.set_input(input);
+        }
+        if let Some(module_loader) = self.module_loader {
+            lox.set_module_loader(module_loader);
+        }
+        if let Some(color) = self.color {
+            lox.set_color(color);
+        }
+        lox.set_error_format(self.error_format);
+        lox
+    }
+}
+
+impl Default for LoxBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt::Debug;
+
+use crate::{
+    environment::Environment,
+    generate_ast::FunctionStmt,
+    interpreter::{Interpreter, LoxRuntimeException},
+    sync::{Lock, MaybeSync, Shared},
+    token::{Object, Token},
+    token_type::TokenType,
+    LoxRuntimeError,
+};
+
+pub trait LoxCallable: MaybeSync {
+    fn arity(&self) -> usize;
+
+    fn name(&self) -> &str;
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxRuntimeException>;
+}
+
+pub struct LoxFunction {
+    pub declaration: Shared<FunctionStmt>,
+    pub closure: Shared<Lock<Environment>>,
+    pub is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: FunctionStmt,
+        closure: Shared<Lock<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            declaration: Shared::new(declaration),
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// The instance a bound method runs on, which an initializer returns.
+    pub(crate) fn this(&self) -> Object {
+        self.closure.borrow().lookup("this").unwrap_or(Object::None)
+    }
+
+    pub fn bind(&self, instance: Object) -> Self {
+        let mut closure = Environment::new_enclosing(self.closure.clone());
+        closure.define("this", &instance);
+        Self {
+            declaration: self.declaration.clone(),
+            closure: Shared::new(Lock::new(closure)),
+            is_initializer: self.is_initializer,
+        }
+    }
+}
+
+// A closure can hold the function itself, so functions compare and print by
+// identity instead of walking their environment.
+impl PartialEq for LoxFunction {
+    fn eq(&self, other: &Self) -> bool {
+        Shared::ptr_eq(&self.declaration, &other.declaration)
+            && Shared::ptr_eq(&self.closure, &other.closure)
+    }
+}
+
+impl Debug for LoxFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoxFunction")
+            .field("name", &self.declaration.name.lexeme)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn name(&self) -> &str {
+        &self.declaration.name.lexeme
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxRuntimeException> {
+        interpreter.call_function(self, paren, arguments)
+    }
+}
+
+#[derive(Clone)]
+pub struct LoxFunctionHandle {
+    callable: Shared<dyn LoxCallable>,
+}
+
+impl LoxFunctionHandle {
+    pub(crate) fn new(callable: Shared<dyn LoxCallable>) -> Self {
+        Self { callable }
+    }
+
+    pub fn name(&self) -> &str {
+        self.callable.name()
+    }
+
+    pub fn arity(&self) -> usize {
+        self.callable.arity()
+    }
+
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxRuntimeError> {
+        let token = Token::new(
+            TokenType::Identifier,
+            self.name().into(),
+            Object::None,
+            0,
+            0,
+        );
+        if arguments.len() != self.arity() {
+            return Err(LoxRuntimeError(
+                token,
+                format!(
+                    "Expected {} arguments but got {}.",
+                    self.arity(),
+                    arguments.len()
+                ),
+            ));
+        }
+        interpreter.start_budget();
+        match self.callable.call(interpreter, &token, arguments) {
+            Ok(value) | Err(LoxRuntimeException::Return(value)) => Ok(value),
+            Err(LoxRuntimeException::Err(err)) => Err(err),
+            Err(
+                LoxRuntimeException::Break
+                | LoxRuntimeException::Continue
+                | LoxRuntimeException::Halt,
+            ) => Ok(Object::None),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+#[derive(Clone, Default, Debug)]
+pub struct CancellationHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    #[cfg(feature = "jit")]
+    pub(crate) fn flag(&self) -> &AtomicBool {
+        &self.cancelled
+    }
+
+    pub(crate) fn take(&self) -> bool {
+        self.cancelled.swap(false, Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,115 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use crate::{
+    callable::{LoxCallable, LoxFunction},
+    interpreter::{Interpreter, LoxRuntimeException},
+    sync::{Lock, Shared},
+    token::{Object, Token},
+};
+
+#[derive(Debug)]
+pub struct LoxClass {
+    pub name: String,
+    pub superclass: Option<Shared<LoxClass>>,
+    methods: HashMap<String, Shared<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: &str,
+        superclass: Option<Shared<LoxClass>>,
+        methods: HashMap<String, Shared<LoxFunction>>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            superclass,
+            methods,
+        }
+    }
+
+    pub(crate) fn methods(&self) -> impl Iterator<Item = (&String, &Shared<LoxFunction>)> {
+        self.methods.iter()
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<Shared<LoxFunction>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+}
+
+impl PartialEq for LoxClass {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl LoxCallable for Shared<LoxClass> {
+    fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.arity())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxRuntimeException> {
+        let instance = interpreter.instantiate(self);
+        if let Some(init) = self.find_method("init") {
+            init.bind(instance.clone())
+                .call(interpreter, paren, arguments)?;
+        }
+        Ok(instance)
+    }
+}
+
+pub struct LoxInstance {
+    pub class: Shared<LoxClass>,
+    fields: Lock<HashMap<String, Object>>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Shared<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: Lock::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Object> {
+        self.fields.borrow().get(name).cloned()
+    }
+
+    pub fn set(&self, name: &str, value: Object) {
+        self.fields.borrow_mut().insert(name.into(), value);
+    }
+
+    pub(crate) fn fields(&self) -> Vec<(String, Object)> {
+        self.fields
+            .borrow()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+}
+
+impl PartialEq for LoxInstance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Fields can refer back to the instance, so only the class is printed.
+impl Debug for LoxInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoxInstance")
+            .field("class", &self.class.name)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,296 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::{error::ErrorKind, ArgAction, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use rlox::{AstFormat, Capability, ColorChoice, ErrorFormat, LintRule};
+
+#[derive(Parser)]
+#[command(name = "rlox", version, about = "An interpreter for the Lox language")]
+#[command(override_usage = "rlox [OPTIONS] [SCRIPTS]...\n       rlox [OPTIONS] <COMMAND>")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+
+    /// Print a detailed explanation of an error code such as E0001
+    #[arg(long, value_name = "CODE", exclusive = true)]
+    pub explain: Option<String>,
+
+    #[command(flatten)]
+    pub global: GlobalArgs,
+}
+
+impl Cli {
+    /// The top-level run arguments are a shorthand for `rlox run`, so they
+    /// can't be combined with a subcommand. Global flags can appear on
+    /// either side of one.
+    pub fn check_conflicts(&self) {
+        if self.command.is_none() {
+            return;
+        }
+        let flag = match &self.run {
+            _ if self.explain.is_some() => "--explain <CODE>",
+            RunArgs { eval: Some(_), .. } => "--eval <CODE>",
+            RunArgs { tokens: true, .. } => "--tokens",
+            RunArgs { ast: Some(_), .. } => "--ast[=<FORMAT>]",
+            RunArgs { literate: true, .. } => "--literate",
+            RunArgs {
+                record: Some(_), ..
+            } => "--record <FILE>",
+            _ => return,
+        };
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!("the argument '{}' cannot be used with a subcommand", flag),
+            )
+            .exit();
+    }
+}
+
+#[derive(Args)]
+pub struct GlobalArgs {
+    /// How to print errors and warnings: human (default) or json
+    #[arg(long, global = true, value_name = "FORMAT", value_parser = parse_error_format)]
+    pub error_format: Option<ErrorFormat>,
+
+    /// When to color diagnostics: auto (default), always or never
+    #[arg(long, global = true, value_name = "WHEN", value_parser = parse_color)]
+    pub color: Option<ColorChoice>,
+
+    /// Warn about a lint rule, or a group such as `unused` or `all`
+    #[arg(short = 'W', long, global = true, value_name = "RULE", value_parser = parse_rule_group)]
+    pub warn: Vec<RuleGroup>,
+
+    /// Silence a lint rule or group; takes precedence over --warn
+    #[arg(short = 'A', long, global = true, value_name = "RULE", value_parser = parse_rule_group)]
+    pub allow: Vec<RuleGroup>,
+
+    /// Stop reporting scan and parse errors after N (0 for no limit) [default: 20]
+    #[arg(long, global = true, value_name = "N")]
+    pub max_errors: Option<usize>,
+
+    /// Treat warnings as errors
+    #[arg(long, global = true)]
+    pub deny_warnings: bool,
+
+    /// Reject reads of unassigned variables and division by zero, and treat shadowing as an error
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    /// Stop a script after it executes N statements
+    #[arg(long, global = true, value_name = "N")]
+    pub max_steps: Option<usize>,
+
+    /// Stop a script after it runs for SECONDS
+    #[arg(long, global = true, value_name = "SECONDS", value_parser = parse_duration)]
+    pub max_duration: Option<Duration>,
+
+    /// Stop a script when its calls nest more than N deep
+    #[arg(long, global = true, value_name = "N")]
+    pub max_call_depth: Option<usize>,
+
+    /// Comma-separated capabilities scripts may use: stdin, filesystem
+    #[arg(long, global = true, value_name = "LIST", value_parser = parse_capabilities)]
+    pub capabilities: Option<Capabilities>,
+
+    /// Only print errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print a timing summary; repeat (-vv) to log each phase
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+}
+
+#[derive(Clone)]
+pub struct RuleGroup(pub Vec<LintRule>);
+
+#[derive(Clone)]
+pub struct Capabilities(pub Vec<Capability>);
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run scripts, a project directory containing main.lox, or `-` for stdin
+    Run(RunArgs),
+    /// Start an interactive session
+    Repl,
+    /// Serve the REPL to remote clients, one connection at a time
+    Serve {
+        /// TCP address to listen on, or `unix:PATH` for a Unix socket
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:7545")]
+        listen: String,
+    },
+    /// Run a script under the interactive debugger
+    Debug {
+        /// Script to debug
+        script: PathBuf,
+
+        /// Replay a trace written by `rlox run --record`, allowing reverse-step and goto
+        #[arg(long, value_name = "FILE")]
+        replay: Option<PathBuf>,
+    },
+    /// Serve the debugger over the Debug Adapter Protocol on stdin/stdout
+    Dap,
+    /// Serve editor support over the Language Server Protocol on stdin/stdout
+    Lsp,
+    /// Format Lox source files
+    Fmt {
+        /// Files to format in place; reads stdin and writes stdout when omitted or `-`
+        files: Vec<PathBuf>,
+
+        /// Don't write anything; exit with status 1 if any file would change
+        #[arg(long)]
+        check: bool,
+    },
+    /// Report likely mistakes in Lox source files
+    Lint(LintArgs),
+    /// Run `*_test.lox` files and files with `// expect:` comments
+    Test {
+        /// Test files or directories containing them; defaults to the current directory
+        paths: Vec<PathBuf>,
+
+        /// Only run tests whose path contains this string
+        #[arg(long, value_name = "NAME")]
+        filter: Option<String>,
+    },
+    /// Compile a script to bytecode; not available yet
+    #[command(hide = true)]
+    Compile {
+        /// Script to compile
+        script: PathBuf,
+    },
+    /// Print a shell completion script
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
+    /// Print the rlox man page in roff format
+    Manpage,
+}
+
+#[derive(Args)]
+pub struct LintArgs {
+    /// Files to check
+    #[arg(required_unless_present = "list_rules")]
+    pub files: Vec<PathBuf>,
+
+    /// Enable a rule
+    #[arg(long, value_name = "RULE", value_parser = parse_lint_rule)]
+    pub enable: Vec<LintRule>,
+
+    /// Disable a rule
+    #[arg(long, value_name = "RULE", value_parser = parse_lint_rule)]
+    pub disable: Vec<LintRule>,
+
+    /// Read rule settings (`rule-name = true|false` lines) from a file
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Output format for warnings
+    #[arg(long, value_enum, default_value_t = LintFormat::Text)]
+    pub format: LintFormat,
+
+    /// List the available rules and exit
+    #[arg(long)]
+    pub list_rules: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum LintFormat {
+    Text,
+    Json,
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// Scripts to run in order with shared globals, a project directory containing main.lox, or `-` for stdin
+    pub scripts: Vec<PathBuf>,
+
+    /// Run code passed on the command line and print the value of its last expression
+    #[arg(short, long, value_name = "CODE", conflicts_with = "scripts")]
+    pub eval: Option<String>,
+
+    /// Print the scanned tokens and exit
+    #[arg(long, conflicts_with = "ast")]
+    pub tokens: bool,
+
+    /// Print the parsed AST and exit: sexpr (default), json or dot
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "sexpr",
+        value_parser = parse_ast_format,
+    )]
+    pub ast: Option<AstFormat>,
+
+    /// Treat scripts as Markdown and run their ```lox code blocks in order
+    #[arg(long, conflicts_with_all = ["eval", "tokens"])]
+    pub literate: bool,
+
+    /// Record clock, random and input values and the executed statements to FILE
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["eval", "tokens", "ast"])]
+    pub record: Option<PathBuf>,
+}
+
+fn parse_ast_format(name: &str) -> Result<AstFormat, String> {
+    AstFormat::from_name(name).ok_or_else(|| format!("unknown AST format '{}'", name))
+}
+
+fn parse_lint_rule(name: &str) -> Result<LintRule, String> {
+    LintRule::from_name(name).ok_or_else(|| format!("unknown lint rule '{}'", name))
+}
+
+fn parse_rule_group(name: &str) -> Result<RuleGroup, String> {
+    LintRule::group(name)
+        .map(RuleGroup)
+        .ok_or_else(|| format!("unknown lint rule or group '{}'", name))
+}
+
+pub fn parse_duration(seconds: &str) -> Result<Duration, String> {
+    let seconds: f64 = seconds
+        .parse()
+        .map_err(|_| format!("'{}' is not a number of seconds", seconds))?;
+    seconds_to_duration(seconds)
+}
+
+pub fn seconds_to_duration(seconds: f64) -> Result<Duration, String> {
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|_| format!("'{}' is not a valid number of seconds", seconds))
+}
+
+fn parse_capabilities(list: &str) -> Result<Capabilities, String> {
+    parse_capability_names(list.split(',').filter(|name| !name.is_empty())).map(Capabilities)
+}
+
+pub fn parse_capability_names<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<Vec<Capability>, String> {
+    names
+        .into_iter()
+        .map(|name| {
+            Capability::from_name(name).ok_or_else(|| format!("unknown capability '{}'", name))
+        })
+        .collect()
+}
+
+pub fn parse_color(name: &str) -> Result<ColorChoice, String> {
+    match name {
+        "auto" => Ok(ColorChoice::Auto),
+        "always" => Ok(ColorChoice::Always),
+        "never" => Ok(ColorChoice::Never),
+        _ => Err(format!("unknown color choice '{}'", name)),
+    }
+}
+
+pub fn parse_error_format(name: &str) -> Result<ErrorFormat, String> {
+    match name {
+        "human" => Ok(ErrorFormat::Human),
+        "json" => Ok(ErrorFormat::Json),
+        _ => Err(format!("unknown error format '{}'", name)),
+    }
+}
@@ -0,0 +1,43 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::sync::{Lock, MaybeSend, Shared};
+
+pub trait Clock: MaybeSend {
+    fn now(&mut self) -> f64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&mut self) -> f64 {
+        since_epoch()
+    }
+}
+
+/// Seconds since the Unix epoch.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn since_epoch() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64())
+}
+
+// `SystemTime::now` panics on wasm32-unknown-unknown.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn since_epoch() -> f64 {
+    #[wasm_bindgen::prelude::wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = Date, js_name = now)]
+        fn date_now() -> f64;
+    }
+    date_now() / 1000.0
+}
+
+impl<F: FnMut() -> f64 + MaybeSend> Clock for F {
+    fn now(&mut self) -> f64 {
+        self()
+    }
+}
+
+pub(crate) type ClockSource = Shared<Lock<Box<dyn Clock>>>;
@@ -0,0 +1,116 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub diagnostics: DiagnosticsConfig,
+    pub warnings: BTreeMap<String, bool>,
+    pub format: FormatConfig,
+    pub repl: ReplConfig,
+    pub sandbox: SandboxConfig,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DiagnosticsConfig {
+    pub error_format: Option<String>,
+    pub color: Option<String>,
+    pub deny_warnings: Option<bool>,
+    pub max_errors: Option<usize>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FormatConfig {
+    pub indent_width: Option<usize>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ReplConfig {
+    pub prompt: Option<String>,
+    pub prelude: Option<PathBuf>,
+}
+
+/// Limits and capabilities for the interpreter. `max-duration` is in seconds,
+/// and `capabilities` replaces the default of allowing every capability.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SandboxConfig {
+    pub max_steps: Option<usize>,
+    pub max_duration: Option<f64>,
+    pub max_call_depth: Option<usize>,
+    pub capabilities: Option<Vec<String>>,
+}
+
+impl Config {
+    pub fn load() -> Result<Self, String> {
+        let mut config = Config::default();
+        for path in [user_config_path(), project_config_path()]
+            .into_iter()
+            .flatten()
+        {
+            config.merge(Self::read(&path)?);
+        }
+        Ok(config)
+    }
+
+    fn read(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("could not read '{}': {}", path.display(), err))?;
+        toml::from_str(&contents).map_err(|err| format!("'{}': {}", path.display(), err))
+    }
+
+    fn merge(&mut self, other: Self) {
+        let diagnostics = other.diagnostics;
+        self.diagnostics.error_format = diagnostics
+            .error_format
+            .or(self.diagnostics.error_format.take());
+        self.diagnostics.color = diagnostics.color.or(self.diagnostics.color.take());
+        self.diagnostics.deny_warnings =
+            diagnostics.deny_warnings.or(self.diagnostics.deny_warnings);
+        self.diagnostics.max_errors = diagnostics.max_errors.or(self.diagnostics.max_errors);
+        self.warnings.extend(other.warnings);
+        self.format.indent_width = other.format.indent_width.or(self.format.indent_width);
+        self.repl.prompt = other.repl.prompt.or(self.repl.prompt.take());
+        self.repl.prelude = other.repl.prelude.or(self.repl.prelude.take());
+        let sandbox = other.sandbox;
+        self.sandbox.max_steps = sandbox.max_steps.or(self.sandbox.max_steps);
+        self.sandbox.max_duration = sandbox.max_duration.or(self.sandbox.max_duration);
+        self.sandbox.max_call_depth = sandbox.max_call_depth.or(self.sandbox.max_call_depth);
+        self.sandbox.capabilities = sandbox.capabilities.or(self.sandbox.capabilities.take());
+    }
+
+    pub fn prelude(&self) -> Option<PathBuf> {
+        match &self.repl.prelude {
+            Some(path) if path.as_os_str().is_empty() => None,
+            Some(path) => Some(path.clone()),
+            None => {
+                let path = PathBuf::from(env::var_os("HOME")?).join(".rlox/prelude.lox");
+                path.is_file().then_some(path)
+            }
+        }
+    }
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    let path = config_home.join("rlox").join("config.toml");
+    path.is_file().then_some(path)
+}
+
+fn project_config_path() -> Option<PathBuf> {
+    let dir = env::current_dir().ok()?;
+    dir.ancestors()
+        .map(|dir| dir.join("rlox.toml"))
+        .find(|path| path.is_file())
+}
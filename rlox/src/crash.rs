@@ -0,0 +1,67 @@
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    panic::{self, AssertUnwindSafe},
+};
+
+use crate::interpreter::Frame;
+
+const BUG_REPORT_URL: &str = "https://github.com/steinwand6/jlox/issues";
+
+thread_local! {
+    static GUARDED: Cell<bool> = const { Cell::new(false) };
+    static PANIC_MESSAGE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if GUARDED.get() {
+            PANIC_MESSAGE.set(Some(info.to_string()));
+        } else {
+            default_hook(info);
+        }
+    }));
+}
+
+pub(crate) fn catch<T>(f: impl FnOnce() -> T) -> Result<T, Box<dyn Any + Send>> {
+    let guarded = GUARDED.replace(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    GUARDED.set(guarded);
+    result
+}
+
+pub(crate) fn resume(
+    payload: Box<dyn Any + Send>,
+    source_name: Option<&str>,
+    frames: &[Frame],
+) -> ! {
+    if let Some(message) = PANIC_MESSAGE.take() {
+        report(&message, source_name, frames);
+    }
+    panic::resume_unwind(payload)
+}
+
+fn report(message: &str, source_name: Option<&str>, frames: &[Frame]) {
+    let source_name = source_name.unwrap_or("<input>");
+    eprintln!(
+        "error: rlox {} crashed. This is a bug in the interpreter, not in your program.\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    eprintln!("{}\n", message);
+    if let Some(frame) = frames.last() {
+        eprintln!("while executing {}:{}", source_name, frame.line);
+        eprintln!("Lox call stack:");
+        for (i, frame) in frames.iter().rev().enumerate() {
+            eprintln!(
+                "  #{} {} at {}:{}",
+                i, frame.function, source_name, frame.line
+            );
+        }
+        eprintln!();
+    }
+    eprintln!(
+        "Please report this at {} with the script and command that triggered it.",
+        BUG_REPORT_URL
+    );
+}
@@ -0,0 +1,354 @@
+use std::{
+    collections::BTreeSet,
+    io,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    debugger::{evaluate_source, Mode, Stepping},
+    interpreter::{DebugHook, Interpreter},
+    sync::{Lock, Shared},
+    transport::{read_message, write_message},
+};
+
+const THREAD_ID: i64 = 1;
+const VARIABLES_REFERENCE: i64 = 1;
+
+pub(crate) struct Connection {
+    messages: Lock<Receiver<Value>>,
+    seq: Lock<i64>,
+}
+
+impl Connection {
+    pub fn stdio() -> Shared<Self> {
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stdin = io::stdin().lock();
+            while let Some(message) = read_message(&mut stdin) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        Shared::new(Self {
+            messages: Lock::new(messages),
+            seq: Lock::new(1),
+        })
+    }
+
+    pub fn recv(&self) -> Option<Value> {
+        self.messages.borrow().recv().ok()
+    }
+
+    fn try_recv(&self) -> Option<Value> {
+        self.messages.borrow().try_recv().ok()
+    }
+
+    fn send(&self, mut message: Value) {
+        let mut seq = self.seq.borrow_mut();
+        message["seq"] = json!(*seq);
+        *seq += 1;
+        write_message(&message);
+    }
+
+    pub fn respond(&self, request: &Value, body: Value) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": true,
+            "command": request["command"],
+            "body": body,
+        }));
+    }
+
+    pub fn respond_error(&self, request: &Value, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": false,
+            "command": request["command"],
+            "message": message,
+        }));
+    }
+
+    pub fn event(&self, event: &str, body: Value) {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }));
+    }
+}
+
+pub(crate) struct Launch {
+    pub program: String,
+    stop_on_entry: bool,
+}
+
+pub(crate) fn configure(
+    connection: &Connection,
+    breakpoints: &mut BTreeSet<usize>,
+) -> Option<Launch> {
+    let mut launch = None;
+    let mut configured = false;
+    while !configured || launch.is_none() {
+        let request = connection.recv()?;
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                connection.respond(
+                    &request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsEvaluateForHovers": true,
+                    }),
+                );
+                connection.event("initialized", json!({}));
+            }
+            "launch" => {
+                let arguments = &request["arguments"];
+                let Some(program) = arguments["program"].as_str() else {
+                    connection.respond_error(&request, "Missing 'program' in launch arguments.");
+                    continue;
+                };
+                launch = Some(Launch {
+                    program: program.into(),
+                    stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+                });
+                connection.respond(&request, json!({}));
+            }
+            "setBreakpoints" => set_breakpoints(connection, &request, breakpoints),
+            "configurationDone" => {
+                configured = true;
+                connection.respond(&request, json!({}));
+            }
+            "threads" => respond_threads(connection, &request),
+            "disconnect" => {
+                connection.respond(&request, json!({}));
+                return None;
+            }
+            _ => connection.respond_error(&request, "Unsupported request."),
+        }
+    }
+    launch
+}
+
+pub(crate) fn finish(connection: &Connection, exit_code: i32) {
+    connection.event("exited", json!({ "exitCode": exit_code }));
+    connection.event("terminated", json!({}));
+    while let Some(request) = connection.recv() {
+        match request["command"].as_str().unwrap_or_default() {
+            "disconnect" => {
+                connection.respond(&request, json!({}));
+                return;
+            }
+            "threads" => respond_threads(connection, &request),
+            _ => connection.respond_error(&request, "The program has terminated."),
+        }
+    }
+}
+
+fn set_breakpoints(connection: &Connection, request: &Value, breakpoints: &mut BTreeSet<usize>) {
+    let lines: Vec<usize> = match request["arguments"]["breakpoints"].as_array() {
+        Some(requested) => requested
+            .iter()
+            .filter_map(|breakpoint| breakpoint["line"].as_u64())
+            .map(|line| line as usize)
+            .collect(),
+        None => vec![],
+    };
+    breakpoints.clear();
+    breakpoints.extend(&lines);
+    let verified: Vec<Value> = lines
+        .iter()
+        .map(|line| json!({ "verified": true, "line": line }))
+        .collect();
+    connection.respond(request, json!({ "breakpoints": verified }));
+}
+
+fn respond_threads(connection: &Connection, request: &Value) {
+    connection.respond(
+        request,
+        json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+    );
+}
+
+enum Action {
+    Stay,
+    Resume,
+    Disconnect,
+}
+
+pub(crate) struct DapDebugger {
+    connection: Shared<Connection>,
+    program: String,
+    stepping: Stepping,
+    reason: &'static str,
+}
+
+impl DapDebugger {
+    pub fn new(
+        connection: Shared<Connection>,
+        launch: Launch,
+        breakpoints: BTreeSet<usize>,
+    ) -> Self {
+        let mode = if launch.stop_on_entry {
+            Mode::Step
+        } else {
+            Mode::Continue
+        };
+        let mut stepping = Stepping::new(mode);
+        stepping.breakpoints = breakpoints;
+        Self {
+            connection,
+            program: launch.program,
+            stepping,
+            reason: "entry",
+        }
+    }
+
+    fn handle(&mut self, request: &Value, interpreter: &mut Interpreter, stopped: bool) -> Action {
+        let connection = self.connection.clone();
+        let arguments = &request["arguments"];
+        let depth = interpreter.frames().len();
+        match request["command"].as_str().unwrap_or_default() {
+            "setBreakpoints" => {
+                set_breakpoints(&connection, request, &mut self.stepping.breakpoints)
+            }
+            "threads" => respond_threads(&connection, request),
+            "pause" => {
+                self.stepping.mode = Mode::Step;
+                self.reason = "pause";
+                connection.respond(request, json!({}));
+            }
+            "disconnect" => {
+                connection.respond(request, json!({}));
+                return Action::Disconnect;
+            }
+            _ if !stopped => connection.respond_error(request, "The program is running."),
+            "stackTrace" => {
+                let frames: Vec<Value> = interpreter
+                    .frames()
+                    .iter()
+                    .rev()
+                    .enumerate()
+                    .map(|(id, frame)| {
+                        json!({
+                            "id": id,
+                            "name": frame.function,
+                            "line": frame.line,
+                            "column": 1,
+                            "source": { "path": self.program },
+                        })
+                    })
+                    .collect();
+                connection.respond(
+                    request,
+                    json!({ "stackFrames": frames, "totalFrames": frames.len() }),
+                );
+            }
+            "scopes" => {
+                let scopes = if arguments["frameId"].as_i64() == Some(0) {
+                    json!([{
+                        "name": "Locals",
+                        "variablesReference": VARIABLES_REFERENCE,
+                        "expensive": false,
+                    }])
+                } else {
+                    json!([])
+                };
+                connection.respond(request, json!({ "scopes": scopes }));
+            }
+            "variables" => {
+                let variables: Vec<Value> =
+                    if arguments["variablesReference"].as_i64() == Some(VARIABLES_REFERENCE) {
+                        interpreter
+                            .bindings()
+                            .iter()
+                            .map(|(name, value)| {
+                                json!({
+                                    "name": name,
+                                    "value": interpreter.stringify(value),
+                                    "type": value.type_name(),
+                                    "variablesReference": 0,
+                                })
+                            })
+                            .collect()
+                    } else {
+                        vec![]
+                    };
+                connection.respond(request, json!({ "variables": variables }));
+            }
+            "evaluate" => {
+                let expression = arguments["expression"].as_str().unwrap_or_default();
+                match evaluate_source(interpreter, expression) {
+                    Ok(result) => connection.respond(
+                        request,
+                        json!({ "result": result, "variablesReference": 0 }),
+                    ),
+                    Err(message) => connection.respond_error(request, &message),
+                }
+            }
+            "continue" => {
+                self.stepping.mode = Mode::Continue;
+                connection.respond(request, json!({ "allThreadsContinued": true }));
+                return Action::Resume;
+            }
+            command @ ("next" | "stepIn" | "stepOut") => {
+                self.stepping.mode = match command {
+                    "next" => Mode::Next(depth),
+                    "stepIn" => Mode::Step,
+                    _ => Mode::Next(depth - 1),
+                };
+                self.reason = "step";
+                connection.respond(request, json!({}));
+                return Action::Resume;
+            }
+            _ => connection.respond_error(request, "Unsupported request."),
+        }
+        Action::Stay
+    }
+}
+
+impl DebugHook for DapDebugger {
+    fn before_statement(&mut self, interpreter: &mut Interpreter, line: usize) -> bool {
+        while let Some(request) = self.connection.try_recv() {
+            if let Action::Disconnect = self.handle(&request, interpreter, false) {
+                return false;
+            }
+        }
+
+        let depth = interpreter.frames().len();
+        if !self.stepping.should_stop(line, depth) {
+            return true;
+        }
+        let reason = match self.stepping.mode {
+            Mode::Continue => "breakpoint",
+            Mode::Next(next_depth) if depth > next_depth => "breakpoint",
+            _ => self.reason,
+        };
+        self.connection.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        );
+        loop {
+            let Some(request) = self.connection.recv() else {
+                return false;
+            };
+            match self.handle(&request, interpreter, true) {
+                Action::Stay => (),
+                Action::Resume => return true,
+                Action::Disconnect => return false,
+            }
+        }
+    }
+
+    fn print(&mut self, text: &str) {
+        self.connection.event(
+            "output",
+            json!({ "category": "stdout", "output": format!("{}\n", text) }),
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+};
+
+use crate::{
+    interpreter::{DebugHook, Interpreter},
+    sync::{Lock, Shared},
+};
+
+pub const HELP: &str = "\
+break [FILE:]LINE  stop before running LINE
+step               run until the next statement
+next               run until the next statement in this function
+continue           run until the next breakpoint
+print EXPR         evaluate EXPR in the current scope
+backtrace          show the active function calls
+quit               stop the program";
+
+pub const REPLAY_HELP: &str = "\
+reverse-step       go back to the previous statement
+goto N             go to the Nth statement of the recording";
+
+pub(crate) enum Mode {
+    Step,
+    Next(usize),
+    Continue,
+}
+
+pub(crate) struct Stepping {
+    pub breakpoints: BTreeSet<usize>,
+    pub mode: Mode,
+    stopped_at: Option<(usize, usize)>,
+}
+
+impl Stepping {
+    pub fn new(mode: Mode) -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            mode,
+            stopped_at: None,
+        }
+    }
+
+    pub fn should_stop(&mut self, line: usize, depth: usize) -> bool {
+        let breakpoint = self.is_breakpoint(line) && self.stopped_at != Some((line, depth));
+        let stop = match self.mode {
+            Mode::Step => true,
+            Mode::Next(next_depth) => depth <= next_depth || breakpoint,
+            Mode::Continue => breakpoint,
+        };
+        if stop {
+            self.stopped_at = Some((line, depth));
+        } else if self.stopped_at.is_some_and(|(stopped, _)| stopped != line) {
+            self.stopped_at = None;
+        }
+        stop
+    }
+
+    pub fn is_breakpoint(&self, line: usize) -> bool {
+        self.breakpoints.contains(&line)
+    }
+}
+
+pub(crate) fn evaluate_source(interpreter: &mut Interpreter, src: &str) -> Result<String, String> {
+    match interpreter.eval_expr(src) {
+        Ok(value) => Ok(interpreter.stringify(&value)),
+        Err(err) => Err(err.diagnostics()[0].message.clone()),
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Rewind {
+    pub target: Option<usize>,
+    pub breakpoints: BTreeSet<usize>,
+}
+
+struct Replay {
+    statements: Vec<usize>,
+    position: usize,
+    target: Option<usize>,
+    rewound: bool,
+    diverged: bool,
+    rewind: Shared<Lock<Rewind>>,
+}
+
+pub struct Debugger {
+    source_name: String,
+    lines: Vec<String>,
+    stepping: Stepping,
+    replay: Option<Replay>,
+}
+
+impl Debugger {
+    pub fn new(source_name: &str, src: &str) -> Self {
+        Self {
+            source_name: source_name.into(),
+            lines: src.lines().map(String::from).collect(),
+            stepping: Stepping::new(Mode::Step),
+            replay: None,
+        }
+    }
+
+    pub(crate) fn replaying(mut self, statements: &[usize], rewind: Shared<Lock<Rewind>>) -> Self {
+        let Rewind {
+            target,
+            breakpoints,
+        } = std::mem::take(&mut *rewind.borrow_mut());
+        self.stepping.breakpoints = breakpoints;
+        self.replay = Some(Replay {
+            statements: statements.to_vec(),
+            position: 0,
+            target,
+            rewound: target.is_some(),
+            diverged: false,
+            rewind,
+        });
+        self
+    }
+
+    fn goto(&mut self, arg: &str) -> Option<bool> {
+        let Some(replay) = &mut self.replay else {
+            println!("Going back needs a recording; start the debugger with --replay.");
+            return None;
+        };
+        let total = replay.statements.len();
+        match arg.trim().parse::<usize>() {
+            Ok(target) if target == replay.position => {
+                println!("Already at statement {}.", target);
+                None
+            }
+            Ok(target) if target > replay.position && target <= total => {
+                replay.target = Some(target);
+                Some(true)
+            }
+            Ok(target) if target >= 1 && target < replay.position => {
+                *replay.rewind.borrow_mut() = Rewind {
+                    target: Some(target),
+                    breakpoints: self.stepping.breakpoints.clone(),
+                };
+                Some(false)
+            }
+            _ => {
+                println!(
+                    "Invalid statement '{}'; the recording has {} statements.",
+                    arg, total
+                );
+                None
+            }
+        }
+    }
+
+    fn add_breakpoint(&mut self, location: &str) {
+        let (file, line) = match location.rsplit_once(':') {
+            Some((file, line)) => (Some(file), line),
+            None => (None, location),
+        };
+        if let Some(file) = file {
+            if !self.source_name.ends_with(file) {
+                println!("No source file named '{}'.", file);
+                return;
+            }
+        }
+        match line.trim().parse::<usize>() {
+            Ok(line) if line >= 1 && line <= self.lines.len() => {
+                self.stepping.breakpoints.insert(line);
+                println!("Breakpoint at {}:{}", self.source_name, line);
+            }
+            _ => println!("Invalid line '{}'.", line),
+        }
+    }
+
+    fn print_expression(&self, interpreter: &mut Interpreter, src: &str) {
+        match evaluate_source(interpreter, src) {
+            Ok(value) => println!("{}", value),
+            Err(message) => println!("error: {}", message),
+        }
+    }
+
+    fn backtrace(&self, interpreter: &Interpreter) {
+        for (i, frame) in interpreter.frames().iter().rev().enumerate() {
+            println!(
+                "#{} {} at {}:{}",
+                i, frame.function, self.source_name, frame.line
+            );
+        }
+    }
+}
+
+impl DebugHook for Debugger {
+    fn before_statement(&mut self, interpreter: &mut Interpreter, line: usize) -> bool {
+        let depth = interpreter.frames().len();
+        if let Some(replay) = &mut self.replay {
+            replay.position += 1;
+            if !replay.diverged && replay.statements.get(replay.position - 1) != Some(&line) {
+                replay.diverged = true;
+                println!(
+                    "Replay diverged from the recording at statement {}.",
+                    replay.position
+                );
+            }
+            match replay.target {
+                Some(target) if replay.position < target => return true,
+                Some(_) => {
+                    replay.target = None;
+                    replay.rewound = false;
+                    self.stepping.mode = Mode::Step;
+                }
+                None => (),
+            }
+        }
+        if !self.stepping.should_stop(line, depth) {
+            return true;
+        }
+        let source_line = self.lines.get(line - 1).map_or("", |line| line.trim());
+        match &self.replay {
+            Some(replay) => println!(
+                "[{}/{}] {}:{}: {}",
+                replay.position,
+                replay.statements.len(),
+                self.source_name,
+                line,
+                source_line
+            ),
+            None => println!("{}:{}: {}", self.source_name, line, source_line),
+        }
+
+        let mut buffer = String::new();
+        loop {
+            buffer.clear();
+            print!("(debug) ");
+            io::stdout().flush().expect("flush");
+            if io::stdin().read_line(&mut buffer).expect("read line") == 0 {
+                return false;
+            }
+            let (command, arg) = match buffer.trim().split_once(' ') {
+                Some((command, arg)) => (command, arg.trim()),
+                None => (buffer.trim(), ""),
+            };
+            match command {
+                "" => (),
+                "break" | "b" => self.add_breakpoint(arg),
+                "step" | "s" => {
+                    self.stepping.mode = Mode::Step;
+                    return true;
+                }
+                "next" | "n" => {
+                    self.stepping.mode = Mode::Next(depth);
+                    return true;
+                }
+                "continue" | "c" => {
+                    self.stepping.mode = Mode::Continue;
+                    return true;
+                }
+                "reverse-step" | "rs" => {
+                    let position = self.replay.as_ref().map_or(0, |replay| replay.position);
+                    if position == 1 {
+                        println!("Already at the first statement.");
+                    } else if let Some(running) = self.goto(&(position.max(1) - 1).to_string()) {
+                        return running;
+                    }
+                }
+                "goto" | "g" => {
+                    if let Some(running) = self.goto(arg) {
+                        return running;
+                    }
+                }
+                "print" | "p" => self.print_expression(interpreter, arg),
+                "backtrace" | "bt" => self.backtrace(interpreter),
+                "quit" | "q" => return false,
+                "help" | "h" if self.replay.is_some() => println!("{}\n{}", HELP, REPLAY_HELP),
+                "help" | "h" => println!("{}", HELP),
+                command => println!("Unknown command '{}'. Type help for a list.", command),
+            }
+        }
+    }
+
+    fn print(&mut self, text: &str) {
+        if !self.replay.as_ref().is_some_and(|replay| replay.rewound) {
+            println!("{}", text);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    env,
+    fmt::Display,
+    io::{self, IsTerminal},
+};
+
+use crate::{ast_printer::json_string, error_codes::ErrorCode, token::Token};
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && io::stderr().is_terminal()
+            }
+        }
+    }
+}
+
+pub fn note(message: &str, color: bool) -> String {
+    if color {
+        format!("{}note{}: {}", BOLD, RESET, message)
+    } else {
+        format!("note: {}", message)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub source: Option<String>,
+    pub notes: Vec<String>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, self.severity, self.message
+        )
+    }
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: &str, line: usize, column: usize) -> Self {
+        Self {
+            severity,
+            code: ErrorCode::for_message(message).map(|error_code| error_code.code.into()),
+            message: message.into(),
+            line,
+            column,
+            length: 1,
+            source: None,
+            notes: vec![],
+        }
+    }
+
+    pub fn at_token(severity: Severity, message: &str, token: &Token) -> Self {
+        let length = token.lexeme.lines().next().unwrap_or("").chars().count();
+        let mut sites: Vec<(&Token, usize)> = vec![];
+        let mut expansion = token.expansion.as_deref();
+        while let Some(site) = expansion {
+            match sites.last_mut() {
+                Some((last, count)) if (last.line, last.column) == (site.line, site.column) => {
+                    *count += 1
+                }
+                _ => sites.push((site, 1)),
+            }
+            expansion = site.expansion.as_deref();
+        }
+        let notes = sites
+            .into_iter()
+            .map(|(site, count)| {
+                let note = format!(
+                    "in expansion of macro '{}' at line {}, column {}",
+                    site.lexeme, site.line, site.column
+                );
+                match count {
+                    1 => note,
+                    count => format!("{} ({} times)", note, count),
+                }
+            })
+            .collect();
+        Self {
+            length: length.max(1),
+            source: token.source.as_deref().map(String::from),
+            notes,
+            ..Self::new(severity, message, token.line, token.column)
+        }
+    }
+
+    pub fn with_code(self, code: &str) -> Self {
+        Self {
+            code: Some(code.into()),
+            ..self
+        }
+    }
+
+    pub fn to_json(&self, source_name: Option<&str>) -> String {
+        format!(
+            "{{\"severity\":\"{}\",\"code\":{},\"message\":{},\"file\":{},\"line\":{},\"column\":{},\"span\":{{\"line_start\":{},\"column_start\":{},\"line_end\":{},\"column_end\":{}}},\"notes\":[{}]}}",
+            self.severity,
+            self.code.as_deref().map_or("null".into(), json_string),
+            json_string(&self.message),
+            source_name.map_or("null".into(), json_string),
+            self.line,
+            self.column,
+            self.line,
+            self.column,
+            self.line,
+            self.column + self.length,
+            self.notes
+                .iter()
+                .map(|note| json_string(note))
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+
+    pub fn render(&self, source_name: Option<&str>, src: &str, color: bool) -> String {
+        let paint = |style: &str, text: &str| {
+            if color {
+                format!("{}{}{}", style, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+        let severity_color = match self.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        let severity = match &self.code {
+            Some(code) => format!("{}[{}]", self.severity, code),
+            None => self.severity.to_string(),
+        };
+        let mut output = format!(
+            "{}{}",
+            paint(severity_color, &severity),
+            paint(BOLD, &format!(": {}", self.message)),
+        );
+        output.push_str(&format!(
+            "\n{}{} {}:{}:{}",
+            gutter,
+            paint(BLUE, "-->"),
+            source_name.unwrap_or("<repl>"),
+            self.line,
+            self.column
+        ));
+
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| {
+                format!(
+                    "\n{} {} {}: {}",
+                    gutter,
+                    paint(BLUE, "="),
+                    paint(BOLD, "note"),
+                    note
+                )
+            })
+            .collect::<String>();
+        let Some(source_line) = src.lines().nth(self.line.saturating_sub(1)) else {
+            return output + &notes;
+        };
+        let source_line = source_line.replace('\t', " ");
+        let caret = format!(
+            "{}{}",
+            " ".repeat(self.column.saturating_sub(1)),
+            "^".repeat(self.length)
+        );
+        output.push_str(&format!("\n{} {}", gutter, paint(BLUE, "|")));
+        output.push_str(&format!(
+            "\n{} {} {}",
+            paint(BLUE, &line_number),
+            paint(BLUE, "|"),
+            source_line
+        ));
+        output.push_str(&format!(
+            "\n{} {} {}",
+            gutter,
+            paint(BLUE, "|"),
+            paint(severity_color, &caret)
+        ));
+        output + &notes
+    }
+}
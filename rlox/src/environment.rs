@@ -1,72 +1,313 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::collections::HashSet;
 
 use crate::{
+    shape::{Depth, InlineCache, Resolution, Shape},
+    suggestion::did_you_mean,
+    sync::{Lock, Shared},
     token::{Object, Token},
     LoxRuntimeError,
 };
 
 #[derive(Debug, PartialEq)]
 pub struct Environment {
-    values: HashMap<String, Object>,
-    enclosing: Option<Rc<RefCell<Environment>>>,
+    root: Shared<Shape>,
+    shape: Shared<Shape>,
+    values: Vec<Object>,
+    uninitialized: HashSet<String>,
+    enclosing: Option<Shared<Lock<Environment>>>,
 }
 
 impl Environment {
     pub fn new() -> Self {
+        let root = Shape::root();
         Self {
-            values: HashMap::new(),
+            shape: root.clone(),
+            root,
+            values: vec![],
+            uninitialized: HashSet::new(),
             enclosing: None,
         }
     }
 
-    pub fn new_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+    pub fn new_enclosing(enclosing: Shared<Lock<Environment>>) -> Self {
+        let root = enclosing.borrow().root.clone();
         Self {
-            values: HashMap::new(),
+            shape: root.clone(),
+            root,
+            values: vec![],
+            uninitialized: HashSet::new(),
             enclosing: Some(enclosing),
         }
     }
 
     pub fn define(&mut self, name: &str, value: &Object) {
-        self.values.insert(name.into(), value.clone());
+        self.uninitialized.remove(name);
+        self.insert(name, value.clone());
     }
 
-    pub fn get(&self, name: &Token) -> Result<Object, LoxRuntimeError> {
-        match self.values.get(&name.lexeme) {
-            Some(value) => Ok(value.clone()),
-            None => match &self.enclosing {
-                Some(enclosing) => enclosing.borrow().get(name),
-                None => Err(LoxRuntimeError(
-                    name.clone(),
-                    format!("Undefined variable '{}'.", name.lexeme),
-                )),
-            },
+    pub fn declare(&mut self, name: &str) {
+        self.insert(name, Object::None);
+        self.uninitialized.insert(name.into());
+    }
+
+    fn insert(&mut self, name: &str, value: Object) {
+        match self.shape.slot(name) {
+            Some(slot) => self.values[slot] = value,
+            None => {
+                self.shape = Shape::with(&self.shape, name);
+                self.values.push(value);
+            }
+        }
+    }
+
+    fn value(&self, name: &str) -> Option<&Object> {
+        self.shape.slot(name).map(|slot| &self.values[slot])
+    }
+
+    pub(crate) fn enclosing(&self) -> Option<Shared<Lock<Environment>>> {
+        self.enclosing.clone()
+    }
+
+    /// Points a copy of a scope at its own enclosing scope and values.
+    pub(crate) fn relocate(
+        &mut self,
+        enclosing: Option<Shared<Lock<Environment>>>,
+        mut copy: impl FnMut(&Object) -> Object,
+    ) {
+        self.enclosing = enclosing;
+        for value in &mut self.values {
+            *value = copy(value);
+        }
+    }
+
+    pub fn define_global(&mut self, name: &str, value: &Object) {
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().define_global(name, value),
+            None => self.define(name, value),
+        }
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get_global(name),
+            None => self.value(name).cloned(),
         }
     }
 
-    pub fn assign(&mut self, name: &Token, value: &Object) -> Result<(), LoxRuntimeError> {
-        if self.values.contains_key(&name.lexeme) {
-            self.values.insert(name.lexeme.clone(), value.clone());
-            return Ok(());
+    pub fn get(&self, name: &Token, cache: &InlineCache) -> Result<Object, LoxRuntimeError> {
+        let read = match cache.depth() {
+            Some(depth) => self.read_at(depth, &name.lexeme, cache),
+            None => self.read(&name.lexeme, cache),
+        };
+        match read {
+            Some(Some(value)) => Ok(value),
+            Some(None) => Err(LoxRuntimeError(
+                name.clone(),
+                format!(
+                    "Variable '{}' is used before it is initialized.",
+                    name.lexeme
+                ),
+            )),
+            None => Err(self.undefined(name)),
         }
-        if let Some(enclosing) = &mut self.enclosing {
-            enclosing.borrow_mut().assign(name, value)?;
-            return Ok(());
+    }
+
+    fn read(&self, name: &str, cache: &InlineCache) -> Option<Option<Object>> {
+        let read = match &*cache.get() {
+            Some(resolution) => self.read_cached(name, &resolution.shapes, resolution.slot),
+            None => None,
+        };
+        match read {
+            Some(read) => Some(read),
+            None => self.resolve(name).map(|resolution| {
+                let read = self.read_cached(name, &resolution.shapes, resolution.slot);
+                cache.set(resolution);
+                read.expect("resolved variable")
+            }),
+        }
+    }
+
+    fn read_at(&self, depth: Depth, name: &str, cache: &InlineCache) -> Option<Option<Object>> {
+        match (depth, &self.enclosing) {
+            (Depth::Local(0), _) | (Depth::Global, None) => (),
+            (Depth::Local(_), None) => return None,
+            (depth, Some(enclosing)) => {
+                return enclosing.borrow().read_at(depth.outer(), name, cache);
+            }
         }
-        Err(LoxRuntimeError(
-            name.clone(),
-            format!("Undefined variable '{}'.", name.lexeme),
-        ))
+        let slot = self.local_slot(name, cache)?;
+        if !self.uninitialized.is_empty() && self.uninitialized.contains(name) {
+            return Some(None);
+        }
+        Some(Some(self.values[slot].clone()))
+    }
+
+    fn local_slot(&self, name: &str, cache: &InlineCache) -> Option<usize> {
+        if let Some(slot) = cache.slot_in(&self.shape) {
+            return Some(slot);
+        }
+        let slot = self.shape.slot(name)?;
+        cache.set(Resolution {
+            shapes: vec![self.shape.clone()],
+            slot,
+        });
+        Some(slot)
+    }
+
+    fn resolve(&self, name: &str) -> Option<Resolution> {
+        let mut shapes = vec![self.shape.clone()];
+        if let Some(slot) = self.shape.slot(name) {
+            return Some(Resolution { shapes, slot });
+        }
+        let mut resolution = self.enclosing.as_ref()?.borrow().resolve(name)?;
+        shapes.append(&mut resolution.shapes);
+        Some(Resolution {
+            shapes,
+            ..resolution
+        })
+    }
+
+    fn read_cached(
+        &self,
+        name: &str,
+        shapes: &[Shared<Shape>],
+        slot: usize,
+    ) -> Option<Option<Object>> {
+        let (shape, rest) = shapes.split_first()?;
+        if !Shared::ptr_eq(&self.shape, shape) {
+            return None;
+        }
+        if !rest.is_empty() {
+            return self
+                .enclosing
+                .as_ref()?
+                .borrow()
+                .read_cached(name, rest, slot);
+        }
+        if !self.uninitialized.is_empty() && self.uninitialized.contains(name) {
+            return Some(None);
+        }
+        Some(Some(self.values[slot].clone()))
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Object> {
+        match self.value(name) {
+            Some(value) => Some(value.clone()),
+            None => self.enclosing.as_ref()?.borrow().lookup(name),
+        }
+    }
+
+    pub fn assign(
+        &mut self,
+        name: &Token,
+        value: &Object,
+        cache: &InlineCache,
+    ) -> Result<(), LoxRuntimeError> {
+        if let Some(depth) = cache.depth() {
+            if self.assign_at(depth, &name.lexeme, value, cache) {
+                return Ok(());
+            }
+            return Err(self.undefined(name));
+        }
+        if let Some(resolution) = &*cache.get() {
+            if self.assign_cached(&name.lexeme, value, &resolution.shapes, resolution.slot) {
+                return Ok(());
+            }
+        }
+        match self.resolve(&name.lexeme) {
+            Some(resolution) => {
+                self.assign_cached(&name.lexeme, value, &resolution.shapes, resolution.slot);
+                cache.set(resolution);
+                Ok(())
+            }
+            None => Err(self.undefined(name)),
+        }
+    }
+
+    fn assign_cached(
+        &mut self,
+        name: &str,
+        value: &Object,
+        shapes: &[Shared<Shape>],
+        slot: usize,
+    ) -> bool {
+        let Some((shape, rest)) = shapes.split_first() else {
+            return false;
+        };
+        if !Shared::ptr_eq(&self.shape, shape) {
+            return false;
+        }
+        if !rest.is_empty() {
+            return match &self.enclosing {
+                Some(enclosing) => enclosing
+                    .borrow_mut()
+                    .assign_cached(name, value, rest, slot),
+                None => false,
+            };
+        }
+        self.values[slot] = value.clone();
+        if !self.uninitialized.is_empty() {
+            self.uninitialized.remove(name);
+        }
+        true
+    }
+
+    fn assign_at(&mut self, depth: Depth, name: &str, value: &Object, cache: &InlineCache) -> bool {
+        match (depth, &self.enclosing) {
+            (Depth::Local(0), _) | (Depth::Global, None) => (),
+            (Depth::Local(_), None) => return false,
+            (depth, Some(enclosing)) => {
+                return enclosing
+                    .borrow_mut()
+                    .assign_at(depth.outer(), name, value, cache);
+            }
+        }
+        let Some(slot) = self.local_slot(name, cache) else {
+            return false;
+        };
+        self.values[slot] = value.clone();
+        if !self.uninitialized.is_empty() {
+            self.uninitialized.remove(name);
+        }
+        true
+    }
+
+    pub(crate) fn undefined(&self, name: &Token) -> LoxRuntimeError {
+        let bindings = self.bindings();
+        let message =
+            match did_you_mean(&name.lexeme, bindings.iter().map(|(name, _)| name.as_str())) {
+                Some(candidate) => format!(
+                    "Undefined variable '{}'. Did you mean '{}'?",
+                    name.lexeme, candidate
+                ),
+                None => format!("Undefined variable '{}'.", name.lexeme),
+            };
+        LoxRuntimeError(name.clone(), message)
     }
 
-    pub fn drop_enclosing(&mut self) {
-        self.enclosing = None;
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        let mut bindings = match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().bindings(),
+            None => vec![],
+        };
+        bindings.retain(|(name, _)| self.shape.slot(name).is_none());
+        bindings.extend(
+            self.shape
+                .names()
+                .map(|(name, slot)| (name.clone(), self.values[slot].clone())),
+        );
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
     }
 }
 
 impl Clone for Environment {
     fn clone(&self) -> Self {
         Self {
+            root: self.root.clone(),
+            shape: self.shape.clone(),
             values: self.values.clone(),
+            uninitialized: self.uninitialized.clone(),
             enclosing: self.enclosing.clone(),
         }
     }
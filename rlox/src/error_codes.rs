@@ -0,0 +1,673 @@
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub messages: &'static [&'static str],
+    pub explanation: &'static str,
+}
+
+impl ErrorCode {
+    pub fn for_message(message: &str) -> Option<&'static ErrorCode> {
+        ERROR_CODES.iter().find(|error_code| {
+            error_code
+                .messages
+                .iter()
+                .any(|template| matches_template(template, message))
+        })
+    }
+
+    pub fn find(code: &str) -> Option<&'static ErrorCode> {
+        ERROR_CODES
+            .iter()
+            .find(|error_code| error_code.code.eq_ignore_ascii_case(code))
+    }
+}
+
+fn matches_template(template: &str, message: &str) -> bool {
+    let mut parts = template.split("{}");
+    let Some(first) = parts.next() else {
+        return false;
+    };
+    let Some(mut rest) = message.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "E0001",
+        messages: &["Unexpected character {}.{}", "Unexpected characters {}.{}"],
+        explanation: "\
+The scanner found a character that does not start any Lox token. A run of
+such characters next to each other is reported once.
+
+Erroneous code example:
+
+    var price = 10 # 2;
+
+Lox has no `#` operator. Remove the character, or use `//` if a comment
+was intended:
+
+    var price = 10; // 2",
+    },
+    ErrorCode {
+        code: "E0002",
+        messages: &["Unterminated string.{}"],
+        explanation: "\
+A string literal was opened with `\"` but never closed. Strings may span
+several lines, so the error points at the opening quote.
+
+Erroneous code example:
+
+    print \"hello;
+
+Add the closing quote:
+
+    print \"hello\";",
+    },
+    ErrorCode {
+        code: "E0003",
+        messages: &["Unterminated block comment.{}"],
+        explanation: "\
+A block comment was opened with `/*` but never closed. Block comments
+nest, so every `/*` inside one needs its own `*/`.
+
+Erroneous code example:
+
+    /* outer /* inner */
+    print 1;
+
+Close each comment that was opened:
+
+    /* outer /* inner */ */
+    print 1;",
+    },
+    ErrorCode {
+        code: "E0101",
+        messages: &["Expect expression.{}"],
+        explanation: "\
+The parser expected an expression (a literal, a variable, a call, ...)
+but found something else.
+
+Erroneous code example:
+
+    var total = 1 + ;
+
+Complete the expression:
+
+    var total = 1 + 2;",
+    },
+    ErrorCode {
+        code: "E0102",
+        messages: &["Expect ';' after {}"],
+        explanation: "\
+Statements in Lox end with a semicolon.
+
+Erroneous code example:
+
+    print \"hello\"
+    var a = 1
+
+Terminate each statement with `;`:
+
+    print \"hello\";
+    var a = 1;",
+    },
+    ErrorCode {
+        code: "E0103",
+        messages: &["Expect ')' {}", "Expect '(' {}"],
+        explanation: "\
+Parentheses around a condition, a parameter list, an argument list or a
+grouping are not balanced.
+
+Erroneous code example:
+
+    if (a > b {
+        print a;
+    }
+
+Close the parenthesis:
+
+    if (a > b) {
+        print a;
+    }",
+    },
+    ErrorCode {
+        code: "E0104",
+        messages: &["Expect '}' {}", "Expect '{' {}"],
+        explanation: "\
+A block or function body is missing one of its braces.
+
+Erroneous code example:
+
+    fun greet() {
+        print \"hi\";
+
+Close the block:
+
+    fun greet() {
+        print \"hi\";
+    }",
+    },
+    ErrorCode {
+        code: "E0105",
+        messages: &["Expect {} name.{}"],
+        explanation: "\
+A declaration needs a name: `var`, `fun` and each parameter must be
+followed by an identifier, and `import` by a string naming the module.
+
+Erroneous code example:
+
+    var = 1;
+    fun (a) {}
+
+Name the declaration:
+
+    var count = 1;
+    fun double(a) {}",
+    },
+    ErrorCode {
+        code: "E0106",
+        messages: &["Invalid assignment target.{}"],
+        explanation: "\
+Only variables can appear on the left-hand side of `=`.
+
+Erroneous code example:
+
+    1 + a = 3;
+
+Assign to a variable instead:
+
+    a = 3 - 1;",
+    },
+    ErrorCode {
+        code: "E0107",
+        messages: &["Can't have more than 255 {}"],
+        explanation: "\
+Functions are limited to 255 parameters, and calls to 255 arguments.
+
+Group related values, or split the function into smaller ones.",
+    },
+    ErrorCode {
+        code: "E0108",
+        messages: &["Expect end of expression.{}"],
+        explanation: "\
+Input that must be a single expression (for example the argument of the
+REPL's `:type` command) continued after the expression ended.
+
+Erroneous code example:
+
+    :type 1 2
+
+Pass exactly one expression:
+
+    :type 1 + 2",
+    },
+    ErrorCode {
+        code: "E0109",
+        messages: &["Can't return from top-level code."],
+        explanation: "\
+A `return` statement appeared outside of any function body. Only function
+calls can be returned from; a script simply ends after its last statement.
+
+Erroneous code example:
+
+    print \"done\";
+    return;
+
+Remove the `return`, or move the code into a function:
+
+    fun main() {
+        print \"done\";
+        return;
+    }
+    main();",
+    },
+    ErrorCode {
+        code: "E0110",
+        messages: &["Duplicate parameter name '{}'."],
+        explanation: "\
+A function declared two parameters with the same name. The later one would
+hide the earlier, so the first argument could never be read.
+
+Erroneous code example:
+
+    fun add(a, a) { return a + a; }
+
+Give every parameter its own name:
+
+    fun add(a, b) { return a + b; }",
+    },
+    ErrorCode {
+        code: "E0111",
+        messages: &["Can't read local variable in its own initializer."],
+        explanation: "\
+A local variable was used inside the expression that initializes it. The
+new variable is not assigned yet at that point, so the read can only be a
+mistake, usually a typo for an outer variable of a similar name.
+
+Erroneous code example:
+
+    var a = 1;
+    {
+        var a = a + 1;
+    }
+
+Give the local variable a different name:
+
+    var a = 1;
+    {
+        var b = a + 1;
+    }",
+    },
+    ErrorCode {
+        code: "E0112",
+        messages: &[
+            "Expression too deeply nested.",
+            "Statement too deeply nested.",
+        ],
+        explanation: "\
+Expressions and statements can be nested at most 256 levels deep, counting
+parentheses, unary operators, blocks and the bodies of `if`, `while` and
+`fun`. Deeper code is almost always generated, and parsing it would
+exhaust the interpreter's stack.
+
+Erroneous code example:
+
+    print ((((((((((((( ... 1 ... ))))))))))))); // 300 levels
+
+Split the expression into smaller parts stored in variables.",
+    },
+    ErrorCode {
+        code: "E0113",
+        messages: &[
+            "Macro '{}' expects {} arguments but got {}.",
+            "Macro '{}' can only be called as a statement.",
+            "Macro expansion of '{}' is too deep.",
+            "Macro parameter '{}' is assigned, so its argument must be a variable.",
+        ],
+        explanation: "\
+A macro call could not be expanded. Macros are replaced by their body before
+the program runs, so a call must be a statement of its own, pass exactly one
+argument per parameter and pass a variable wherever the body assigns to the
+parameter. A macro that calls itself is expanded at most 64 levels deep.
+
+Erroneous code example:
+
+    macro swap(a, b) { var tmp = a; a = b; b = tmp; }
+    swap(1, 2);
+
+Pass variables that the body can assign to:
+
+    var x = 1;
+    var y = 2;
+    swap(x, y);",
+    },
+    ErrorCode {
+        code: "E0114",
+        messages: &["Can't use 'this' outside of a class."],
+        explanation: "\
+`this` refers to the instance a method was called on, so it can only be
+used inside the methods of a class (including functions nested in them).
+
+Erroneous code example:
+
+    fun describe() {
+        print this.name;
+    }
+
+Make the function a method of the class whose instances it describes:
+
+    class Pet {
+        describe() {
+            print this.name;
+        }
+    }",
+    },
+    ErrorCode {
+        code: "E0115",
+        messages: &[
+            "Can't use 'super' outside of a class.",
+            "Can't use 'super' in a class with no superclass.",
+        ],
+        explanation: "\
+`super.method` calls the superclass's version of a method, so it can only
+be used inside the methods of a class declared with `<`.
+
+Erroneous code example:
+
+    class Pet {
+        speak() {
+            super.speak();
+        }
+    }
+
+Give the class a superclass, or call the method directly:
+
+    class Pet < Animal {
+        speak() {
+            super.speak();
+        }
+    }",
+    },
+    ErrorCode {
+        code: "E0116",
+        messages: &["A class can't inherit from itself."],
+        explanation: "\
+The class after `<` must be a different class declared earlier.
+
+Erroneous code example:
+
+    class Pet < Pet {}
+
+Inherit from another class:
+
+    class Pet < Animal {}",
+    },
+    ErrorCode {
+        code: "E0117",
+        messages: &["Can't return a value from an initializer."],
+        explanation: "\
+`init` always returns the instance it initialized, so a `return` inside it
+can't give a value of its own. A bare `return;` is allowed and ends the
+initializer early.
+
+Erroneous code example:
+
+    class Point {
+        init(x) {
+            this.x = x;
+            return x;
+        }
+    }
+
+Leave the value off:
+
+    class Point {
+        init(x) {
+            this.x = x;
+            return;
+        }
+    }",
+    },
+    ErrorCode {
+        code: "E0118",
+        messages: &[
+            "Can't use 'break' outside of a loop.",
+            "Can't use 'continue' outside of a loop.",
+        ],
+        explanation: "\
+`break` leaves the innermost `while` or `for` loop and `continue` skips to
+its next iteration, so both have to appear inside the body of one. A function declared inside a loop body starts
+outside of any loop.
+
+Erroneous code example:
+
+    fun stop() {
+        break;
+    }
+
+Move the `break` into the loop, or `return` from the function instead:
+
+    while (true) {
+        break;
+    }",
+    },
+    ErrorCode {
+        code: "E0201",
+        messages: &["Undefined variable '{}'.{}"],
+        explanation: "\
+A variable was read or assigned before it was declared with `var` (or
+`fun`) in any enclosing scope.
+
+Erroneous code example:
+
+    print count;
+
+Declare the variable first:
+
+    var count = 0;
+    print count;",
+    },
+    ErrorCode {
+        code: "E0202",
+        messages: &["Operands must be two numbers or two strings.{}"],
+        explanation: "\
+`+` adds two numbers or concatenates two strings; it does not mix types.
+
+Erroneous code example:
+
+    print \"total: \" + 3;
+
+Only combine values of the same type:
+
+    print \"total: \" + \"3\";",
+    },
+    ErrorCode {
+        code: "E0203",
+        messages: &["Operand must be a number.{}"],
+        explanation: "\
+Unary `-` only works on numbers.
+
+Erroneous code example:
+
+    print -\"ten\";
+
+Negate a number instead:
+
+    print -10;",
+    },
+    ErrorCode {
+        code: "E0204",
+        messages: &["Operands must be numbers.{}"],
+        explanation: "\
+`-`, `*`, `/`, `%`, `<`, `<=`, `>` and `>=` only work on numbers.
+
+Erroneous code example:
+
+    print \"b\" > \"a\";
+
+Compare numbers instead:
+
+    print 2 > 1;",
+    },
+    ErrorCode {
+        code: "E0205",
+        messages: &["Expected {} arguments but got {}."],
+        explanation: "\
+A function was called with a different number of arguments than it
+declares parameters.
+
+Erroneous code example:
+
+    fun add(a, b) { return a + b; }
+    print add(1);
+
+Pass one argument per parameter:
+
+    print add(1, 2);",
+    },
+    ErrorCode {
+        code: "E0206",
+        messages: &["Can only call functions and classes.{}"],
+        explanation: "\
+Call syntax `(...)` was used on a value that is not callable.
+
+Erroneous code example:
+
+    var name = \"lox\";
+    name();
+
+Only call functions (and classes):
+
+    fun name() { return \"lox\"; }
+    name();",
+    },
+    ErrorCode {
+        code: "E0207",
+        messages: &["'{}' is not permitted by the sandbox policy ({})."],
+        explanation: "\
+A native function was called, or a module imported from the file system,
+that needs a capability the interpreter's sandbox policy does not grant,
+such as reading standard input.
+
+Erroneous code example (with stdin disabled):
+
+    var line = readLine();
+
+Either avoid the native function, or have the host allow the capability
+in its `InterpreterOptions`.",
+    },
+    ErrorCode {
+        code: "E0208",
+        messages: &[
+            "Execution exceeded the step limit of {} statements.",
+            "Execution exceeded the time limit of {}.",
+            "String length exceeds the limit of {} bytes.",
+            "Stack overflow.",
+            "Execution was stopped by the resource meter.",
+        ],
+        explanation: "\
+The program ran into one of the resource limits configured for the
+interpreter: the number of executed statements, the wall-clock time, the
+call depth or the length of a string. It is also reported when a resource
+meter installed by the host refuses to let the program continue.
+
+Erroneous code example:
+
+    fun forever(n) { return forever(n + 1); }
+    forever(0);
+
+Make sure loops and recursion terminate, or have the host raise the limit
+in its `InterpreterOptions`.",
+    },
+    ErrorCode {
+        code: "E0209",
+        messages: &["Could not import '{}': {}"],
+        explanation: "\
+An `import` statement named a module that could not be found, read or
+parsed.
+
+Erroneous code example:
+
+    import \"missing\";
+
+Check the module name. By default modules are looked up relative to the
+current directory (or the project directory in project mode), with a
+`.lox` extension added when the name has none:
+
+    import \"util\"; // loads util.lox",
+    },
+    ErrorCode {
+        code: "E0210",
+        messages: &["Execution was cancelled."],
+        explanation: "\
+The host cancelled the running program through its cancellation handle,
+for example because a request timed out. Execution stops at the next
+statement boundary; the interpreter itself stays usable and can run
+further code.",
+    },
+    ErrorCode {
+        code: "E0211",
+        messages: &["Variable '{}' is used before it is initialized."],
+        explanation: "\
+In strict mode a variable declared without an initializer has no value
+until something is assigned to it, and reading it before then is an
+error instead of producing `nil`.
+
+Erroneous code example (with `--strict`):
+
+    var total;
+    print total;
+
+Initialize the variable when declaring it, or assign it before use:
+
+    var total = 0;
+    print total;",
+    },
+    ErrorCode {
+        code: "E0212",
+        messages: &["Division by zero."],
+        explanation: "\
+In strict mode dividing by zero, or taking a remainder by zero with `%`,
+is an error instead of producing infinity or NaN. Builds with decimal numbers, which have no infinity,
+always report it.
+
+Erroneous code example (with `--strict`):
+
+    print 1 / 0;
+
+Check the divisor before dividing:
+
+    var n = 0;
+    if (n != 0) print 1 / n;",
+    },
+    ErrorCode {
+        code: "E0213",
+        messages: &[
+            "Only instances have properties.{}",
+            "Only instances have fields.{}",
+        ],
+        explanation: "\
+A property was read or assigned with `.` on a value that is not an
+instance of a class. Numbers, strings, functions and classes themselves
+have no properties.
+
+Erroneous code example:
+
+    var name = \"lox\";
+    print name.length;
+
+Only use `.` on instances:
+
+    class Name {}
+    var name = Name();
+    name.length = 3;
+    print name.length;",
+    },
+    ErrorCode {
+        code: "E0214",
+        messages: &["Undefined property '{}'."],
+        explanation: "\
+A property was read from an instance that has neither a field nor a method
+of that name. Fields only exist once something has been assigned to them.
+
+Erroneous code example:
+
+    class Point {}
+    var p = Point();
+    print p.x;
+
+Assign the field before reading it:
+
+    var p = Point();
+    p.x = 1;
+    print p.x;",
+    },
+    ErrorCode {
+        code: "E0215",
+        messages: &["Superclass must be a class.{}"],
+        explanation: "\
+The name after `<` in a class declaration refers to a value that is not a
+class, so there are no methods to inherit.
+
+Erroneous code example:
+
+    var Animal = \"animal\";
+    class Pet < Animal {}
+
+Inherit from a class:
+
+    class Animal {}
+    class Pet < Animal {}",
+    },
+];
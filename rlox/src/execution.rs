@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+
+use crate::{
+    environment::Environment,
+    generate_ast::{ClassStmt, Expr, FunctionStmt, ImportStmt, Stmt, SuperExpr},
+    interpreter::{Frame, Interpreter, LoxRuntimeException, Progress},
+    shape::InlineCache,
+    sync::{Lock, Shared},
+    token::{Object, Token},
+    token_type::TokenType,
+    LoxRuntimeError,
+};
+
+// `Interpreter::interpret` walks the tree and keeps its place on the Rust
+// stack, so it can only stop once a top-level statement has run to the end.
+// An `Execution` lowers the same statements to a flat list of operations per
+// function and keeps the operands, the scopes and the calls in progress
+// itself. A step can then stop before any statement, however deep inside
+// loops, blocks and calls it is, and the next step carries on from there.
+
+enum Op {
+    /// Starts a statement, and is where a step stops once its budget is used.
+    Statement(Option<usize>),
+    Constant(Object),
+    Pop,
+    Variable(Token, InlineCache),
+    Assign(Token, InlineCache),
+    Super(SuperExpr),
+    Get(Token),
+    CheckInstance(Token),
+    Set(Token),
+    Interpolate(Token, usize),
+    Binary(Token),
+    Unary(Token),
+    /// Jumps over the right operand, keeping the left one, when it decides
+    /// the result.
+    Logical(TokenType, usize),
+    Jump(usize),
+    JumpIfFalse(usize),
+    Call(Token, usize),
+    ExpressionValue,
+    Print,
+    Var(Token, bool),
+    Function(Shared<FunctionStmt>),
+    Class(ClassStmt, Vec<Shared<FunctionStmt>>),
+    PushScope,
+    PopScope,
+    Return,
+    Import(ImportStmt),
+}
+
+type Code = Shared<[Op]>;
+
+#[derive(Default)]
+struct Lowering {
+    code: Vec<Op>,
+    scopes: usize,
+    loops: Vec<Loop>,
+}
+
+struct Loop {
+    scopes: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+impl Lowering {
+    fn lower(stmts: &[Stmt]) -> Code {
+        let mut lowering = Self::default();
+        for stmt in stmts {
+            lowering.stmt(stmt);
+        }
+        lowering.emit(Op::Constant(Object::None));
+        lowering.emit(Op::Return);
+        lowering.code.into()
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.code.push(op);
+        self.code.len() - 1
+    }
+
+    fn patch(&mut self, jump: usize) {
+        let target = self.code.len();
+        match &mut self.code[jump] {
+            Op::Jump(to) | Op::JumpIfFalse(to) | Op::Logical(_, to) => *to = target,
+            _ => unreachable!("only jumps are patched"),
+        }
+    }
+
+    /// Leaves the scopes opened inside the innermost loop and jumps out of
+    /// its body, to a target patched in once the loop is lowered.
+    fn leave_loop(&mut self) -> Option<usize> {
+        let scopes = self.loops.last()?.scopes;
+        for _ in scopes..self.scopes {
+            self.emit(Op::PopScope);
+        }
+        Some(self.emit(Op::Jump(0)))
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        self.emit(Op::Statement(stmt.line()));
+        match stmt {
+            Stmt::Expression(stmt) => {
+                self.expr(&stmt.expression);
+                self.emit(Op::ExpressionValue);
+            }
+            Stmt::If(stmt) => {
+                self.expr(&stmt.condition);
+                let else_jump = self.emit(Op::JumpIfFalse(0));
+                self.stmt(&stmt.then_branch);
+                let end_jump = self.emit(Op::Jump(0));
+                self.patch(else_jump);
+                if let Some(else_branch) = &stmt.else_branch {
+                    self.stmt(else_branch);
+                }
+                self.patch(end_jump);
+            }
+            Stmt::While(stmt) => {
+                let start = self.code.len();
+                self.expr(&stmt.condition);
+                let exit_jump = self.emit(Op::JumpIfFalse(0));
+                self.loops.push(Loop {
+                    scopes: self.scopes,
+                    breaks: vec![],
+                    continues: vec![],
+                });
+                self.stmt(&stmt.body);
+                let body = self.loops.pop().expect("the loop being lowered");
+                for jump in body.continues {
+                    self.patch(jump);
+                }
+                if let Some(increment) = &stmt.increment {
+                    self.emit(Op::Statement(Some(stmt.keyword.line)));
+                    self.expr(increment);
+                    self.emit(Op::Pop);
+                }
+                self.emit(Op::Jump(start));
+                self.patch(exit_jump);
+                for jump in body.breaks {
+                    self.patch(jump);
+                }
+            }
+            Stmt::Function(stmt) => {
+                self.emit(Op::Function(Shared::new(stmt.clone())));
+            }
+            Stmt::Class(stmt) => {
+                if let Some(superclass) = &stmt.superclass {
+                    self.expr(superclass);
+                }
+                let methods = stmt.methods.iter().cloned().map(Shared::new).collect();
+                self.emit(Op::Class(stmt.clone(), methods));
+            }
+            Stmt::Block(stmt) => {
+                self.emit(Op::PushScope);
+                self.scopes += 1;
+                for stmt in &stmt.statements {
+                    self.stmt(stmt);
+                }
+                self.scopes -= 1;
+                self.emit(Op::PopScope);
+            }
+            Stmt::Break(_) => {
+                if let Some(jump) = self.leave_loop() {
+                    self.loops.last_mut().expect("a loop").breaks.push(jump);
+                }
+            }
+            Stmt::Continue(_) => {
+                if let Some(jump) = self.leave_loop() {
+                    self.loops.last_mut().expect("a loop").continues.push(jump);
+                }
+            }
+            Stmt::Return(stmt) => {
+                match &stmt.value {
+                    Some(value) => self.expr(value),
+                    None => {
+                        self.emit(Op::Constant(Object::None));
+                    }
+                }
+                self.emit(Op::Return);
+            }
+            Stmt::Print(stmt) => {
+                self.expr(&stmt.expression);
+                self.emit(Op::Print);
+            }
+            Stmt::Var(stmt) => {
+                if let Some(initializer) = &stmt.initializer {
+                    self.expr(initializer);
+                }
+                self.emit(Op::Var(stmt.name.clone(), stmt.initializer.is_some()));
+            }
+            Stmt::Import(stmt) => {
+                self.emit(Op::Import(stmt.clone()));
+            }
+            Stmt::Macro(_) => (),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign(expr) => {
+                self.expr(&expr.value);
+                self.emit(Op::Assign(expr.name.clone(), expr.cache.clone()));
+            }
+            Expr::Binary(expr) => {
+                self.expr(&expr.left);
+                self.expr(&expr.right);
+                self.emit(Op::Binary(expr.operator.clone()));
+            }
+            Expr::Call(expr) => {
+                self.expr(&expr.callee);
+                for argument in &expr.arguments {
+                    self.expr(argument);
+                }
+                self.emit(Op::Call(expr.paren.clone(), expr.arguments.len()));
+            }
+            Expr::Get(expr) => {
+                self.expr(&expr.object);
+                self.emit(Op::Get(expr.name.clone()));
+            }
+            Expr::Grouping(expr) => self.expr(&expr.expression),
+            Expr::Interpolation(expr) => {
+                for part in &expr.parts {
+                    self.expr(part);
+                }
+                self.emit(Op::Interpolate(expr.quote.clone(), expr.parts.len()));
+            }
+            Expr::Literal(expr) => {
+                self.emit(Op::Constant(expr.value.clone()));
+            }
+            Expr::Logical(expr) => {
+                self.expr(&expr.left);
+                let jump = self.emit(Op::Logical(expr.operator.token_type, 0));
+                self.expr(&expr.right);
+                self.patch(jump);
+            }
+            Expr::Set(expr) => {
+                self.expr(&expr.object);
+                self.emit(Op::CheckInstance(expr.name.clone()));
+                self.expr(&expr.value);
+                self.emit(Op::Set(expr.name.clone()));
+            }
+            Expr::Super(expr) => {
+                self.emit(Op::Super(expr.clone()));
+            }
+            Expr::This(expr) => {
+                self.emit(Op::Variable(expr.keyword.clone(), expr.cache.clone()));
+            }
+            Expr::Unary(expr) => {
+                self.expr(&expr.right);
+                self.emit(Op::Unary(expr.operator.clone()));
+            }
+            Expr::Variable(expr) => {
+                self.emit(Op::Variable(expr.name.clone(), expr.cache.clone()));
+            }
+        }
+    }
+}
+
+enum CallKind {
+    Script,
+    /// A call to a function, carrying the instance an initializer returns.
+    Function(Option<Object>),
+    Module,
+}
+
+struct CallFrame {
+    code: Code,
+    pc: usize,
+    kind: CallKind,
+    /// The caller's environment, restored on return.
+    environment: Shared<Lock<Environment>>,
+    stack: usize,
+    scopes: usize,
+}
+
+pub struct Execution {
+    script: Option<Code>,
+    calls: Vec<CallFrame>,
+    stack: Vec<Object>,
+    scopes: Vec<Shared<Lock<Environment>>>,
+    functions: HashMap<usize, (Shared<FunctionStmt>, Code)>,
+    suspended: Option<(Shared<Lock<Environment>>, Vec<Frame>)>,
+}
+
+impl Execution {
+    pub(crate) fn new(stmts: Vec<Stmt>) -> Self {
+        Self {
+            script: Some(Lowering::lower(&stmts)),
+            calls: vec![],
+            stack: vec![],
+            scopes: vec![],
+            functions: HashMap::new(),
+            suspended: None,
+        }
+    }
+
+    pub(crate) fn run(
+        &mut self,
+        interpreter: &mut Interpreter,
+        statements: usize,
+    ) -> Result<Progress, LoxRuntimeError> {
+        if let Some(code) = self.script.take() {
+            interpreter.start_budget();
+            let environment = interpreter.environment();
+            self.enter(interpreter, code, CallKind::Script, environment);
+        }
+        let Some(script) = self.calls.first() else {
+            return Ok(Progress::Finished);
+        };
+        let environment = script.environment.clone();
+        let frames = interpreter.frames().len();
+        // Between steps the interpreter is left in the script's environment,
+        // so it can be inspected and used as if nothing were running.
+        if let Some((current, suspended)) = self.suspended.take() {
+            interpreter.replace_environment(current);
+            interpreter.frames_mut().extend(suspended);
+        }
+        let target = interpreter.executed_statements() + statements;
+        match self.execute(interpreter, target) {
+            Ok(Progress::Running) => {
+                let current = interpreter.replace_environment(environment);
+                let suspended = interpreter.frames_mut().split_off(frames);
+                self.suspended = Some((current, suspended));
+                Ok(Progress::Running)
+            }
+            Ok(Progress::Finished) => Ok(Progress::Finished),
+            Err(exception) => {
+                interpreter.replace_environment(environment);
+                interpreter.frames_mut().truncate(frames);
+                self.calls.clear();
+                self.stack.clear();
+                self.scopes.clear();
+                match exception {
+                    LoxRuntimeException::Err(err) => Err(err),
+                    _ => Ok(Progress::Finished),
+                }
+            }
+        }
+    }
+
+    fn execute(
+        &mut self,
+        interpreter: &mut Interpreter,
+        target: usize,
+    ) -> Result<Progress, LoxRuntimeException> {
+        loop {
+            let call = self.calls.last_mut().expect("a call in progress");
+            let (code, pc) = (call.code.clone(), call.pc);
+            call.pc += 1;
+            match &code[pc] {
+                Op::Statement(line) => {
+                    if interpreter.executed_statements() >= target {
+                        self.jump(pc);
+                        return Ok(Progress::Running);
+                    }
+                    interpreter.begin_statement(*line)?;
+                }
+                Op::Constant(value) => self.stack.push(value.clone()),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Variable(name, cache) => {
+                    let value = interpreter.read_variable(name, cache)?;
+                    self.stack.push(value);
+                }
+                Op::Assign(name, cache) => {
+                    interpreter.assign_variable(name, self.peek(), cache)?;
+                }
+                Op::Super(expr) => {
+                    let method = interpreter.evaluate_super(expr)?;
+                    self.stack.push(method);
+                }
+                Op::Get(name) => {
+                    let object = self.pop();
+                    let value = interpreter.get_property(object, name)?;
+                    self.stack.push(value);
+                }
+                Op::CheckInstance(name) => {
+                    Interpreter::instance_for_set(self.peek(), name)?;
+                }
+                Op::Set(name) => {
+                    let value = self.pop();
+                    let instance = Interpreter::instance_for_set(&self.pop(), name)?;
+                    instance.set(&name.lexeme, value.clone());
+                    self.stack.push(value);
+                }
+                Op::Interpolate(quote, parts) => {
+                    let parts = self.stack.split_off(self.stack.len() - parts);
+                    let value = interpreter.interpolate(quote, &parts)?;
+                    self.stack.push(value);
+                }
+                Op::Binary(operator) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = interpreter.binary(operator, left, right)?;
+                    self.stack.push(value);
+                }
+                Op::Unary(operator) => {
+                    let value = interpreter.unary(operator, &self.pop())?;
+                    self.stack.push(value);
+                }
+                Op::Logical(operator, end) => {
+                    if Interpreter::is_truthy(self.peek()) == (*operator == TokenType::Or) {
+                        self.jump(*end);
+                    } else {
+                        self.pop();
+                    }
+                }
+                Op::Jump(to) => self.jump(*to),
+                Op::JumpIfFalse(to) => {
+                    if !Interpreter::is_truthy(&self.pop()) {
+                        self.jump(*to);
+                    }
+                }
+                Op::Call(paren, arguments) => {
+                    let arguments = self.stack.split_off(self.stack.len() - arguments);
+                    let callee = self.pop();
+                    self.call(interpreter, callee, paren, arguments)?;
+                }
+                Op::ExpressionValue => {
+                    let value = self.pop();
+                    interpreter.expression_value(value);
+                }
+                Op::Print => interpreter.print(&self.pop()),
+                Op::Var(name, initialized) => {
+                    let value = initialized.then(|| self.pop());
+                    interpreter.declare_variable(name, value);
+                }
+                Op::Function(declaration) => interpreter.declare_function(declaration.clone()),
+                Op::Class(stmt, methods) => {
+                    let superclass = stmt.superclass.is_some().then(|| self.pop());
+                    interpreter.declare_class(stmt, methods.clone(), superclass)?;
+                }
+                Op::PushScope => {
+                    let environment = Environment::new_enclosing(interpreter.environment());
+                    let previous =
+                        interpreter.replace_environment(Shared::new(Lock::new(environment)));
+                    self.scopes.push(previous);
+                }
+                Op::PopScope => {
+                    let previous = self.scopes.pop().expect("a scope to leave");
+                    interpreter.replace_environment(previous);
+                }
+                Op::Return => {
+                    let value = self.pop();
+                    if self.leave(interpreter, value) {
+                        return Ok(Progress::Finished);
+                    }
+                }
+                Op::Import(stmt) => {
+                    if let Some(stmts) = interpreter.load_module(stmt)? {
+                        interpreter
+                            .frames_mut()
+                            .push(Interpreter::module_frame(stmt));
+                        // A module's top level is global wherever it is imported from.
+                        let globals = interpreter.globals();
+                        self.enter(
+                            interpreter,
+                            Lowering::lower(&stmts),
+                            CallKind::Module,
+                            globals,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        interpreter: &mut Interpreter,
+        callee: Object,
+        paren: &Token,
+        arguments: Vec<Object>,
+    ) -> Result<(), LoxRuntimeException> {
+        let callable = Interpreter::callable(&callee, paren, arguments.len())?;
+        let fun = match callee {
+            Object::Fun(fun) => fun,
+            Object::Class(class) => {
+                let instance = interpreter.instantiate(&class);
+                match class.find_method("init") {
+                    Some(init) => Shared::new(init.bind(instance)),
+                    None => {
+                        self.stack.push(instance);
+                        return Ok(());
+                    }
+                }
+            }
+            _ => {
+                let value = match callable.call(interpreter, paren, arguments) {
+                    Ok(value) | Err(LoxRuntimeException::Return(value)) => value,
+                    Err(exception) => return Err(exception),
+                };
+                self.stack.push(value);
+                return Ok(());
+            }
+        };
+        interpreter.check_call_depth(paren)?;
+        interpreter.frames_mut().push(Frame {
+            function: fun.declaration.name.lexeme.clone(),
+            line: paren.line,
+        });
+        let environment = Interpreter::function_environment(&fun, &arguments);
+        let code = self.function_code(&fun.declaration);
+        let kind = CallKind::Function(fun.is_initializer.then(|| fun.this()));
+        self.enter(interpreter, code, kind, Shared::new(Lock::new(environment)));
+        Ok(())
+    }
+
+    /// Functions declared while stepping share their declaration, so each
+    /// body is only lowered once however many closures are made from it.
+    fn function_code(&mut self, declaration: &Shared<FunctionStmt>) -> Code {
+        let key = Shared::as_ptr(declaration) as usize;
+        let (_, code) = self
+            .functions
+            .entry(key)
+            .or_insert_with(|| (declaration.clone(), Lowering::lower(&declaration.body)));
+        code.clone()
+    }
+
+    fn enter(
+        &mut self,
+        interpreter: &mut Interpreter,
+        code: Code,
+        kind: CallKind,
+        environment: Shared<Lock<Environment>>,
+    ) {
+        let environment = interpreter.replace_environment(environment);
+        self.calls.push(CallFrame {
+            code,
+            pc: 0,
+            kind,
+            environment,
+            stack: self.stack.len(),
+            scopes: self.scopes.len(),
+        });
+    }
+
+    /// Returns from the innermost call, and reports whether that ended the
+    /// script.
+    fn leave(&mut self, interpreter: &mut Interpreter, value: Object) -> bool {
+        let call = self.calls.pop().expect("a call to return from");
+        interpreter.replace_environment(call.environment);
+        self.stack.truncate(call.stack);
+        self.scopes.truncate(call.scopes);
+        match call.kind {
+            CallKind::Script => return true,
+            CallKind::Function(this) => {
+                interpreter.frames_mut().pop();
+                self.stack.push(this.unwrap_or(value));
+            }
+            CallKind::Module => {
+                interpreter.frames_mut().pop();
+            }
+        }
+        false
+    }
+
+    fn jump(&mut self, to: usize) {
+        if let Some(call) = self.calls.last_mut() {
+            call.pc = to;
+        }
+    }
+
+    fn pop(&mut self) -> Object {
+        self.stack.pop().expect("an operand on the stack")
+    }
+
+    fn peek(&self) -> &Object {
+        self.stack.last().expect("an operand on the stack")
+    }
+}
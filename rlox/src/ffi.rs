@@ -0,0 +1,129 @@
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    ptr,
+};
+
+use crate::{Lox, LoxError};
+
+pub struct RloxHandle {
+    lox: Lox,
+    last_error: Option<CString>,
+}
+
+impl RloxHandle {
+    fn fail(&mut self, message: &str) {
+        self.last_error = CString::new(message.replace('\0', "")).ok();
+    }
+}
+
+fn exit_code(err: &LoxError) -> c_int {
+    match err {
+        LoxError::Compile(_) => 65,
+        LoxError::Runtime(_) => 70,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rlox_new() -> *mut RloxHandle {
+    Box::into_raw(Box::new(RloxHandle {
+        lox: Lox::new(),
+        last_error: None,
+    }))
+}
+
+/// # Safety
+///
+/// `handle` must be null or a pointer returned by `rlox_new` that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_free(handle: *mut RloxHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// # Safety
+///
+/// `handle` must be a live pointer returned by `rlox_new` and `source` a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_run(handle: *mut RloxHandle, source: *const c_char) -> c_int {
+    let Some(handle) = handle.as_mut() else {
+        return -1;
+    };
+    handle.last_error = None;
+    if source.is_null() {
+        handle.fail("source is null");
+        return -1;
+    }
+    let Ok(source) = CStr::from_ptr(source).to_str() else {
+        handle.fail("source is not valid UTF-8");
+        return -1;
+    };
+    match handle.lox.run_source(source) {
+        Ok(_) => 0,
+        Err(err) => {
+            handle.fail(&err.to_string());
+            exit_code(&err)
+        }
+    }
+}
+
+/// # Safety
+///
+/// `handle` must be a live pointer returned by `rlox_new` and `expr` a
+/// NUL-terminated string. A non-null result must be released with
+/// `rlox_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_eval(handle: *mut RloxHandle, expr: *const c_char) -> *mut c_char {
+    let Some(handle) = handle.as_mut() else {
+        return ptr::null_mut();
+    };
+    handle.last_error = None;
+    if expr.is_null() {
+        handle.fail("expression is null");
+        return ptr::null_mut();
+    }
+    let Ok(expr) = CStr::from_ptr(expr).to_str() else {
+        handle.fail("expression is not valid UTF-8");
+        return ptr::null_mut();
+    };
+    let interpreter = handle.lox.interpreter();
+    let result = interpreter
+        .eval_expr(expr)
+        .map(|value| interpreter.stringify(&value));
+    match result {
+        Ok(text) => CString::new(text.replace('\0', "")).map_or(ptr::null_mut(), CString::into_raw),
+        Err(err) => {
+            handle.fail(&err.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `handle` must be null or a live pointer returned by `rlox_new`. The
+/// returned string is owned by the handle and stays valid until the next
+/// call that takes the same handle.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_last_error(handle: *const RloxHandle) -> *const c_char {
+    match handle
+        .as_ref()
+        .and_then(|handle| handle.last_error.as_ref())
+    {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// # Safety
+///
+/// `string` must be null or a pointer returned by `rlox_eval` that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use crate::{
+    callable::LoxFunction,
+    class::{LoxClass, LoxInstance},
+    environment::Environment,
+    sync::{Lock, Shared},
+    token::Object,
+};
+
+// A forked interpreter starts from a copy of everything its globals can
+// reach, so neither side sees the other's assignments, fields or captured
+// variables. Functions, classes and instances can't change once they are
+// made, apart from the fields of an instance, so their copies are made first
+// and point at copies of environments that are still unfilled. Environments
+// and fields are filled in afterwards, which follows each cycle through a
+// closure or a field only once.
+
+#[derive(Default)]
+pub(crate) struct Fork {
+    environments: HashMap<usize, Shared<Lock<Environment>>>,
+    functions: HashMap<usize, Shared<LoxFunction>>,
+    classes: HashMap<usize, Shared<LoxClass>>,
+    instances: HashMap<usize, Shared<LoxInstance>>,
+    pending: Vec<Pending>,
+}
+
+enum Pending {
+    Environment(Shared<Lock<Environment>>, Shared<Lock<Environment>>),
+    Instance(Shared<LoxInstance>, Shared<LoxInstance>),
+}
+
+fn key<T: ?Sized>(shared: &Shared<T>) -> usize {
+    Shared::as_ptr(shared) as *const () as usize
+}
+
+impl Fork {
+    pub fn copy(globals: &Shared<Lock<Environment>>) -> Shared<Lock<Environment>> {
+        let mut fork = Self::default();
+        let copy = fork.environment(globals);
+        while let Some(pending) = fork.pending.pop() {
+            match pending {
+                Pending::Environment(original, copy) => {
+                    let enclosing = original.borrow().enclosing();
+                    let enclosing = enclosing.map(|enclosing| fork.environment(&enclosing));
+                    copy.borrow_mut()
+                        .relocate(enclosing, |value| fork.object(value));
+                }
+                Pending::Instance(original, copy) => {
+                    for (name, value) in original.fields() {
+                        copy.set(&name, fork.object(&value));
+                    }
+                }
+            }
+        }
+        copy
+    }
+
+    fn object(&mut self, value: &Object) -> Object {
+        match value {
+            Object::Fun(fun) => Object::Fun(self.function(fun)),
+            Object::Class(class) => Object::Class(self.class(class)),
+            Object::Instance(instance) => Object::Instance(self.instance(instance)),
+            value => value.clone(),
+        }
+    }
+
+    fn environment(
+        &mut self,
+        environment: &Shared<Lock<Environment>>,
+    ) -> Shared<Lock<Environment>> {
+        if let Some(copy) = self.environments.get(&key(environment)) {
+            return copy.clone();
+        }
+        let copy = Shared::new(Lock::new(environment.borrow().clone()));
+        self.environments.insert(key(environment), copy.clone());
+        self.pending
+            .push(Pending::Environment(environment.clone(), copy.clone()));
+        copy
+    }
+
+    fn function(&mut self, fun: &Shared<LoxFunction>) -> Shared<LoxFunction> {
+        if let Some(copy) = self.functions.get(&key(fun)) {
+            return copy.clone();
+        }
+        let copy = Shared::new(LoxFunction {
+            declaration: fun.declaration.clone(),
+            closure: self.environment(&fun.closure),
+            is_initializer: fun.is_initializer,
+        });
+        self.functions.insert(key(fun), copy.clone());
+        copy
+    }
+
+    fn class(&mut self, class: &Shared<LoxClass>) -> Shared<LoxClass> {
+        if let Some(copy) = self.classes.get(&key(class)) {
+            return copy.clone();
+        }
+        let superclass = class
+            .superclass
+            .as_ref()
+            .map(|superclass| self.class(superclass));
+        let methods = class
+            .methods()
+            .map(|(name, method)| (name.clone(), self.function(method)))
+            .collect();
+        let copy = Shared::new(LoxClass::new(&class.name, superclass, methods));
+        self.classes.insert(key(class), copy.clone());
+        copy
+    }
+
+    fn instance(&mut self, instance: &Shared<LoxInstance>) -> Shared<LoxInstance> {
+        if let Some(copy) = self.instances.get(&key(instance)) {
+            return copy.clone();
+        }
+        let copy = Shared::new(LoxInstance::new(self.class(&instance.class)));
+        self.instances.insert(key(instance), copy.clone());
+        self.pending
+            .push(Pending::Instance(instance.clone(), copy.clone()));
+        copy
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{token::Token, token_type::TokenType};
+
+pub struct Formatter {
+    indent_width: usize,
+    output: String,
+    depth: usize,
+    paren_depth: usize,
+    pending_newline: bool,
+    last_unary: bool,
+}
+
+impl Formatter {
+    pub fn new(indent_width: usize) -> Self {
+        Self {
+            indent_width,
+            output: String::new(),
+            depth: 0,
+            paren_depth: 0,
+            pending_newline: false,
+            last_unary: false,
+        }
+    }
+
+    pub fn format(mut self, tokens: &[Token]) -> String {
+        let mut previous: Option<&Token> = None;
+        let mut previous_code: Option<&Token> = None;
+
+        for (i, token) in tokens.iter().enumerate() {
+            let next = tokens[i + 1..]
+                .iter()
+                .find(|token| token.token_type != TokenType::Comment);
+            match token.token_type {
+                TokenType::Eof => break,
+                TokenType::Comment => {
+                    let comment = token.lexeme.trim_end();
+                    match previous {
+                        Some(previous)
+                            if !self.output.is_empty() && end_line(previous) == token.line =>
+                        {
+                            self.output.push(' ');
+                        }
+                        _ => self.start_line(token, previous),
+                    }
+                    self.output.push_str(comment);
+                    // A block comment can sit in front of code on the same line.
+                    self.pending_newline = comment.starts_with("//")
+                        || next.is_none_or(|next| next.line > end_line(token));
+                }
+                _ => {
+                    if token.token_type == TokenType::RightBrace {
+                        self.depth = self.depth.saturating_sub(1);
+                        if previous_code.map(|t| t.token_type) != Some(TokenType::LeftBrace) {
+                            self.pending_newline = true;
+                        }
+                    }
+                    if self.pending_newline {
+                        self.start_line(token, previous);
+                    } else if previous.is_some_and(|t| t.token_type == TokenType::Comment) {
+                        self.output.push(' ');
+                    } else if let Some(previous_code) = previous_code {
+                        if self.needs_space(previous_code, token) {
+                            self.output.push(' ');
+                        }
+                    }
+                    self.output.push_str(&token.lexeme);
+                    self.after_token(previous_code, token, next);
+                    previous_code = Some(token);
+                }
+            }
+            previous = Some(token);
+        }
+
+        if !self.output.is_empty() {
+            self.output.push('\n');
+        }
+        self.output
+    }
+
+    fn after_token(&mut self, previous: Option<&Token>, token: &Token, next: Option<&Token>) {
+        let next_type = next.map(|t| t.token_type);
+        self.last_unary = match token.token_type {
+            TokenType::Bang => true,
+            TokenType::Minus => !previous.is_some_and(ends_operand),
+            _ => false,
+        };
+        match token.token_type {
+            TokenType::LeftParen => self.paren_depth += 1,
+            TokenType::RightParen => self.paren_depth = self.paren_depth.saturating_sub(1),
+            TokenType::LeftBrace => {
+                self.depth += 1;
+                if next_type != Some(TokenType::RightBrace) {
+                    self.pending_newline = true;
+                }
+            }
+            TokenType::RightBrace
+                if !matches!(
+                    next_type,
+                    Some(TokenType::Else) | Some(TokenType::SemiColon) | Some(TokenType::Comma)
+                ) =>
+            {
+                self.pending_newline = true;
+            }
+            TokenType::SemiColon if self.paren_depth == 0 => self.pending_newline = true,
+            _ => (),
+        }
+    }
+
+    fn needs_space(&self, previous: &Token, token: &Token) -> bool {
+        match token.token_type {
+            TokenType::SemiColon | TokenType::Comma | TokenType::RightParen | TokenType::Dot => {
+                return false
+            }
+            TokenType::LeftParen if ends_operand(previous) => return false,
+            TokenType::RightBrace if previous.token_type == TokenType::LeftBrace => return false,
+            TokenType::String | TokenType::Interpolation if token.lexeme.starts_with('}') => {
+                return false
+            }
+            _ => (),
+        }
+        match previous.token_type {
+            TokenType::LeftParen | TokenType::Dot | TokenType::Interpolation => false,
+            TokenType::Bang | TokenType::Minus => !self.last_unary,
+            _ => true,
+        }
+    }
+
+    fn start_line(&mut self, token: &Token, previous: Option<&Token>) {
+        if !self.output.is_empty() {
+            self.output.push('\n');
+            if previous.is_some_and(|previous| token.line > end_line(previous) + 1) {
+                self.output.push('\n');
+            }
+        }
+        self.output
+            .push_str(&" ".repeat(self.depth * self.indent_width));
+        self.pending_newline = false;
+    }
+}
+
+fn ends_operand(token: &Token) -> bool {
+    matches!(
+        token.token_type,
+        TokenType::Identifier
+            | TokenType::String
+            | TokenType::Number
+            | TokenType::RightParen
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil
+            | TokenType::This
+            | TokenType::Super
+    )
+}
+
+fn end_line(token: &Token) -> usize {
+    token.line + token.lexeme.matches('\n').count()
+}
@@ -1,6 +1,10 @@
 use paste::paste;
 
-use crate::token::{Object, Token};
+use crate::{
+    jit::JitCache,
+    shape::InlineCache,
+    token::{Object, Token},
+};
 
 macro_rules! generate_ast {
     ($name:ident, [$( $varient:ident : {$($field:ident: $type:ty),*}),*]) => {
@@ -33,26 +37,76 @@ macro_rules! generate_ast {
 
 generate_ast!(Expr,
     [
-        Assign : {name: Token, value: Box<Expr>},
+        Assign : {name: Token, value: Box<Expr>, cache: InlineCache},
         Binary : {left: Box<Expr>, operator: Token, right: Box<Expr>},
         Call : {callee: Box<Expr>, paren: Token, arguments: Vec<Expr>},
+        Get : {object: Box<Expr>, name: Token},
         Grouping : {expression: Box<Expr>},
+        Interpolation : {quote: Token, parts: Vec<Expr>},
         Literal : {value: Object},
         Logical : {left: Box<Expr>, operator: Token, right: Box<Expr>},
+        Set : {object: Box<Expr>, name: Token, value: Box<Expr>},
+        Super : {keyword: Token, method: Token, cache: InlineCache},
+        This : {keyword: Token, cache: InlineCache},
         Unary : {operator: Token, right: Box<Expr>},
-        Variable: {name: Token}
+        Variable: {name: Token, cache: InlineCache}
     ]
 );
 
 generate_ast!(Stmt,
     [
         Block : {statements: Vec<Stmt>},
+        Break : {keyword: Token},
+        Class : {name: Token, superclass: Option<Expr>, methods: Vec<FunctionStmt>},
+        Continue : {keyword: Token},
         Expression : {expression: Expr},
-        Function : {name: Token, params: Vec<Token>, body: Vec<Stmt>},
-        If : {condition: Expr, then_branch: Box<Stmt>, else_branch: Option<Box<Stmt>>},
-        Print : {expression: Expr},
+        Function : {name: Token, params: Vec<Token>, body: Vec<Stmt>, jit: JitCache},
+        If : {keyword: Token, condition: Expr, then_branch: Box<Stmt>, else_branch: Option<Box<Stmt>>},
+        Print : {keyword: Token, expression: Expr},
         Return : {_keyword: Token, value: Option<Expr>},
-        While : {condition: Expr, body: Box<Stmt>},
-        Var : {name: Token, initializer: Expr}
+        While : {keyword: Token, condition: Expr, body: Box<Stmt>, increment: Option<Box<Expr>>},
+        Var : {name: Token, initializer: Option<Expr>},
+        Import : {keyword: Token, name: Token},
+        Macro : {name: Token, params: Vec<Token>, body: Vec<Stmt>}
     ]
 );
+
+impl Expr {
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Expr::Assign(expr) => Some(expr.name.line),
+            Expr::Binary(expr) => expr.left.line().or(Some(expr.operator.line)),
+            Expr::Call(expr) => expr.callee.line().or(Some(expr.paren.line)),
+            Expr::Get(expr) => expr.object.line().or(Some(expr.name.line)),
+            Expr::Grouping(expr) => expr.expression.line(),
+            Expr::Interpolation(expr) => Some(expr.quote.line),
+            Expr::Literal(_) => None,
+            Expr::Logical(expr) => expr.left.line().or(Some(expr.operator.line)),
+            Expr::Set(expr) => expr.object.line().or(Some(expr.name.line)),
+            Expr::Super(expr) => Some(expr.keyword.line),
+            Expr::This(expr) => Some(expr.keyword.line),
+            Expr::Unary(expr) => Some(expr.operator.line),
+            Expr::Variable(expr) => Some(expr.name.line),
+        }
+    }
+}
+
+impl Stmt {
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Stmt::Block(_) => None,
+            Stmt::Break(stmt) => Some(stmt.keyword.line),
+            Stmt::Class(stmt) => Some(stmt.name.line),
+            Stmt::Continue(stmt) => Some(stmt.keyword.line),
+            Stmt::Expression(stmt) => stmt.expression.line(),
+            Stmt::Function(stmt) => Some(stmt.name.line),
+            Stmt::If(stmt) => Some(stmt.keyword.line),
+            Stmt::Print(stmt) => Some(stmt.keyword.line),
+            Stmt::Return(stmt) => Some(stmt._keyword.line),
+            Stmt::While(stmt) => Some(stmt.keyword.line),
+            Stmt::Var(stmt) => Some(stmt.name.line),
+            Stmt::Import(stmt) => Some(stmt.keyword.line),
+            Stmt::Macro(stmt) => Some(stmt.name.line),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{
+    bindings::{BindingKind, BindingTable},
+    parser::Parser,
+    scanner::Scanner,
+    token_type::TokenType,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SemanticKind {
+    Keyword,
+    String,
+    Number,
+    Comment,
+    Class,
+    Function,
+    Parameter,
+    Variable,
+}
+
+impl SemanticKind {
+    pub const ALL: [SemanticKind; 8] = [
+        SemanticKind::Keyword,
+        SemanticKind::String,
+        SemanticKind::Number,
+        SemanticKind::Comment,
+        SemanticKind::Class,
+        SemanticKind::Function,
+        SemanticKind::Parameter,
+        SemanticKind::Variable,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SemanticKind::Keyword => "keyword",
+            SemanticKind::String => "string",
+            SemanticKind::Number => "number",
+            SemanticKind::Comment => "comment",
+            SemanticKind::Class => "class",
+            SemanticKind::Function => "function",
+            SemanticKind::Parameter => "parameter",
+            SemanticKind::Variable => "variable",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SemanticToken {
+    pub kind: SemanticKind,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+pub fn semantic_tokens(src: &str) -> Vec<SemanticToken> {
+    let mut scanner = Scanner::new(src);
+    let tokens = scanner.scan_tokens();
+    let bindings = Parser::new(tokens.iter().flatten().collect())
+        .parse()
+        .ok()
+        .map(|stmts| BindingTable::new(&stmts));
+
+    let mut scanner = Scanner::with_comments(src);
+    scanner
+        .scan_tokens()
+        .iter()
+        .flatten()
+        .filter_map(|token| {
+            let kind = match token.token_type {
+                TokenType::String | TokenType::Interpolation => SemanticKind::String,
+                TokenType::Number => SemanticKind::Number,
+                TokenType::Comment => SemanticKind::Comment,
+                TokenType::Identifier => {
+                    let declaration = bindings
+                        .as_ref()
+                        .and_then(|bindings| bindings.declaration_at(token.line, token.column));
+                    match declaration.map(|declaration| declaration.kind) {
+                        Some(BindingKind::Class) => SemanticKind::Class,
+                        Some(BindingKind::Function) => SemanticKind::Function,
+                        Some(BindingKind::Parameter) => SemanticKind::Parameter,
+                        _ => SemanticKind::Variable,
+                    }
+                }
+                token_type if (TokenType::And..=TokenType::While).contains(&token_type) => {
+                    SemanticKind::Keyword
+                }
+                _ => return None,
+            };
+            Some(SemanticToken {
+                kind,
+                line: token.line,
+                column: token.column,
+                length: token.lexeme.chars().count(),
+            })
+        })
+        .collect()
+}
@@ -1,23 +1,73 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    any::Any,
+    collections::HashSet,
+    io::{self, BufReader, Write},
+    mem,
+    ops::DerefMut,
+    time::Instant,
+};
 
 use crate::{
+    callable::{LoxCallable, LoxFunction, LoxFunctionHandle},
+    cancel::CancellationHandle,
+    class::{LoxClass, LoxInstance},
+    clock::{Clock, ClockSource, SystemClock},
+    diagnostic::Diagnostic,
     environment::Environment,
+    execution::Execution,
+    fork::Fork,
     generate_ast::{
-        AssignExpr, BinaryExpr, CallExpr, Expr, FunctionStmt, GroupingExpr, LiteralExpr,
-        LogicalExpr, Stmt, UnaryExpr,
+        AssignExpr, BinaryExpr, CallExpr, ClassStmt, Expr, FunctionStmt, GetExpr, GroupingExpr,
+        ImportStmt, InterpolationExpr, LiteralExpr, LogicalExpr, SetExpr, Stmt, SuperExpr,
+        UnaryExpr,
     },
+    macros,
+    module::{FileSystemLoader, ModuleLoader},
+    native::{self, Input, NativeFunction, NativeResult},
+    native_class::NativeClass,
+    number::{Number, Numeric},
+    options::{InterpreterOptions, Usage},
+    parser::Parser,
+    preprocess::Preprocessed,
+    random::{Random, RandomSource, SeededRandom},
+    resolver,
+    shape::{Depth, InlineCache},
+    sync::{Lock, MaybeSend, MaybeSync, Meter, Preprocessor, PrintHook, Reader, Shared, Writer},
     token::{Object, Token},
     token_type::TokenType,
-    LoxRuntimeError,
+    userdata, LoxError, LoxRuntimeError, LoxScanError,
 };
 
 pub enum LoxRuntimeException {
     Err(LoxRuntimeError),
     Return(Object),
+    Break,
+    Continue,
+    Halt,
+}
+
+pub trait DebugHook: MaybeSync {
+    fn before_statement(&mut self, interpreter: &mut Interpreter, line: usize) -> bool;
+
+    fn print(&mut self, text: &str) {
+        println!("{}", text);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Progress {
+    Running,
+    Finished,
+}
+
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub function: String,
+    pub line: usize,
 }
 
 impl LoxRuntimeException {
-    fn throw_err(token: Token, message: &str) -> Result<Object, Self> {
+    pub(crate) fn throw_err(token: Token, message: &str) -> Result<Object, Self> {
         Err(Self::Err(LoxRuntimeError(token, message.into())))
     }
 }
@@ -29,30 +79,333 @@ impl From<LoxRuntimeError> for LoxRuntimeException {
 }
 
 pub struct Interpreter {
-    environment: Environment,
+    environment: Shared<Lock<Environment>>,
+    globals: Shared<Lock<Environment>>,
+    executed_statements: usize,
+    frames: Vec<Frame>,
+    hook: Option<Box<dyn DebugHook>>,
+    print_hook: Option<Shared<Lock<Box<PrintHook>>>>,
+    output: Shared<Lock<Box<Writer>>>,
+    input: Input,
+    clock: ClockSource,
+    random: RandomSource,
+    options: InterpreterOptions,
+    budget_start: usize,
+    deadline: Option<Instant>,
+    module_loader: Shared<dyn ModuleLoader>,
+    preprocessor: Option<Shared<Preprocessor>>,
+    imported: HashSet<String>,
+    cancellation: CancellationHandle,
+    meter: Option<Box<Meter>>,
+    meter_interval: usize,
+    allocated: usize,
+    last_value: Object,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Self::with_options(InterpreterOptions::new())
+    }
+
+    pub fn with_options(options: InterpreterOptions) -> Self {
+        let globals = Shared::new(Lock::new(Environment::new()));
+        let mut interpreter = Self {
+            environment: globals.clone(),
+            globals,
+            executed_statements: 0,
+            frames: vec![Frame {
+                function: "<script>".into(),
+                line: 0,
+            }],
+            hook: None,
+            print_hook: None,
+            output: Shared::new(Lock::new(Box::new(io::stdout()))),
+            input: Shared::new(Lock::new(Box::new(BufReader::new(io::stdin())))),
+            clock: Shared::new(Lock::new(Box::new(SystemClock))),
+            random: Shared::new(Lock::new(Box::new(SeededRandom::from_time()))),
+            options,
+            budget_start: 0,
+            deadline: None,
+            module_loader: Shared::new(FileSystemLoader::new(".")),
+            preprocessor: None,
+            imported: HashSet::new(),
+            cancellation: CancellationHandle::new(),
+            meter: None,
+            meter_interval: 0,
+            allocated: 0,
+            last_value: Object::None,
+        };
+        interpreter.define_builtins();
+        interpreter
+    }
+
+    /// Starts an interpreter with a copy of everything this one's globals can
+    /// reach, writing to the same output.
+    pub fn fork(&self) -> Self {
+        let globals = Fork::copy(&self.globals);
         Self {
-            environment: Environment::new(),
+            environment: globals.clone(),
+            globals,
+            executed_statements: 0,
+            frames: vec![Frame {
+                function: "<script>".into(),
+                line: 0,
+            }],
+            hook: None,
+            print_hook: self.print_hook.clone(),
+            output: self.output.clone(),
+            input: self.input.clone(),
+            clock: self.clock.clone(),
+            random: self.random.clone(),
+            options: self.options.clone(),
+            budget_start: 0,
+            deadline: None,
+            module_loader: self.module_loader.clone(),
+            preprocessor: self.preprocessor.clone(),
+            imported: self.imported.clone(),
+            cancellation: CancellationHandle::new(),
+            meter: None,
+            meter_interval: 0,
+            allocated: 0,
+            last_value: Object::None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.globals = Shared::new(Lock::new(Environment::new()));
+        self.environment = self.globals.clone();
+        self.imported.clear();
+        self.define_builtins();
+    }
+
+    fn define_builtins(&mut self) {
+        if !self.options.stdlib {
+            return;
+        }
+        let read_line = native::read_line(&self.input);
+        let read_all = native::read_all(&self.input);
+        self.define("readLine", Object::Native(Shared::new(read_line)));
+        self.define("readAll", Object::Native(Shared::new(read_all)));
+        let clock = native::clock(&self.clock);
+        let random = native::random(&self.random);
+        self.define("clock", Object::Native(Shared::new(clock)));
+        self.define("random", Object::Native(Shared::new(random)));
+        self.define("len", Object::Native(Shared::new(native::len())));
+        self.define("charAt", Object::Native(Shared::new(native::char_at())));
+        self.define(
+            "substring",
+            Object::Native(Shared::new(native::substring())),
+        );
+        self.define("isNan", Object::Native(Shared::new(native::is_nan())));
+        self.define("isFinite", Object::Native(Shared::new(native::is_finite())));
+    }
+
+    pub fn with_output(output: impl Write + MaybeSync + 'static) -> Self {
+        let mut interpreter = Self::new();
+        interpreter.set_output(Box::new(output));
+        interpreter
+    }
+
+    pub fn set_output(&mut self, output: Box<Writer>) {
+        self.output = Shared::new(Lock::new(output));
+    }
+
+    pub(crate) fn output(&self) -> impl DerefMut<Target = Box<Writer>> + '_ {
+        self.output.borrow_mut()
+    }
+
+    pub fn set_options(&mut self, options: InterpreterOptions) {
+        self.options = options;
+    }
+
+    pub fn options(&self) -> &InterpreterOptions {
+        &self.options
+    }
+
+    pub(crate) fn start_budget(&mut self) {
+        self.budget_start = self.executed_statements;
+        self.allocated = 0;
+        self.deadline = self
+            .options
+            .max_duration
+            .map(|duration| Instant::now() + duration);
+    }
+
+    pub fn set_input(&mut self, input: Box<Reader>) {
+        *self.input.borrow_mut() = input;
+    }
+
+    pub(crate) fn swap_io(
+        &mut self,
+        input: Box<Reader>,
+        output: Box<Writer>,
+    ) -> (Box<Reader>, Box<Writer>) {
+        let input = std::mem::replace(&mut *self.input.borrow_mut(), input);
+        let output = std::mem::replace(&mut *self.output.borrow_mut(), output);
+        (input, output)
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        *self.clock.borrow_mut() = clock;
+    }
+
+    pub fn set_random(&mut self, random: Box<dyn Random>) {
+        *self.random.borrow_mut() = random;
+    }
+
+    pub(crate) fn read_line(&mut self, buffer: &mut String) -> io::Result<usize> {
+        self.input.borrow_mut().read_line(buffer)
+    }
+
+    pub fn set_meter(&mut self, interval: usize, meter: Option<Box<Meter>>) {
+        self.meter_interval = interval.max(1);
+        self.meter = meter;
+    }
+
+    pub fn cancellation_handle(&self) -> CancellationHandle {
+        self.cancellation.clone()
+    }
+
+    pub fn set_module_loader(&mut self, module_loader: Box<dyn ModuleLoader>) {
+        self.module_loader = module_loader.into();
+    }
+
+    pub fn set_preprocessor(&mut self, preprocessor: Option<Box<Preprocessor>>) {
+        self.preprocessor = preprocessor.map(Shared::from);
+    }
+
+    pub(crate) fn scan(
+        &self,
+        source_name: Option<&str>,
+        source: &str,
+    ) -> Vec<Result<Token, LoxScanError>> {
+        match &self.preprocessor {
+            Some(preprocessor) => preprocessor(source).scan(source_name),
+            None => Preprocessed::new(source).scan(source_name),
         }
     }
 
-    pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<(), LoxRuntimeError> {
+    pub fn set_hook(&mut self, hook: Option<Box<dyn DebugHook>>) {
+        self.hook = hook;
+    }
+
+    pub fn set_print_hook(&mut self, print_hook: Option<Box<PrintHook>>) {
+        self.print_hook = print_hook.map(|print_hook| Shared::new(Lock::new(print_hook)));
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn executed_statements(&self) -> usize {
+        self.executed_statements
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(statements = stmts.len()))
+    )]
+    pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<Object, LoxRuntimeError> {
+        self.start_budget();
+        self.last_value = Object::None;
         for stmt in stmts {
-            if let Err(LoxRuntimeException::Err(err)) = self.execute_stmt(&stmt) {
-                return Err(err);
+            match self.execute_stmt(&stmt) {
+                Err(LoxRuntimeException::Err(err)) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(line = err.0.line, message = %err.1, "runtime error");
+                    return Err(err);
+                }
+                Err(LoxRuntimeException::Halt) => break,
+                _ => (),
             }
         }
 
-        Ok(())
+        Ok(mem::replace(&mut self.last_value, Object::None))
+    }
+
+    pub fn step(
+        &mut self,
+        execution: &mut Execution,
+        statements: usize,
+    ) -> Result<Progress, LoxRuntimeError> {
+        execution.run(self, statements)
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Object, LoxRuntimeError> {
+        self.start_budget();
+        match self.evaluate_expr(expr) {
+            Ok(value) | Err(LoxRuntimeException::Return(value)) => Ok(value),
+            Err(LoxRuntimeException::Err(err)) => Err(err),
+            Err(
+                LoxRuntimeException::Break
+                | LoxRuntimeException::Continue
+                | LoxRuntimeException::Halt,
+            ) => Ok(Object::None),
+        }
+    }
+
+    pub fn eval_expr(&mut self, source: &str) -> Result<Object, LoxError> {
+        let tokens = self.scan(None, source);
+        let diagnostics: Vec<Diagnostic> = tokens
+            .iter()
+            .filter_map(|token| token.as_ref().err())
+            .map(LoxScanError::diagnostic)
+            .collect();
+        if !diagnostics.is_empty() {
+            return Err(LoxError::Compile(diagnostics));
+        }
+        let mut parser = Parser::new(tokens.iter().flatten().collect());
+        let expr = parser
+            .parse_expression()
+            .map_err(|err| LoxError::Compile(vec![err.diagnostic()]))?;
+        self.evaluate(&expr)
+            .map_err(|err| LoxError::Runtime(err.diagnostic()))
+    }
+
+    pub fn define(&mut self, name: &str, value: Object) {
+        self.environment.borrow_mut().define(name, &value);
+    }
+
+    pub fn set_global(&mut self, name: &str, value: impl Into<Object>) {
+        self.environment
+            .borrow_mut()
+            .define_global(name, &value.into());
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        self.environment.borrow().get_global(name)
+    }
+
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Object]) -> NativeResult + MaybeSync + 'static,
+    ) {
+        let native = NativeFunction::new(name, arity, function);
+        self.define(name, Object::Native(Shared::new(native)));
+    }
+
+    pub fn define_class<T: Any + MaybeSend>(&mut self, class: NativeClass<T>) {
+        let native = class.into_constructor();
+        self.define(&native.name.clone(), Object::Native(Shared::new(native)));
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<LoxFunctionHandle> {
+        let callable = self.environment.borrow().lookup(name)?.callable()?;
+        Some(LoxFunctionHandle::new(callable))
+    }
+
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        self.environment.borrow().bindings()
     }
 
     fn execute_stmt(&mut self, stmt: &Stmt) -> Result<(), LoxRuntimeException> {
+        self.begin_statement(stmt.line())?;
         match stmt {
             Stmt::Expression(stmt) => {
-                self.evaluate_expr(&stmt.expression)?;
+                let value = self.evaluate_expr(&stmt.expression)?;
+                self.expression_value(value);
             }
             Stmt::If(stmt) => {
                 if Self::is_truthy(&self.evaluate_expr(&stmt.condition)?) {
@@ -63,26 +416,34 @@ impl Interpreter {
             }
             Stmt::While(stmt) => {
                 while Self::is_truthy(&self.evaluate_expr(&stmt.condition)?) {
-                    self.execute_stmt(&stmt.body)?;
+                    match self.execute_stmt(&stmt.body) {
+                        Ok(()) | Err(LoxRuntimeException::Continue) => (),
+                        Err(LoxRuntimeException::Break) => break,
+                        Err(exception) => return Err(exception),
+                    }
+                    if let Some(increment) = &stmt.increment {
+                        // A `for` loop's increment runs as a statement of its own on the
+                        // line of the `for` keyword.
+                        self.begin_statement(Some(stmt.keyword.line))?;
+                        self.evaluate_expr(increment)?;
+                    }
                 }
             }
-            Stmt::Function(stmt) => {
-                let fun = Object::Fun(Box::new(stmt.clone()), self.environment.clone());
-                self.environment.define(&stmt.name.lexeme, &fun);
+            Stmt::Function(stmt) => self.declare_function(Shared::new(stmt.clone())),
+            Stmt::Class(stmt) => {
+                let superclass = match &stmt.superclass {
+                    Some(expr) => Some(self.evaluate_expr(expr)?),
+                    None => None,
+                };
+                let methods = stmt.methods.iter().cloned().map(Shared::new).collect();
+                self.declare_class(stmt, methods, superclass)?;
             }
             Stmt::Block(stmt) => {
-                let previous = Rc::new(RefCell::new(self.environment.clone()));
-                {
-                    let previous_ref = previous.clone();
-                    self.environment = Environment::new_enclosing(previous_ref);
-                    for s in &stmt.statements {
-                        self.execute_stmt(s)?;
-                    }
-                }
-                self.environment.drop_enclosing();
-                let previous = Rc::try_unwrap(previous).unwrap().into_inner();
-                self.environment = previous;
+                let environment = Environment::new_enclosing(self.environment.clone());
+                self.execute_block(&stmt.statements, environment)?;
             }
+            Stmt::Break(_) => return Err(LoxRuntimeException::Break),
+            Stmt::Continue(_) => return Err(LoxRuntimeException::Continue),
             Stmt::Return(stmt) => {
                 let value = match &stmt.value {
                     Some(expr) => self.evaluate_expr(expr)?,
@@ -92,25 +453,299 @@ impl Interpreter {
             }
             Stmt::Print(stmt) => {
                 let value = self.evaluate_expr(&stmt.expression)?;
-                println!("{}", self.strigify(&value));
+                self.print(&value);
             }
             Stmt::Var(stmt) => {
-                let value = self.evaluate_expr(&stmt.initializer)?;
-                self.environment.define(&stmt.name.lexeme, &value);
+                let value = match &stmt.initializer {
+                    Some(initializer) => Some(self.evaluate_expr(initializer)?),
+                    None => None,
+                };
+                self.declare_variable(&stmt.name, value);
+            }
+            Stmt::Import(stmt) => self.import(stmt)?,
+            Stmt::Macro(_) => (),
+        }
+        Ok(())
+    }
+
+    /// Counts a statement against the limits and reports it to the debug hook
+    /// before it runs.
+    pub(crate) fn begin_statement(
+        &mut self,
+        line: Option<usize>,
+    ) -> Result<(), LoxRuntimeException> {
+        self.executed_statements += 1;
+        self.check_limits()?;
+        if let Some(line) = line {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(line, "execute");
+            if let Some(frame) = self.frames.last_mut() {
+                frame.line = line;
+            }
+            if let Some(mut hook) = self.hook.take() {
+                let running = hook.before_statement(self, line);
+                self.hook = Some(hook);
+                if !running {
+                    return Err(LoxRuntimeException::Halt);
+                }
             }
         }
         Ok(())
     }
 
+    pub(crate) fn expression_value(&mut self, value: Object) {
+        if self.frames.len() == 1 {
+            self.last_value = value;
+        }
+    }
+
+    pub(crate) fn print(&mut self, value: &Object) {
+        if let Some(print_hook) = &self.print_hook {
+            (print_hook.borrow_mut())(value);
+            return;
+        }
+        let text = self.stringify(value);
+        match &mut self.hook {
+            Some(hook) => hook.print(&text),
+            None => writeln!(self.output.borrow_mut(), "{}", text).expect("write output"),
+        }
+    }
+
+    pub(crate) fn declare_variable(&mut self, name: &Token, value: Option<Object>) {
+        let mut environment = self.environment.borrow_mut();
+        match value {
+            Some(value) => environment.define(&name.lexeme, &value),
+            None if self.options.strict => environment.declare(&name.lexeme),
+            None => environment.define(&name.lexeme, &Object::None),
+        }
+    }
+
+    pub(crate) fn declare_function(&mut self, declaration: Shared<FunctionStmt>) {
+        self.allocated += mem::size_of::<LoxFunction>();
+        let name = declaration.name.lexeme.clone();
+        let fun = Object::Fun(Shared::new(LoxFunction {
+            declaration,
+            closure: self.environment.clone(),
+            is_initializer: false,
+        }));
+        self.environment.borrow_mut().define(&name, &fun);
+    }
+
+    pub(crate) fn declare_class(
+        &mut self,
+        stmt: &ClassStmt,
+        methods: Vec<Shared<FunctionStmt>>,
+        superclass: Option<Object>,
+    ) -> Result<(), LoxRuntimeException> {
+        let superclass = match superclass {
+            Some(Object::Class(superclass)) => Some(superclass),
+            Some(value) => {
+                let token = match &stmt.superclass {
+                    Some(Expr::Variable(variable)) => variable.name.clone(),
+                    _ => stmt.name.clone(),
+                };
+                let message = format!("Superclass must be a class. Got {}.", value.describe());
+                return Err(LoxRuntimeError(token, message).into());
+            }
+            None => None,
+        };
+        let closure = match &superclass {
+            Some(superclass) => {
+                let mut closure = Environment::new_enclosing(self.environment.clone());
+                closure.define("super", &Object::Class(superclass.clone()));
+                Shared::new(Lock::new(closure))
+            }
+            None => self.environment.clone(),
+        };
+        let methods = methods
+            .into_iter()
+            .map(|declaration| {
+                let name = declaration.name.lexeme.clone();
+                let fun = LoxFunction {
+                    is_initializer: name == "init",
+                    declaration,
+                    closure: closure.clone(),
+                };
+                (name, Shared::new(fun))
+            })
+            .collect();
+        self.allocated += mem::size_of::<LoxClass>();
+        let class = LoxClass::new(&stmt.name.lexeme, superclass, methods);
+        self.environment
+            .borrow_mut()
+            .define(&stmt.name.lexeme, &Object::Class(Shared::new(class)));
+        Ok(())
+    }
+
+    fn import(&mut self, stmt: &ImportStmt) -> Result<(), LoxRuntimeException> {
+        let Some(stmts) = self.load_module(stmt)? else {
+            return Ok(());
+        };
+        self.frames.push(Self::module_frame(stmt));
+        // A module's top level is global wherever it is imported from.
+        let previous = mem::replace(&mut self.environment, self.globals.clone());
+        let result = stmts.iter().try_for_each(|stmt| self.execute_stmt(stmt));
+        self.environment = previous;
+        self.frames.pop();
+        result
+    }
+
+    /// Reads and compiles an imported module, or returns `None` when it has
+    /// already been imported.
+    pub(crate) fn load_module(
+        &mut self,
+        stmt: &ImportStmt,
+    ) -> Result<Option<Vec<Stmt>>, LoxRuntimeError> {
+        let name = stmt.name.literal.to_string();
+        if let Some(capability) = self.module_loader.capability() {
+            if !self.options.allows(capability) {
+                return Err(LoxRuntimeError(
+                    stmt.keyword.clone(),
+                    format!(
+                        "'import' is not permitted by the sandbox policy ({}).",
+                        capability.name()
+                    ),
+                ));
+            }
+        }
+        let failed = |message: String| {
+            LoxRuntimeError(
+                stmt.name.clone(),
+                format!("Could not import '{}': {}", name, message),
+            )
+        };
+        let path = self.module_loader.resolve(&name).map_err(failed)?;
+        if !self.imported.insert(path.clone()) {
+            return Ok(None);
+        }
+        let src = self.module_loader.load(&path).map_err(failed)?;
+        let tokens = self.scan(Some(&path), &src);
+        if let Some(err) = tokens.iter().find_map(|token| token.as_ref().err()) {
+            return Err(failed(err.to_string()));
+        }
+        let stmts = Parser::new(tokens.iter().flatten().collect())
+            .parse()
+            .and_then(macros::expand)
+            .and_then(resolver::resolve)
+            .map_err(|errors| failed(errors[0].to_string()))?;
+        Ok(Some(stmts))
+    }
+
+    pub(crate) fn environment(&self) -> Shared<Lock<Environment>> {
+        self.environment.clone()
+    }
+
+    pub(crate) fn globals(&self) -> Shared<Lock<Environment>> {
+        self.globals.clone()
+    }
+
+    /// Reads a variable. Globals come from this interpreter's own scope rather
+    /// than the root of the closure's chain, which belongs to the interpreter
+    /// a forked function was declared in.
+    pub(crate) fn read_variable(
+        &self,
+        name: &Token,
+        cache: &InlineCache,
+    ) -> Result<Object, LoxRuntimeError> {
+        if cache.depth() != Some(Depth::Global) {
+            return self.environment.borrow().get(name, cache);
+        }
+        let read = self.globals.borrow().get(name, cache);
+        if read.is_err() && self.globals.borrow().lookup(&name.lexeme).is_none() {
+            return Err(self.environment.borrow().undefined(name));
+        }
+        read
+    }
+
+    pub(crate) fn assign_variable(
+        &self,
+        name: &Token,
+        value: &Object,
+        cache: &InlineCache,
+    ) -> Result<(), LoxRuntimeError> {
+        if cache.depth() != Some(Depth::Global) {
+            return self.environment.borrow_mut().assign(name, value, cache);
+        }
+        let assigned = self.globals.borrow_mut().assign(name, value, cache);
+        assigned.map_err(|_| self.environment.borrow().undefined(name))
+    }
+
+    pub(crate) fn replace_environment(
+        &mut self,
+        environment: Shared<Lock<Environment>>,
+    ) -> Shared<Lock<Environment>> {
+        mem::replace(&mut self.environment, environment)
+    }
+
+    pub(crate) fn frames_mut(&mut self) -> &mut Vec<Frame> {
+        &mut self.frames
+    }
+
+    pub(crate) fn module_frame(stmt: &ImportStmt) -> Frame {
+        Frame {
+            function: format!("<module {}>", stmt.name.literal),
+            line: stmt.keyword.line,
+        }
+    }
+
+    fn check_limits(&mut self) -> Result<(), LoxRuntimeError> {
+        let message = if self.cancellation.take() {
+            "Execution was cancelled.".into()
+        } else if self
+            .options
+            .max_steps
+            .is_some_and(|max_steps| self.executed_statements - self.budget_start > max_steps)
+        {
+            format!(
+                "Execution exceeded the step limit of {} statements.",
+                self.options.max_steps.unwrap_or_default()
+            )
+        } else if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() > deadline)
+        {
+            format!(
+                "Execution exceeded the time limit of {:?}.",
+                self.options.max_duration.unwrap_or_default()
+            )
+        } else if self.meter_vetoed() {
+            "Execution was stopped by the resource meter.".into()
+        } else {
+            return Ok(());
+        };
+        let line = self.frames.last().map_or(0, |frame| frame.line);
+        Err(LoxRuntimeError(
+            Token::new(TokenType::Identifier, String::new(), Object::None, line, 1),
+            message,
+        ))
+    }
+
+    fn meter_vetoed(&mut self) -> bool {
+        let statements = self.executed_statements - self.budget_start;
+        let usage = Usage {
+            statements,
+            allocated: self.allocated,
+        };
+        match &mut self.meter {
+            Some(meter) if statements.is_multiple_of(self.meter_interval) => !meter(&usage),
+            _ => false,
+        }
+    }
+
     fn evaluate_expr(&mut self, expr: &Expr) -> Result<Object, LoxRuntimeException> {
         let obj = match expr {
             Expr::Assign(expr) => self.evaluate_assign(expr)?,
             Expr::Binary(expr) => self.evaluate_binary(expr)?,
             Expr::Call(expr) => self.evaluate_call(expr)?,
+            Expr::Get(expr) => self.evaluate_get(expr)?,
+            Expr::Set(expr) => self.evaluate_set(expr)?,
+            Expr::Super(expr) => self.evaluate_super(expr)?,
+            Expr::This(expr) => self.read_variable(&expr.keyword, &expr.cache)?,
             Expr::Grouping(expr) => self.evaluate_grouping(expr)?,
+            Expr::Interpolation(expr) => self.evaluate_interpolation(expr)?,
             Expr::Literal(expr) => self.evaluate_literal(expr)?,
             Expr::Unary(expr) => self.evaluate_unary(expr)?,
-            Expr::Variable(expr) => self.environment.get(&expr.name)?,
+            Expr::Variable(expr) => self.read_variable(&expr.name, &expr.cache)?,
             Expr::Logical(expr) => self.evaluate_logical(expr)?,
         };
         Ok(obj)
@@ -118,52 +753,90 @@ impl Interpreter {
 
     fn evaluate_assign(&mut self, expr: &AssignExpr) -> Result<Object, LoxRuntimeException> {
         let value = self.evaluate_expr(&expr.value)?;
-        self.environment.assign(&expr.name, &value)?;
+        self.assign_variable(&expr.name, &value, &expr.cache)?;
         Ok(value)
     }
 
     fn evaluate_binary(&mut self, expr: &BinaryExpr) -> Result<Object, LoxRuntimeException> {
         let left = self.evaluate_expr(&expr.left)?;
         let right = self.evaluate_expr(&expr.right)?;
+        self.binary(&expr.operator, left, right)
+    }
 
-        match expr.operator.token_type {
+    pub(crate) fn binary(
+        &mut self,
+        operator: &Token,
+        left: Object,
+        right: Object,
+    ) -> Result<Object, LoxRuntimeException> {
+        match operator.token_type {
             TokenType::Plus => match (left, right) {
                 (Object::String(left), Object::String(right)) => {
+                    if let Some(max_length) = self.options.max_string_length {
+                        if left.len() + right.len() > max_length {
+                            return LoxRuntimeException::throw_err(
+                                operator.clone(),
+                                &format!(
+                                    "String length exceeds the limit of {} bytes.",
+                                    max_length
+                                ),
+                            );
+                        }
+                    }
+                    self.allocated += left.len() + right.len();
                     Ok(Object::String(format!("{}{}", left, right)))
                 }
                 (Object::Num(left), Object::Num(right)) => Ok(Object::Num(left + right)),
-                _ => LoxRuntimeException::throw_err(
-                    expr.operator.clone(),
-                    "Operands must be two numbers or two strings.",
+                (left, right) => LoxRuntimeException::throw_err(
+                    operator.clone(),
+                    &format!(
+                        "Operands must be two numbers or two strings. Got {} and {}.",
+                        left.describe(),
+                        right.describe()
+                    ),
                 ),
             },
             TokenType::Minus => {
-                let (a, b) = self.check_number_operands(&expr.operator, &left, &right)?;
+                let (a, b) = self.check_number_operands(operator, &left, &right)?;
                 Ok(Object::Num(a - b))
             }
             TokenType::Star => {
-                let (a, b) = self.check_number_operands(&expr.operator, &left, &right)?;
+                let (a, b) = self.check_number_operands(operator, &left, &right)?;
                 Ok(Object::Num(a * b))
             }
             TokenType::Slash => {
-                let (a, b) = self.check_number_operands(&expr.operator, &left, &right)?;
-                Ok(Object::Num(a / b))
+                let (a, b) = self.check_number_operands(operator, &left, &right)?;
+                match a.checked_divide(b) {
+                    Some(quotient) if !(self.options.strict && b.to_float() == 0.0) => {
+                        Ok(Object::Num(quotient))
+                    }
+                    _ => LoxRuntimeException::throw_err(operator.clone(), "Division by zero."),
+                }
+            }
+            TokenType::Percent => {
+                let (a, b) = self.check_number_operands(operator, &left, &right)?;
+                match a.checked_remainder(b) {
+                    Some(remainder) if !(self.options.strict && b.to_float() == 0.0) => {
+                        Ok(Object::Num(remainder))
+                    }
+                    _ => LoxRuntimeException::throw_err(operator.clone(), "Division by zero."),
+                }
             }
 
             TokenType::Greater => {
-                let (a, b) = self.check_number_operands(&expr.operator, &left, &right)?;
+                let (a, b) = self.check_number_operands(operator, &left, &right)?;
                 Ok(Object::Bool(a > b))
             }
             TokenType::GreaterEqual => {
-                let (a, b) = self.check_number_operands(&expr.operator, &left, &right)?;
+                let (a, b) = self.check_number_operands(operator, &left, &right)?;
                 Ok(Object::Bool(a >= b))
             }
             TokenType::Less => {
-                let (a, b) = self.check_number_operands(&expr.operator, &left, &right)?;
+                let (a, b) = self.check_number_operands(operator, &left, &right)?;
                 Ok(Object::Bool(a < b))
             }
             TokenType::LessEqual => {
-                let (a, b) = self.check_number_operands(&expr.operator, &left, &right)?;
+                let (a, b) = self.check_number_operands(operator, &left, &right)?;
                 Ok(Object::Bool(a <= b))
             }
 
@@ -174,85 +847,272 @@ impl Interpreter {
     }
 
     fn evaluate_call(&mut self, expr: &CallExpr) -> Result<Object, LoxRuntimeException> {
-        let mut callee = self.evaluate_expr(&expr.callee)?;
+        let callee = self.evaluate_expr(&expr.callee)?;
         let mut arguments = vec![];
 
         for arg in &expr.arguments {
             arguments.push(self.evaluate_expr(arg)?);
         }
 
-        match &callee {
-            Object::Fun(fun, _) => {
-                if arguments.len() != callee.arity().unwrap() {
-                    return LoxRuntimeException::throw_err(
-                        expr.paren.clone(),
-                        format!(
-                            "Expected {} arguments but got {}.",
-                            callee.arity().unwrap(),
-                            arguments.len()
-                        )
-                        .as_str(),
-                    );
+        let callable = Self::callable(&callee, &expr.paren, arguments.len())?;
+        callable.call(self, &expr.paren, arguments)
+    }
+
+    pub(crate) fn callable(
+        callee: &Object,
+        paren: &Token,
+        arguments: usize,
+    ) -> Result<Shared<dyn LoxCallable>, LoxRuntimeError> {
+        let Some(callable) = callee.callable() else {
+            return Err(LoxRuntimeError(
+                paren.clone(),
+                format!(
+                    "Can only call functions and classes. Got {}.",
+                    callee.describe()
+                ),
+            ));
+        };
+        if arguments != callable.arity() {
+            return Err(LoxRuntimeError(
+                paren.clone(),
+                format!(
+                    "Expected {} arguments but got {}.",
+                    callable.arity(),
+                    arguments
+                ),
+            ));
+        }
+        Ok(callable)
+    }
+
+    fn evaluate_get(&mut self, expr: &GetExpr) -> Result<Object, LoxRuntimeException> {
+        let object = self.evaluate_expr(&expr.object)?;
+        self.get_property(object, &expr.name)
+    }
+
+    pub(crate) fn get_property(
+        &mut self,
+        object: Object,
+        token: &Token,
+    ) -> Result<Object, LoxRuntimeException> {
+        let name = &token.lexeme;
+        match &object {
+            Object::Instance(instance) => {
+                if let Some(value) = instance.get(name) {
+                    return Ok(value);
+                }
+                if let Some(method) = instance.class.find_method(name) {
+                    self.allocated += mem::size_of::<LoxFunction>();
+                    return Ok(Object::Fun(Shared::new(method.bind(object.clone()))));
                 }
-                Ok(self.call(arguments, *fun.clone(), callee.get_closure().unwrap())?)
             }
-            _ => LoxRuntimeException::throw_err(
-                expr.paren.clone(),
-                "Can only call functions and classes.",
+            Object::UserData(data) => {
+                if let Some(getter) = data.getter(name) {
+                    return getter
+                        .call(std::slice::from_ref(&object))
+                        .map_err(|message| LoxRuntimeError(token.clone(), message).into());
+                }
+                if let Some(method) = data.method(name) {
+                    let bound = userdata::bind(&method, object.clone());
+                    return Ok(Object::Native(Shared::new(bound)));
+                }
+            }
+            _ => {
+                return LoxRuntimeException::throw_err(
+                    token.clone(),
+                    &format!("Only instances have properties. Got {}.", object.describe()),
+                )
+            }
+        }
+        LoxRuntimeException::throw_err(token.clone(), &format!("Undefined property '{}'.", name))
+    }
+
+    fn evaluate_set(&mut self, expr: &SetExpr) -> Result<Object, LoxRuntimeException> {
+        let object = self.evaluate_expr(&expr.object)?;
+        let instance = Self::instance_for_set(&object, &expr.name)?;
+        let value = self.evaluate_expr(&expr.value)?;
+        instance.set(&expr.name.lexeme, value.clone());
+        Ok(value)
+    }
+
+    pub(crate) fn instance_for_set(
+        object: &Object,
+        name: &Token,
+    ) -> Result<Shared<LoxInstance>, LoxRuntimeError> {
+        match object {
+            Object::Instance(instance) => Ok(instance.clone()),
+            _ => Err(LoxRuntimeError(
+                name.clone(),
+                format!("Only instances have fields. Got {}.", object.describe()),
+            )),
+        }
+    }
+
+    pub(crate) fn evaluate_super(
+        &mut self,
+        expr: &SuperExpr,
+    ) -> Result<Object, LoxRuntimeException> {
+        let superclass = self.environment.borrow().get(&expr.keyword, &expr.cache)?;
+        let Object::Class(superclass) = superclass else {
+            unreachable!("'super' is only defined as a class");
+        };
+        let instance = self
+            .environment
+            .borrow()
+            .lookup("this")
+            .unwrap_or(Object::None);
+        match superclass.find_method(&expr.method.lexeme) {
+            Some(method) => {
+                self.allocated += mem::size_of::<LoxFunction>();
+                Ok(Object::Fun(Shared::new(method.bind(instance))))
+            }
+            None => LoxRuntimeException::throw_err(
+                expr.method.clone(),
+                &format!("Undefined property '{}'.", expr.method.lexeme),
             ),
         }
     }
 
-    fn call(
+    pub(crate) fn call_function(
         &mut self,
-        params: Vec<Object>,
-        fun: FunctionStmt,
-        env: &mut Environment,
+        fun: &LoxFunction,
+        paren: &Token,
+        arguments: Vec<Object>,
     ) -> Result<Object, LoxRuntimeException> {
-        let previous = self.environment.clone();
-        let closure = Rc::new(RefCell::new(env.clone()));
-        {
-            let closure_ref = closure.clone();
-            self.environment = Environment::new_enclosing(closure_ref);
-            for (i, param) in params.iter().enumerate() {
-                self.environment.define(&fun.params[i].lexeme, param);
+        self.check_call_depth(paren)?;
+        #[cfg(feature = "jit")]
+        if self.jit_enabled() && !fun.is_initializer {
+            let cancelled = self.cancellation.flag();
+            if let Some(value) = fun
+                .declaration
+                .jit
+                .call(&fun.declaration, &arguments, cancelled)
+            {
+                return Ok(value);
             }
-            for s in fun.body {
-                if let Err(exception) = self.execute_stmt(&s) {
-                    self.environment.drop_enclosing();
-                    self.environment = previous;
-                    match exception {
-                        LoxRuntimeException::Return(value) => {
-                            return Ok(value);
-                        }
-                        LoxRuntimeException::Err(err) => {
-                            return Err(LoxRuntimeException::from(err));
-                        }
-                    }
-                }
+        }
+        self.frames.push(Frame {
+            function: fun.name().into(),
+            line: paren.line,
+        });
+        let result = self.call(arguments, fun);
+        self.frames.pop();
+        result
+    }
+
+    pub(crate) fn check_call_depth(&self, paren: &Token) -> Result<(), LoxRuntimeError> {
+        match self.options.max_call_depth {
+            Some(max_call_depth) if self.frames.len() > max_call_depth => {
+                Err(LoxRuntimeError(paren.clone(), "Stack overflow.".into()))
             }
+            _ => Ok(()),
         }
-        self.environment.drop_enclosing();
+    }
+
+    pub(crate) fn instantiate(&mut self, class: &Shared<LoxClass>) -> Object {
+        self.allocated += mem::size_of::<LoxInstance>();
+        Object::Instance(Shared::new(LoxInstance::new(class.clone())))
+    }
 
+    #[cfg(feature = "jit")]
+    fn jit_enabled(&self) -> bool {
+        self.hook.is_none()
+            && self.meter.is_none()
+            && self.options.max_steps.is_none()
+            && self.options.max_duration.is_none()
+            && !self.options.strict
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(function = %fun.declaration.name.lexeme))
+    )]
+    fn call(
+        &mut self,
+        params: Vec<Object>,
+        fun: &LoxFunction,
+    ) -> Result<Object, LoxRuntimeException> {
+        let environment = Self::function_environment(fun, &params);
+        match self.execute_block(&fun.declaration.body, environment) {
+            Ok(()) | Err(LoxRuntimeException::Return(_)) if fun.is_initializer => Ok(fun.this()),
+            Ok(()) => Ok(Object::None),
+            Err(LoxRuntimeException::Return(value)) => Ok(value),
+            Err(exception) => Err(exception),
+        }
+    }
+
+    pub(crate) fn function_environment(fun: &LoxFunction, arguments: &[Object]) -> Environment {
+        let mut environment = Environment::new_enclosing(fun.closure.clone());
+        for (param, value) in fun.declaration.params.iter().zip(arguments) {
+            environment.define(&param.lexeme, value);
+        }
+        environment
+    }
+
+    fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<(), LoxRuntimeException> {
+        let previous = mem::replace(&mut self.environment, Shared::new(Lock::new(environment)));
+        let result = statements
+            .iter()
+            .try_for_each(|stmt| self.execute_stmt(stmt));
         self.environment = previous;
-        Ok(Object::None)
+        result
     }
 
     fn evaluate_grouping(&mut self, expr: &GroupingExpr) -> Result<Object, LoxRuntimeException> {
         self.evaluate_expr(&expr.expression)
     }
 
+    fn evaluate_interpolation(
+        &mut self,
+        expr: &InterpolationExpr,
+    ) -> Result<Object, LoxRuntimeException> {
+        let mut parts = vec![];
+        for part in &expr.parts {
+            parts.push(self.evaluate_expr(part)?);
+        }
+        self.interpolate(&expr.quote, &parts)
+    }
+
+    pub(crate) fn interpolate(
+        &mut self,
+        quote: &Token,
+        parts: &[Object],
+    ) -> Result<Object, LoxRuntimeException> {
+        let text: String = parts.iter().map(|part| self.stringify(part)).collect();
+        if let Some(max_length) = self.options.max_string_length {
+            if text.len() > max_length {
+                return LoxRuntimeException::throw_err(
+                    quote.clone(),
+                    &format!("String length exceeds the limit of {} bytes.", max_length),
+                );
+            }
+        }
+        self.allocated += text.len();
+        Ok(Object::String(text))
+    }
+
     fn evaluate_literal(&self, expr: &LiteralExpr) -> Result<Object, LoxRuntimeError> {
         Ok(expr.value.clone())
     }
 
     fn evaluate_unary(&mut self, expr: &UnaryExpr) -> Result<Object, LoxRuntimeException> {
         let right = self.evaluate_expr(&expr.right)?;
+        Ok(self.unary(&expr.operator, &right)?)
+    }
 
-        let obj = match expr.operator.token_type {
-            TokenType::Bang => Object::Bool(!Self::is_truthy(&right)),
+    pub(crate) fn unary(
+        &self,
+        operator: &Token,
+        right: &Object,
+    ) -> Result<Object, LoxRuntimeError> {
+        let obj = match operator.token_type {
+            TokenType::Bang => Object::Bool(!Self::is_truthy(right)),
             TokenType::Minus => {
-                let num = self.check_number_operand(&expr.operator, &right)?;
+                let num = self.check_number_operand(operator, right)?;
                 Object::Num(-num)
             }
             _ => unimplemented!(),
@@ -273,7 +1133,7 @@ impl Interpreter {
         self.evaluate_expr(&expr.right)
     }
 
-    fn is_truthy(obj: &Object) -> bool {
+    pub(crate) fn is_truthy(obj: &Object) -> bool {
         match obj {
             Object::Bool(b) => *b,
             Object::None => false,
@@ -281,42 +1141,56 @@ impl Interpreter {
         }
     }
 
-    fn check_number_operand(
+    fn check_number_operand<'a>(
         &self,
         operator: &Token,
-        operand: &Object,
-    ) -> Result<f64, LoxRuntimeError> {
+        operand: &'a Object,
+    ) -> Result<&'a Number, LoxRuntimeError> {
         match operand.num() {
             Ok(num) => Ok(num),
             Err(_) => Err(LoxRuntimeError(
                 operator.clone(),
-                "Operand must be number.".into(),
+                format!("Operand must be a number. Got {}.", operand.describe()),
             )),
         }
     }
 
-    fn check_number_operands(
+    fn check_number_operands<'a>(
         &self,
         operator: &Token,
-        a: &Object,
-        b: &Object,
-    ) -> Result<(f64, f64), LoxRuntimeError> {
+        a: &'a Object,
+        b: &'a Object,
+    ) -> Result<(&'a Number, &'a Number), LoxRuntimeError> {
         match (a.num(), b.num()) {
             (Ok(a), Ok(b)) => Ok((a, b)),
             _ => Err(LoxRuntimeError(
                 operator.clone(),
-                "Operand must be numbers.".into(),
+                format!(
+                    "Operands must be numbers. Got {} and {}.",
+                    a.describe(),
+                    b.describe()
+                ),
             )),
         }
     }
 
-    fn strigify(&self, obj: &Object) -> String {
+    pub fn stringify(&self, obj: &Object) -> String {
         match obj {
             Object::String(s) => s.into(),
             Object::Bool(b) => b.to_string(),
-            Object::Num(n) => n.to_string().replace(".0", ""),
-            Object::Fun(stmt, _) => stmt.name.lexeme.to_string(),
+            Object::Num(n) => n.to_lox_string(),
+            Object::Fun(fun) => fun.name().into(),
+            Object::Native(native) => format!("<native fn {}>", native.name),
+            Object::Class(class) => class.name.clone(),
+            Object::Instance(instance) => format!("{} instance", instance.class.name),
+            Object::UserData(data) => format!("<userdata {}>", data.type_name()),
             Object::None => "nil".into(),
         }
     }
 }
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
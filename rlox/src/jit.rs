@@ -0,0 +1,403 @@
+// With the `jit` feature, a function that has been called often enough is
+// compiled to native code through Cranelift. Only functions that work purely
+// on numbers are compiled: their parameters and `var`s must hold numbers and
+// they may not print, call functions or touch variables outside their own
+// body. Such a function has no side effects, so whenever compiled code can't
+// be used (an argument isn't a number, or the script is cancelled while it
+// runs) the call is simply interpreted instead.
+
+#[cfg(feature = "jit")]
+mod imp {
+    use std::{collections::HashMap, fmt::Debug, sync::atomic::AtomicBool};
+
+    use cranelift_codegen::{
+        ir::{condcodes::FloatCC, types, AbiParam, InstBuilder, MemFlags, Value},
+        settings::{self, Configurable},
+    };
+    use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+    use cranelift_jit::{JITBuilder, JITModule};
+    use cranelift_module::{default_libcall_names, Linkage, Module};
+
+    use crate::{
+        generate_ast::{Expr, FunctionStmt, Stmt},
+        number::{Number, Numeric},
+        sync::{Lock, Shared},
+        token::Object,
+        token_type::TokenType,
+    };
+
+    const HOT_CALLS: usize = 100;
+
+    const RETURNED: u8 = 0;
+    const RETURNED_NIL: u8 = 1;
+    const CANCELLED: u8 = 2;
+
+    type Code = unsafe extern "C" fn(*const f64, *mut f64, *const AtomicBool) -> u8;
+
+    #[derive(Clone, Copy)]
+    enum JitState {
+        Counting(usize),
+        Compiled(Code),
+        Unsupported,
+    }
+
+    #[derive(Clone)]
+    pub struct JitCache(Shared<Lock<JitState>>);
+
+    impl Default for JitCache {
+        fn default() -> Self {
+            Self(Shared::new(Lock::new(JitState::Counting(0))))
+        }
+    }
+
+    impl Debug for JitCache {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("JitCache").finish_non_exhaustive()
+        }
+    }
+
+    impl JitCache {
+        pub(crate) fn call(
+            &self,
+            declaration: &FunctionStmt,
+            arguments: &[Object],
+            cancelled: &AtomicBool,
+        ) -> Option<Object> {
+            let state = *self.0.borrow();
+            let code = match state {
+                JitState::Compiled(code) => code,
+                JitState::Unsupported => return None,
+                JitState::Counting(calls) if calls + 1 < HOT_CALLS => {
+                    *self.0.borrow_mut() = JitState::Counting(calls + 1);
+                    return None;
+                }
+                JitState::Counting(_) => {
+                    let state = match compile(declaration) {
+                        Some(code) => JitState::Compiled(code),
+                        None => JitState::Unsupported,
+                    };
+                    *self.0.borrow_mut() = state;
+                    let JitState::Compiled(code) = state else {
+                        return None;
+                    };
+                    code
+                }
+            };
+            let arguments = arguments
+                .iter()
+                .map(|argument| match argument {
+                    Object::Num(n) => Some(n.to_float()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let mut result = 0.0;
+            // SAFETY: `code` was compiled for exactly `arguments.len()`
+            // parameters and only reads them, writes `result` and loads the
+            // cancellation flag.
+            match unsafe { code(arguments.as_ptr(), &mut result, cancelled) } {
+                RETURNED => Some(Object::Num(Number::from_float(result))),
+                RETURNED_NIL => Some(Object::None),
+                _ => None,
+            }
+        }
+    }
+
+    fn compile(declaration: &FunctionStmt) -> Option<Code> {
+        if cfg!(feature = "decimal") {
+            return None;
+        }
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").ok()?;
+        let isa = cranelift_native::builder()
+            .ok()?
+            .finish(settings::Flags::new(flags))
+            .ok()?;
+        let mut module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+        let pointer = module.target_config().pointer_type();
+
+        let mut context = module.make_context();
+        for _ in 0..3 {
+            context.func.signature.params.push(AbiParam::new(pointer));
+        }
+        context
+            .func
+            .signature
+            .returns
+            .push(AbiParam::new(types::I8));
+
+        let mut builder_context = FunctionBuilderContext::new();
+        let builder = FunctionBuilder::new(&mut context.func, &mut builder_context);
+        let mut translator = Translator {
+            builder,
+            scopes: vec![HashMap::new()],
+            variables: 0,
+            out: None,
+            cancelled: None,
+        };
+        translator.function(declaration)?;
+        translator.builder.finalize();
+
+        let id = module
+            .declare_function("lox_function", Linkage::Local, &context.func.signature)
+            .ok()?;
+        module.define_function(id, &mut context).ok()?;
+        module.clear_context(&mut context);
+        module.finalize_definitions().ok()?;
+        let code = module.get_finalized_function(id);
+        // SAFETY: the function was declared with the signature of `Code`,
+        // and the module leaks its memory on drop, so the pointer stays valid.
+        Some(unsafe { std::mem::transmute::<*const u8, Code>(code) })
+    }
+
+    struct Translator<'a> {
+        builder: FunctionBuilder<'a>,
+        scopes: Vec<HashMap<String, Variable>>,
+        variables: u32,
+        out: Option<Value>,
+        cancelled: Option<Value>,
+    }
+
+    impl Translator<'_> {
+        fn function(&mut self, declaration: &FunctionStmt) -> Option<()> {
+            let entry = self.builder.create_block();
+            self.builder.append_block_params_for_function_params(entry);
+            self.builder.switch_to_block(entry);
+            let &[arguments, out, cancelled] = self.builder.block_params(entry) else {
+                return None;
+            };
+            self.out = Some(out);
+            self.cancelled = Some(cancelled);
+            for (i, param) in declaration.params.iter().enumerate() {
+                let value = self.builder.ins().load(
+                    types::F64,
+                    MemFlags::trusted(),
+                    arguments,
+                    i as i32 * 8,
+                );
+                self.declare(&param.lexeme, value);
+            }
+            self.stmts(&declaration.body)?;
+            self.exit(RETURNED_NIL);
+            self.builder.seal_all_blocks();
+            Some(())
+        }
+
+        fn declare(&mut self, name: &str, value: Value) {
+            let variable = Variable::from_u32(self.variables);
+            self.variables += 1;
+            self.builder.declare_var(variable, types::F64);
+            self.builder.def_var(variable, value);
+            self.scopes
+                .last_mut()
+                .expect("scope")
+                .insert(name.into(), variable);
+        }
+
+        fn variable(&self, name: &str) -> Option<Variable> {
+            self.scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(name).copied())
+        }
+
+        fn exit(&mut self, code: u8) {
+            let code = self.builder.ins().iconst(types::I8, code as i64);
+            self.builder.ins().return_(&[code]);
+            let unreachable = self.builder.create_block();
+            self.builder.switch_to_block(unreachable);
+        }
+
+        fn stmts(&mut self, stmts: &[Stmt]) -> Option<()> {
+            stmts.iter().try_for_each(|stmt| self.stmt(stmt))
+        }
+
+        fn stmt(&mut self, stmt: &Stmt) -> Option<()> {
+            match stmt {
+                Stmt::Expression(stmt) => {
+                    self.value(&stmt.expression)?;
+                }
+                Stmt::Var(stmt) => {
+                    let value = self.value(stmt.initializer.as_ref()?)?;
+                    self.declare(&stmt.name.lexeme, value);
+                }
+                Stmt::Block(stmt) => {
+                    self.scopes.push(HashMap::new());
+                    let result = self.stmts(&stmt.statements);
+                    self.scopes.pop();
+                    result?;
+                }
+                Stmt::If(stmt) => {
+                    let condition = self.condition(&stmt.condition)?;
+                    let then_block = self.builder.create_block();
+                    let else_block = self.builder.create_block();
+                    let merge = self.builder.create_block();
+                    self.builder
+                        .ins()
+                        .brif(condition, then_block, &[], else_block, &[]);
+                    self.builder.switch_to_block(then_block);
+                    self.stmt(&stmt.then_branch)?;
+                    self.builder.ins().jump(merge, &[]);
+                    self.builder.switch_to_block(else_block);
+                    if let Some(else_branch) = &stmt.else_branch {
+                        self.stmt(else_branch)?;
+                    }
+                    self.builder.ins().jump(merge, &[]);
+                    self.builder.switch_to_block(merge);
+                }
+                Stmt::While(stmt) => {
+                    let header = self.builder.create_block();
+                    let check = self.builder.create_block();
+                    let body = self.builder.create_block();
+                    let cancelled = self.builder.create_block();
+                    let exit = self.builder.create_block();
+                    self.builder.ins().jump(header, &[]);
+                    self.builder.switch_to_block(header);
+                    let flag =
+                        self.builder
+                            .ins()
+                            .load(types::I8, MemFlags::trusted(), self.cancelled?, 0);
+                    self.builder.ins().brif(flag, cancelled, &[], check, &[]);
+                    self.builder.switch_to_block(cancelled);
+                    self.exit(CANCELLED);
+                    self.builder.ins().jump(exit, &[]);
+                    self.builder.switch_to_block(check);
+                    let condition = self.condition(&stmt.condition)?;
+                    self.builder.ins().brif(condition, body, &[], exit, &[]);
+                    self.builder.switch_to_block(body);
+                    self.stmt(&stmt.body)?;
+                    if let Some(increment) = &stmt.increment {
+                        self.value(increment)?;
+                    }
+                    self.builder.ins().jump(header, &[]);
+                    self.builder.switch_to_block(exit);
+                }
+                Stmt::Return(stmt) => match &stmt.value {
+                    Some(value) => {
+                        let value = self.value(value)?;
+                        self.builder
+                            .ins()
+                            .store(MemFlags::trusted(), value, self.out?, 0);
+                        self.exit(RETURNED);
+                    }
+                    None => self.exit(RETURNED_NIL),
+                },
+                Stmt::Print(_)
+                | Stmt::Break(_)
+                | Stmt::Continue(_)
+                | Stmt::Function(_)
+                | Stmt::Class(_)
+                | Stmt::Import(_)
+                | Stmt::Macro(_) => return None,
+            }
+            Some(())
+        }
+
+        fn value(&mut self, expr: &Expr) -> Option<Value> {
+            let value = match expr {
+                Expr::Literal(expr) => match &expr.value {
+                    Object::Num(n) => self.builder.ins().f64const(n.to_float()),
+                    _ => return None,
+                },
+                Expr::Variable(expr) => {
+                    let variable = self.variable(&expr.name.lexeme)?;
+                    self.builder.use_var(variable)
+                }
+                Expr::Assign(expr) => {
+                    let variable = self.variable(&expr.name.lexeme)?;
+                    let value = self.value(&expr.value)?;
+                    self.builder.def_var(variable, value);
+                    value
+                }
+                Expr::Grouping(expr) => self.value(&expr.expression)?,
+                Expr::Unary(expr) if expr.operator.token_type == TokenType::Minus => {
+                    let right = self.value(&expr.right)?;
+                    self.builder.ins().fneg(right)
+                }
+                Expr::Binary(expr) => {
+                    let left = self.value(&expr.left)?;
+                    let right = self.value(&expr.right)?;
+                    match expr.operator.token_type {
+                        TokenType::Plus => self.builder.ins().fadd(left, right),
+                        TokenType::Minus => self.builder.ins().fsub(left, right),
+                        TokenType::Star => self.builder.ins().fmul(left, right),
+                        TokenType::Slash => self.builder.ins().fdiv(left, right),
+                        _ => return None,
+                    }
+                }
+                Expr::Unary(_)
+                | Expr::Interpolation(_)
+                | Expr::Logical(_)
+                | Expr::Call(_)
+                | Expr::Get(_)
+                | Expr::Set(_)
+                | Expr::Super(_)
+                | Expr::This(_) => return None,
+            };
+            Some(value)
+        }
+
+        fn condition(&mut self, expr: &Expr) -> Option<Value> {
+            let condition = match expr {
+                Expr::Literal(expr) => {
+                    let truthy = !matches!(expr.value, Object::Bool(false) | Object::None);
+                    self.builder.ins().iconst(types::I8, truthy as i64)
+                }
+                Expr::Grouping(expr) => self.condition(&expr.expression)?,
+                Expr::Unary(expr) if expr.operator.token_type == TokenType::Bang => {
+                    let right = self.condition(&expr.right)?;
+                    self.builder.ins().bxor_imm(right, 1)
+                }
+                Expr::Binary(binary) => {
+                    let comparison = match binary.operator.token_type {
+                        TokenType::Less => FloatCC::LessThan,
+                        TokenType::LessEqual => FloatCC::LessThanOrEqual,
+                        TokenType::Greater => FloatCC::GreaterThan,
+                        TokenType::GreaterEqual => FloatCC::GreaterThanOrEqual,
+                        TokenType::EqualEqual => FloatCC::Equal,
+                        TokenType::BangEqual => FloatCC::NotEqual,
+                        _ => {
+                            self.value(expr)?;
+                            return Some(self.builder.ins().iconst(types::I8, 1));
+                        }
+                    };
+                    let left = self.value(&binary.left)?;
+                    let right = self.value(&binary.right)?;
+                    self.builder.ins().fcmp(comparison, left, right)
+                }
+                Expr::Logical(expr) => {
+                    let left = self.condition(&expr.left)?;
+                    let right_block = self.builder.create_block();
+                    let merge = self.builder.create_block();
+                    let result = self.builder.append_block_param(merge, types::I8);
+                    if expr.operator.token_type == TokenType::And {
+                        self.builder
+                            .ins()
+                            .brif(left, right_block, &[], merge, &[left]);
+                    } else {
+                        self.builder
+                            .ins()
+                            .brif(left, merge, &[left], right_block, &[]);
+                    }
+                    self.builder.switch_to_block(right_block);
+                    let right = self.condition(&expr.right)?;
+                    self.builder.ins().jump(merge, &[right]);
+                    self.builder.switch_to_block(merge);
+                    result
+                }
+                _ => {
+                    self.value(expr)?;
+                    self.builder.ins().iconst(types::I8, 1)
+                }
+            };
+            Some(condition)
+        }
+    }
+}
+
+#[cfg(not(feature = "jit"))]
+mod imp {
+    #[derive(Clone, Default, Debug)]
+    pub struct JitCache {}
+}
+
+pub use imp::JitCache;
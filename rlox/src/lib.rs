@@ -1,108 +1,907 @@
+#![allow(clippy::result_large_err)]
+
+#[cfg(feature = "tools")]
+use std::collections::BTreeSet;
 use std::{
-    fs::File,
+    any::Any,
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display},
+    fs::{self, File},
     io::{self, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    time::Instant,
 };
 
-use interpreter::Interpreter;
-use parser::Parser;
-use scanner::Scanner;
-use token::Token;
-use token_type::TokenType;
+use ast_printer::AstPrinter;
+#[cfg(feature = "tools")]
+use dap::{Connection, DapDebugger};
+use debugger::{Debugger, Rewind};
+use formatter::Formatter;
+use lint::Linter;
+use repl::Command;
+use server::Listener;
+use sync::Lock;
 
+mod ast_printer;
+mod bindings;
+mod builder;
+mod callable;
+mod cancel;
+mod class;
+mod clock;
+mod crash;
+#[cfg(feature = "tools")]
+mod dap;
+mod debugger;
+mod diagnostic;
 mod environment;
+mod error_codes;
+mod execution;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod fork;
+mod formatter;
 mod generate_ast;
+mod highlight;
 mod interpreter;
+mod jit;
+mod lint;
+#[cfg(feature = "tools")]
+mod lsp;
+mod macros;
+mod module;
+mod native;
+mod native_class;
+mod number;
+#[cfg(feature = "serde")]
+mod object_serde;
+mod options;
 mod parser;
+mod preprocess;
+#[cfg(feature = "python")]
+mod python;
+mod random;
+mod repl;
+mod replay;
+mod resolver;
 mod scanner;
+mod server;
+mod shape;
+mod suggestion;
+mod sync;
 mod token;
 mod token_type;
+#[cfg(feature = "tools")]
+mod transport;
+mod userdata;
+#[cfg(target_arch = "wasm32")]
+mod wasm;
+
+pub use ast_printer::{json_string, AstFormat};
+pub use builder::LoxBuilder;
+pub use callable::LoxFunctionHandle;
+pub use cancel::CancellationHandle;
+pub use clock::{Clock, SystemClock};
+pub use crash::install_panic_hook;
+pub use diagnostic::{ColorChoice, Diagnostic, ErrorFormat, Severity};
+pub use error_codes::{ErrorCode, ERROR_CODES};
+pub use execution::Execution;
+pub use generate_ast::{
+    AssignExpr, BinaryExpr, BlockStmt, CallExpr, Expr, ExpressionStmt, FunctionStmt, GroupingExpr,
+    IfStmt, ImportStmt, LiteralExpr, LogicalExpr, PrintStmt, ReturnStmt, Stmt, UnaryExpr, VarStmt,
+    VariableExpr, WhileStmt,
+};
+pub use highlight::{semantic_tokens, SemanticKind, SemanticToken};
+pub use interpreter::{DebugHook, Frame, Interpreter, Progress};
+pub use lint::{LintConfig, LintRule, LintWarning, Suppressions};
+#[cfg(feature = "tools")]
+pub use lsp::LanguageServer;
+pub use module::{FileSystemLoader, ModuleLoader};
+pub use native::{NativeFunction, NativeResult};
+pub use native_class::NativeClass;
+pub use number::{Number, Numeric};
+pub use options::{Capability, InterpreterOptions, Usage};
+pub use parser::Parser;
+pub use preprocess::Preprocessed;
+pub use random::{Random, SeededRandom};
+pub use replay::{Recorder, Trace};
+pub use scanner::Scanner;
+pub use sync::{
+    DiagnosticSink, MaybeSend, MaybeSync, Meter, Preprocessor, PrintHook, Reader, Shared, Writer,
+};
+pub use token::{ConversionError, Object, Token};
+pub use token_type::TokenType;
+pub use userdata::UserData;
 
 pub struct Lox {
     had_error: bool,
+    had_runtime_error: bool,
     interpreter: Interpreter,
+    history: Vec<String>,
+    natives: Vec<Shared<NativeFunction>>,
+    last_input: Option<String>,
+    source_name: Option<String>,
+    source: String,
+    sources: HashMap<String, String>,
+    error_format: ErrorFormat,
+    import_root: Option<PathBuf>,
+    warnings: LintConfig,
+    deny_warnings: bool,
+    diagnostic_output: Box<Writer>,
+    diagnostic_sink: Option<Box<DiagnosticSink>>,
+    color: bool,
+    verbosity: Verbosity,
+    indent_width: usize,
+    prompt: String,
+    prelude: Option<PathBuf>,
+    max_errors: usize,
+    literate: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+    Debug,
 }
 
 impl Lox {
     pub fn new() -> Self {
         Self {
             had_error: false,
+            had_runtime_error: false,
             interpreter: Interpreter::new(),
+            history: vec![],
+            natives: vec![],
+            last_input: None,
+            source_name: None,
+            source: String::new(),
+            sources: HashMap::new(),
+            error_format: ErrorFormat::Human,
+            import_root: None,
+            warnings: LintConfig::new(),
+            deny_warnings: false,
+            diagnostic_output: Box::new(io::stderr()),
+            diagnostic_sink: None,
+            color: ColorChoice::Auto.enabled(),
+            verbosity: Verbosity::Normal,
+            indent_width: 4,
+            prompt: "> ".into(),
+            prelude: None,
+            max_errors: 20,
+            literate: false,
+        }
+    }
+
+    pub fn builder() -> LoxBuilder {
+        LoxBuilder::new()
+    }
+
+    pub fn with_output(output: impl Write + MaybeSync + 'static) -> Self {
+        let mut lox = Self::new();
+        lox.set_output(Box::new(output));
+        lox
+    }
+
+    pub fn run_file(&mut self, file_name: String) -> io::Result<()> {
+        let src = fs::read_to_string(&file_name)?;
+        self.source_name = Some(file_name);
+        self.run_script(&src);
+        Ok(())
+    }
+
+    pub fn run_source(&mut self, src: &str) -> Result<Object, LoxError> {
+        let stmts = self.compile(src).map_err(LoxError::Compile)?;
+        self.interpreter
+            .interpret(stmts)
+            .map_err(|err| LoxError::Runtime(err.diagnostic()))
+    }
+
+    pub fn prepare(&mut self, src: &str) -> Result<Execution, LoxError> {
+        let stmts = self.compile(src).map_err(LoxError::Compile)?;
+        Ok(Execution::new(stmts))
+    }
+
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Object]) -> NativeResult + MaybeSync + 'static,
+    ) {
+        let native = Shared::new(NativeFunction::new(name, arity, function));
+        self.interpreter
+            .define(name, Object::Native(native.clone()));
+        self.natives.push(native);
+    }
+
+    pub fn define_class<T: Any + MaybeSend>(&mut self, class: NativeClass<T>) {
+        let native = Shared::new(class.into_constructor());
+        self.interpreter
+            .define(&native.name, Object::Native(native.clone()));
+        self.natives.push(native);
+    }
+
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+
+    pub fn set_error_format(&mut self, error_format: ErrorFormat) {
+        self.error_format = error_format;
+    }
+
+    pub fn set_output(&mut self, output: Box<Writer>) {
+        self.interpreter.set_output(output);
+    }
+
+    pub fn set_options(&mut self, options: InterpreterOptions) {
+        self.interpreter.set_options(options);
+    }
+
+    pub fn set_module_loader(&mut self, module_loader: Box<dyn ModuleLoader>) {
+        self.interpreter.set_module_loader(module_loader);
+    }
+
+    pub fn set_input(&mut self, input: Box<Reader>) {
+        self.interpreter.set_input(input);
+    }
+
+    pub fn set_print_hook(&mut self, print_hook: Option<Box<PrintHook>>) {
+        self.interpreter.set_print_hook(print_hook);
+    }
+
+    pub fn set_preprocessor(&mut self, preprocessor: Option<Box<Preprocessor>>) {
+        self.interpreter.set_preprocessor(preprocessor);
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.interpreter.set_clock(clock);
+    }
+
+    pub fn set_random(&mut self, random: Box<dyn Random>) {
+        self.interpreter.set_random(random);
+    }
+
+    pub fn set_meter(&mut self, interval: usize, meter: Option<Box<Meter>>) {
+        self.interpreter.set_meter(interval, meter);
+    }
+
+    pub fn set_diagnostic_output(&mut self, output: Box<Writer>) {
+        self.diagnostic_output = output;
+        self.color = false;
+    }
+
+    pub fn set_diagnostic_sink(&mut self, diagnostic_sink: Option<Box<DiagnosticSink>>) {
+        self.diagnostic_sink = diagnostic_sink;
+    }
+
+    pub fn set_color(&mut self, color: ColorChoice) {
+        self.color = color.enabled();
+    }
+
+    pub fn set_indent_width(&mut self, indent_width: usize) {
+        self.indent_width = indent_width;
+    }
+
+    pub fn set_prompt(&mut self, prompt: &str) {
+        self.prompt = prompt.into();
+    }
+
+    pub fn set_prelude(&mut self, prelude: Option<PathBuf>) {
+        self.prelude = prelude;
+    }
+
+    pub fn set_max_errors(&mut self, max_errors: usize) {
+        self.max_errors = max_errors;
+    }
+
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    pub fn set_warnings(&mut self, warnings: LintConfig) {
+        self.warnings = warnings;
+    }
+
+    pub fn set_deny_warnings(&mut self, deny_warnings: bool) {
+        self.deny_warnings = deny_warnings;
+    }
+
+    /// Treats scripts as Markdown and runs only their ```` ```lox ```` code
+    /// blocks. Imported modules are still plain Lox.
+    pub fn set_literate(&mut self, literate: bool) {
+        self.literate = literate;
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        let mut options = self.interpreter.options().clone();
+        options.strict = strict;
+        self.interpreter.set_options(options);
+    }
+
+    pub fn had_error(&self) -> bool {
+        self.had_error
+    }
+
+    pub fn had_runtime_error(&self) -> bool {
+        self.had_runtime_error
+    }
+
+    pub fn run_project(&mut self, dir: &Path) -> io::Result<()> {
+        let main = dir.join("main.lox");
+        if !main.is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no main.lox found in '{}'", dir.display()),
+            ));
+        }
+        self.import_root = Some(dir.to_path_buf());
+        self.set_module_loader(Box::new(FileSystemLoader::new(dir)));
+        let src = fs::read_to_string(&main)?;
+        self.run_named(&main.to_string_lossy(), &src);
+        Ok(())
+    }
+
+    pub fn import_root(&self) -> Option<&Path> {
+        self.import_root.as_deref()
+    }
+
+    pub fn run_named(&mut self, source_name: &str, src: &str) {
+        self.source_name = Some(source_name.into());
+        self.run_script(src);
+    }
+
+    pub fn run_eval(&mut self, src: &str) {
+        self.source_name = Some("<eval>".into());
+        if let Some(value) = self.run_script(src) {
+            if value != Object::None {
+                let text = self.interpreter.stringify(&value);
+                self.print(&text);
+            }
         }
     }
 
-    pub fn run_file(&mut self, file_name: String) {
-        let file = File::open(file_name).expect("open file");
+    pub fn read_file(file_name: &str) -> io::Result<String> {
+        let file = File::open(file_name)?;
         let mut reader = BufReader::new(file);
         let mut buffer = String::new();
-        reader.read_to_string(&mut buffer).expect("read file");
-        self.run(&buffer);
+        reader.read_to_string(&mut buffer)?;
+        Ok(buffer)
     }
 
     pub fn run_prompt(&mut self) {
-        let mut buffer = String::new();
+        self.load_prelude();
+        self.read_eval_print();
+    }
+
+    /// Serves the REPL to one client at a time over TCP, or over a Unix
+    /// socket when `address` is `unix:PATH`. Globals outlive each connection,
+    /// so a client can reconnect and pick up where the last one left off.
+    pub fn serve(&mut self, address: &str) -> io::Result<()> {
+        let listener = Listener::bind(address)?;
+        self.print_error(&format!("Listening on {}", address));
+        self.load_prelude();
+        let cancellation = self.interpreter.cancellation_handle();
+        loop {
+            let session = listener.accept(&cancellation)?;
+            self.print_error(&format!("Connection from {}", session.peer));
+            let color = std::mem::replace(&mut self.color, false);
+            let diagnostic_output =
+                std::mem::replace(&mut self.diagnostic_output, session.diagnostic_output);
+            let (input, output) = self.interpreter.swap_io(session.input, session.output);
+            self.read_eval_print();
+            self.interpreter.swap_io(input, output);
+            self.diagnostic_output = diagnostic_output;
+            self.color = color;
+            cancellation.take();
+            self.print_error(&format!("Connection from {} closed", session.peer));
+        }
+    }
 
+    fn read_eval_print(&mut self) {
+        let mut buffer = String::new();
         loop {
             buffer.clear();
-            print!("> ");
-            io::stdout().flush().expect("flush");
-            io::stdin().read_line(&mut buffer).expect("read line");
+            {
+                let mut output = self.interpreter.output();
+                write!(output, "{}", self.prompt).expect("write output");
+                output.flush().expect("flush");
+            }
+            self.interpreter.read_line(&mut buffer).expect("read line");
             if buffer.is_empty() {
                 return;
             }
-            self.run(&buffer);
+            if let Some(command) = Command::parse(&buffer) {
+                if !self.run_command(command) {
+                    return;
+                }
+                self.had_error = false;
+                self.had_runtime_error = false;
+                continue;
+            }
+            self.run_line(&buffer);
+            if !self.had_error && !self.had_runtime_error {
+                self.history.push(buffer.clone());
+            }
+            self.last_input = Some(buffer.clone());
 
             self.had_error = false;
+            self.had_runtime_error = false;
+        }
+    }
+
+    fn load_prelude(&mut self) {
+        let Some(path) = self.prelude.clone() else {
+            return;
+        };
+        let name = path.to_string_lossy().to_string();
+        match fs::read_to_string(&path) {
+            Ok(src) => {
+                let source_name = self.source_name.take();
+                self.run_named(&name, &src);
+                if self.had_error || self.had_runtime_error {
+                    self.print_error(&format!(
+                        "Prelude '{}' did not load cleanly; continuing.",
+                        name
+                    ));
+                }
+                self.source_name = source_name;
+            }
+            Err(err) => self.print_error(&format!("Could not load prelude '{}': {}", name, err)),
+        }
+        self.had_error = false;
+        self.had_runtime_error = false;
+    }
+
+    fn run_command(&mut self, command: Command) -> bool {
+        match command {
+            Command::Help => self.print(repl::HELP),
+            Command::Env => {
+                for (name, value) in self.interpreter.bindings() {
+                    let value = self.interpreter.stringify(&value);
+                    self.print(&format!("{} = {}", name, value));
+                }
+            }
+            Command::Load(file_name) => match fs::read_to_string(&file_name) {
+                Ok(src) => self.run(&src),
+                Err(err) => self.print_error(&format!("Could not load '{}': {}", file_name, err)),
+            },
+            Command::Type(src) => {
+                self.source = src.clone();
+                match self.interpreter.eval_expr(&src) {
+                    Ok(value) => self.print(value.type_name()),
+                    Err(LoxError::Compile(diagnostics)) => self.report_errors(&diagnostics),
+                    Err(LoxError::Runtime(diagnostic)) => {
+                        self.report(&diagnostic);
+                        self.had_runtime_error = true;
+                    }
+                }
+            }
+            Command::Time(src) => {
+                let statements = self.interpreter.executed_statements();
+                let start = Instant::now();
+                self.run(&src);
+                let elapsed = start.elapsed();
+                let statements = self.interpreter.executed_statements() - statements;
+                self.print(&format!("time: {:?}, statements: {}", elapsed, statements));
+            }
+            Command::Save(file_name) => {
+                if let Err(err) = fs::write(&file_name, self.history.concat()) {
+                    self.print_error(&format!("Could not save '{}': {}", file_name, err));
+                }
+            }
+            Command::Tokens => match self.last_input.clone() {
+                Some(src) => self.print_tokens(&src),
+                None => self.print_error("No previous input to show."),
+            },
+            Command::Ast(format) => {
+                let format = match format.as_str() {
+                    "" => Some(AstFormat::SExpr),
+                    name => AstFormat::from_name(name),
+                };
+                match (format, self.last_input.clone()) {
+                    (None, _) => self.print_error("Unknown AST format. Use sexpr, json or dot."),
+                    (Some(_), None) => self.print_error("No previous input to show."),
+                    (Some(format), Some(src)) => self.print_ast(&src, format),
+                }
+            }
+            Command::Reset => {
+                self.interpreter.reset();
+                for native in &self.natives {
+                    self.interpreter
+                        .define(&native.name, Object::Native(native.clone()));
+                }
+                self.history.clear();
+                self.last_input = None;
+                self.load_prelude();
+            }
+            Command::Quit => return false,
+            Command::Unknown(command) => self.print_error(&format!(
+                "Unknown command '{}'. Type :help for a list.",
+                command
+            )),
         }
+        true
     }
 
     fn run(&mut self, src: &str) {
+        if let Some(stmts) = self.parse(src) {
+            self.interpret(stmts);
+        }
+    }
+
+    fn interpret(&mut self, stmts: Vec<Stmt>) -> Option<Object> {
+        let interpreter = &mut self.interpreter;
+        match crash::catch(|| interpreter.interpret(stmts)) {
+            Ok(Ok(value)) => Some(value),
+            Ok(Err(err)) => {
+                self.error_in_interpret(err);
+                None
+            }
+            Err(payload) => crash::resume(
+                payload,
+                self.source_name.as_deref(),
+                self.interpreter.frames(),
+            ),
+        }
+    }
+
+    fn run_script(&mut self, src: &str) -> Option<Object> {
+        let start = self.timer();
+        let stmts = self.parse(src)?;
+        let lint_start = self.timer();
+        let mut config = self.warnings.clone();
+        if self.interpreter.options().strict {
+            config.set(LintRule::Shadowing, true);
+        }
+        let warnings = Linter::new(&config)
+            .with_globals(self.global_names())
+            .with_suppressions(Suppressions::from_source(src))
+            .lint(&stmts);
+        self.log_phase("lint", lint_start);
+        for warning in &warnings {
+            self.report_lint_warning(warning);
+        }
+        if warnings.iter().any(|warning| self.denies(warning)) {
+            return None;
+        }
+        let executed = self.interpreter.executed_statements();
+        let execute_start = self.timer();
+        let value = self.interpret(stmts);
+        self.log_phase("execute", execute_start);
+        if let Some(start) = start {
+            writeln!(
+                self.diagnostic_output,
+                "rlox: finished in {:?}, {} statements executed",
+                start.elapsed(),
+                self.interpreter.executed_statements() - executed
+            )
+            .expect("write diagnostic");
+        }
+        value
+    }
+
+    fn global_names(&self) -> Vec<String> {
+        self.interpreter
+            .bindings()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    fn timer(&self) -> Option<Instant> {
+        (self.verbosity >= Verbosity::Verbose).then(Instant::now)
+    }
+
+    fn log_phase(&mut self, phase: &str, start: Option<Instant>) {
+        if self.verbosity < Verbosity::Debug {
+            return;
+        }
+        if let Some(start) = start {
+            writeln!(
+                self.diagnostic_output,
+                "rlox: {} took {:?}",
+                phase,
+                start.elapsed()
+            )
+            .expect("write diagnostic");
+        }
+    }
+
+    pub fn debug(&mut self, source_name: &str, src: &str) {
+        self.source_name = Some(source_name.into());
+        let Some(stmts) = self.parse(src) else {
+            return;
+        };
+        self.print(&format!("{}\n", debugger::HELP));
+        self.interpreter
+            .set_hook(Some(Box::new(Debugger::new(source_name, src))));
+        self.interpret(stmts);
+        self.interpreter.set_hook(None);
+    }
+
+    pub fn debug_replay(&mut self, source_name: &str, src: &str, trace: &Trace) {
+        self.source_name = Some(source_name.into());
+        let Some(stmts) = self.parse(src) else {
+            return;
+        };
+        self.print(&format!("{}\n{}\n", debugger::HELP, debugger::REPLAY_HELP));
+        let rewind = Shared::new(Lock::new(Rewind::default()));
+        loop {
+            self.interpreter.reset();
+            trace.install(&mut self.interpreter);
+            let debugger =
+                Debugger::new(source_name, src).replaying(&trace.statements, rewind.clone());
+            self.interpreter.set_hook(Some(Box::new(debugger)));
+            self.interpret(stmts.clone());
+            self.interpreter.set_hook(None);
+            if rewind.borrow().target.is_none() {
+                break;
+            }
+        }
+    }
+
+    pub fn record(&mut self) -> Recorder {
+        Recorder::install(&mut self.interpreter)
+    }
+
+    #[cfg(feature = "tools")]
+    pub fn serve_dap(&mut self) {
+        let connection = Connection::stdio();
+        let mut breakpoints = BTreeSet::new();
+        let Some(launch) = dap::configure(&connection, &mut breakpoints) else {
+            return;
+        };
+        match fs::read_to_string(&launch.program) {
+            Ok(src) => {
+                self.source_name = Some(launch.program.clone());
+                if let Some(stmts) = self.parse(&src) {
+                    let debugger = DapDebugger::new(connection.clone(), launch, breakpoints);
+                    self.interpreter.set_hook(Some(Box::new(debugger)));
+                    self.interpret(stmts);
+                    self.interpreter.set_hook(None);
+                }
+            }
+            Err(err) => {
+                self.print_error(&format!("Could not read '{}': {}", launch.program, err));
+                self.had_error = true;
+            }
+        }
+        let exit_code = if self.had_error {
+            65
+        } else if self.had_runtime_error {
+            70
+        } else {
+            0
+        };
+        dap::finish(&connection, exit_code);
+    }
+
+    pub fn dump_tokens(&mut self, source_name: &str, src: &str) {
+        self.source_name = Some(source_name.into());
+        self.print_tokens(src);
+    }
+
+    fn print_tokens(&mut self, src: &str) {
+        self.source = src.to_string();
         let mut scanner = Scanner::new(src);
-        let tokens = scanner.scan_tokens();
+        for token in scanner.scan_tokens() {
+            match token {
+                Ok(token) => {
+                    let position = format!("{}:{}", token.line, token.column);
+                    let token_type = token.token_type.to_string();
+                    match token.literal {
+                        Object::None => self.print(&format!(
+                            "{:<8}{:<14}{}",
+                            position, token_type, token.lexeme
+                        )),
+                        _ => self.print(&format!(
+                            "{:<8}{:<14}{:<16}{}",
+                            position, token_type, token.lexeme, token.literal
+                        )),
+                    }
+                }
+                Err(err) => self.error(err),
+            }
+        }
+    }
+
+    pub fn dump_ast(&mut self, source_name: &str, src: &str, format: AstFormat) {
+        self.source_name = Some(source_name.into());
+        self.print_ast(src, format);
+    }
+
+    fn print_ast(&mut self, src: &str, format: AstFormat) {
+        if let Some(stmts) = self.parse(src) {
+            self.print(&AstPrinter::new(format).print(&stmts));
+        }
+    }
+
+    pub fn format(&mut self, source_name: &str, src: &str) -> Option<String> {
+        self.source_name = Some(source_name.into());
+        self.had_error = false;
+        self.parse(src)?;
+        if self.had_error {
+            return None;
+        }
+        let mut scanner = Scanner::with_comments(src);
+        let tokens: Vec<Token> = scanner.scan_tokens().iter().flatten().cloned().collect();
+        Some(Formatter::new(self.indent_width).format(&tokens))
+    }
+
+    pub fn lint(
+        &mut self,
+        source_name: &str,
+        src: &str,
+        config: &LintConfig,
+    ) -> Option<Vec<LintWarning>> {
+        self.source_name = Some(source_name.into());
+        self.had_error = false;
+        let stmts = self.parse(src)?;
+        if self.had_error {
+            return None;
+        }
+        Some(
+            Linter::new(config)
+                .with_globals(self.global_names())
+                .with_suppressions(Suppressions::from_source(src))
+                .lint(&stmts),
+        )
+    }
+
+    pub fn report_lint_warning(&mut self, warning: &LintWarning) {
+        let denied = self.denies(warning);
+        if self.verbosity == Verbosity::Quiet && !denied {
+            return;
+        }
+        let severity = if denied {
+            self.had_error = true;
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        self.report(
+            &Diagnostic::at_token(severity, &warning.message, &warning.token)
+                .with_code(warning.rule.name()),
+        );
+    }
+
+    fn denies(&self, warning: &LintWarning) -> bool {
+        self.deny_warnings
+            || (self.interpreter.options().strict && warning.rule == LintRule::Shadowing)
+    }
+
+    fn run_line(&mut self, src: &str) {
+        let Some(stmts) = self.parse(src) else {
+            return;
+        };
+        if let [Stmt::Expression(stmt)] = stmts.as_slice() {
+            match self.interpreter.evaluate(&stmt.expression) {
+                Ok(value) => {
+                    let text = self.interpreter.stringify(&value);
+                    self.print(&text);
+                    self.interpreter.define("_", value);
+                }
+                Err(err) => self.error_in_interpret(err),
+            }
+            return;
+        }
+        self.interpret(stmts);
+    }
+
+    fn parse(&mut self, src: &str) -> Option<Vec<Stmt>> {
+        self.source = src.to_string();
+        if let Some(source_name) = &self.source_name {
+            self.sources.insert(source_name.clone(), src.to_string());
+        }
+        match self.compile(src) {
+            Ok(stmts) => Some(stmts),
+            Err(diagnostics) => {
+                self.report_errors(&diagnostics);
+                None
+            }
+        }
+    }
 
-        tokens
+    fn compile(&mut self, src: &str) -> Result<Vec<Stmt>, Vec<Diagnostic>> {
+        let scan_start = self.timer();
+        let tokens = if self.literate {
+            Preprocessed::from_markdown(src).scan(self.source_name.as_deref())
+        } else {
+            self.interpreter.scan(self.source_name.as_deref(), src)
+        };
+        self.log_phase("scan", scan_start);
+
+        let mut diagnostics: Vec<Diagnostic> = tokens
             .iter()
             .filter_map(|token| token.as_ref().err())
-            .for_each(|err| self.error(err.0, &err.1));
+            .map(LoxScanError::diagnostic)
+            .collect();
 
+        let parse_start = self.timer();
         let mut parser = Parser::new(tokens.iter().flatten().collect());
-        let stmts = parser.parse();
-        match stmts {
-            Ok(stmts) => match self.interpreter.interpret(stmts) {
-                Ok(_) => (),
-                Err(err) => self.error_in_interpret(err),
-            },
+        let result = parser.parse().and_then(macros::expand);
+        self.log_phase("parse", parse_start);
+
+        let resolve_start = self.timer();
+        let result = result.and_then(resolver::resolve);
+        self.log_phase("resolve", resolve_start);
+        match result {
+            Ok(stmts) if diagnostics.is_empty() => Ok(stmts),
+            Ok(_) => Err(diagnostics),
             Err(errors) => {
-                for err in errors {
-                    self.error_in_parse(&err);
-                }
+                diagnostics.extend(errors.iter().map(LoxParseError::diagnostic));
+                Err(diagnostics)
             }
         }
     }
 
-    fn error(&mut self, line: usize, message: &str) {
-        self.report(line, "", message);
+    fn report_errors(&mut self, diagnostics: &[Diagnostic]) {
+        if diagnostics.is_empty() {
+            return;
+        }
+        self.had_error = true;
+        let shown = match self.max_errors {
+            0 => diagnostics.len(),
+            max_errors => diagnostics.len().min(max_errors),
+        };
+        for diagnostic in &diagnostics[..shown] {
+            self.report(diagnostic);
+        }
+        let suppressed = diagnostics.len() - shown;
+        if suppressed > 0
+            && self.diagnostic_sink.is_none()
+            && self.error_format == ErrorFormat::Human
+        {
+            let message = format!(
+                "{} additional error{} suppressed; use --max-errors to show more",
+                suppressed,
+                if suppressed == 1 { "" } else { "s" }
+            );
+            writeln!(
+                self.diagnostic_output,
+                "{}",
+                diagnostic::note(&message, self.color)
+            )
+            .expect("write diagnostic");
+        }
     }
 
-    fn report(&mut self, line: usize, place: &str, message: &str) {
-        eprintln!("[line {}] Error {}: {}", line, place, message);
+    fn error(&mut self, scan_err: &LoxScanError) {
+        self.report(&scan_err.diagnostic());
         self.had_error = true;
     }
 
-    fn error_in_parse(&mut self, parse_err: &LoxParseError) {
-        if parse_err.0.token_type == TokenType::Eof {
-            self.report(parse_err.0.line, "at end", &parse_err.1);
-        } else {
-            self.report(
-                parse_err.0.line,
-                &format!("at '{}'", &parse_err.0.lexeme),
-                &parse_err.1,
-            );
+    fn print(&mut self, text: &str) {
+        writeln!(self.interpreter.output(), "{}", text).expect("write output");
+    }
+
+    fn print_error(&mut self, text: &str) {
+        writeln!(self.diagnostic_output, "{}", text).expect("write diagnostic");
+    }
+
+    fn report(&mut self, diagnostic: &Diagnostic) {
+        let source_name = diagnostic.source.as_deref().or(self.source_name.as_deref());
+        let src = match source_name.and_then(|name| self.sources.get(name)) {
+            Some(src) => src.as_str(),
+            None if diagnostic.source.is_some() => "",
+            None => &self.source,
+        };
+        if let Some(diagnostic_sink) = &mut self.diagnostic_sink {
+            diagnostic_sink(diagnostic);
+            return;
         }
+        let text = match self.error_format {
+            ErrorFormat::Human => diagnostic.render(source_name, src, self.color),
+            ErrorFormat::Json => diagnostic.to_json(source_name),
+        };
+        writeln!(self.diagnostic_output, "{}", text).expect("write diagnostic");
     }
 
     fn error_in_interpret(&mut self, runtime_err: LoxRuntimeError) {
-        eprintln!("{}", runtime_err.1);
-        eprintln!("[line {}]", runtime_err.0.line);
+        self.report(&runtime_err.diagnostic());
+        self.had_runtime_error = true;
     }
 }
 
@@ -112,8 +911,124 @@ impl Default for Lox {
     }
 }
 
-pub struct LoxScanError(usize, String);
+#[derive(Debug)]
+pub enum LoxError {
+    Compile(Vec<Diagnostic>),
+    Runtime(Diagnostic),
+}
+
+impl LoxError {
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            LoxError::Compile(diagnostics) => diagnostics,
+            LoxError::Runtime(diagnostic) => std::slice::from_ref(diagnostic),
+        }
+    }
+}
+
+impl Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let diagnostics: Vec<String> = self
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        write!(f, "{}", diagnostics.join("\n"))
+    }
+}
+
+impl Error for LoxError {}
+
+#[derive(Clone, Debug)]
+pub struct LoxScanError(usize, usize, String);
+
+impl LoxScanError {
+    pub fn line(&self) -> usize {
+        self.0
+    }
+
+    pub fn column(&self) -> usize {
+        self.1
+    }
+
+    pub fn message(&self) -> &str {
+        &self.2
+    }
+
+    pub(crate) fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(Severity::Error, &self.2, self.0, self.1)
+    }
+}
+
+impl Display for LoxScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Error: {}", self.0, self.2)
+    }
+}
+
+impl Error for LoxScanError {}
+
 #[derive(Debug)]
 pub struct LoxParseError(Token, String);
 
+impl LoxParseError {
+    pub fn token(&self) -> &Token {
+        &self.0
+    }
+
+    pub fn line(&self) -> usize {
+        self.0.line
+    }
+
+    pub fn message(&self) -> &str {
+        &self.1
+    }
+
+    pub(crate) fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::at_token(Severity::Error, &self.1, &self.0)
+    }
+}
+
+impl Display for LoxParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.token_type {
+            TokenType::Eof => write!(f, "[line {}] Error at end: {}", self.0.line, self.1),
+            _ => write!(
+                f,
+                "[line {}] Error at '{}': {}",
+                self.0.line, self.0.lexeme, self.1
+            ),
+        }
+    }
+}
+
+impl Error for LoxParseError {}
+
+#[derive(Debug)]
 pub struct LoxRuntimeError(Token, String);
+
+impl LoxRuntimeError {
+    pub fn token(&self) -> &Token {
+        &self.0
+    }
+
+    pub fn line(&self) -> usize {
+        self.0.line
+    }
+
+    pub fn message(&self) -> &str {
+        &self.1
+    }
+
+    pub(crate) fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::at_token(Severity::Error, &self.1, &self.0)
+    }
+}
+
+impl Display for LoxRuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n[line {}]", self.1, self.0.line)
+    }
+}
+
+impl Error for LoxRuntimeError {}
@@ -0,0 +1,459 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    generate_ast::{Expr, FunctionStmt, Stmt},
+    scanner::Scanner,
+    token::Token,
+    token_type::TokenType,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LintRule {
+    UnusedVariable,
+    UnusedParameter,
+    UndefinedVariable,
+    Shadowing,
+    UnreachableCode,
+    SelfAssignment,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 6] = [
+        LintRule::UnusedVariable,
+        LintRule::UnusedParameter,
+        LintRule::UndefinedVariable,
+        LintRule::Shadowing,
+        LintRule::UnreachableCode,
+        LintRule::SelfAssignment,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::UnusedVariable => "unused-variable",
+            LintRule::UnusedParameter => "unused-parameter",
+            LintRule::UndefinedVariable => "undefined-variable",
+            LintRule::Shadowing => "shadowing",
+            LintRule::UnreachableCode => "unreachable-code",
+            LintRule::SelfAssignment => "self-assignment",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    pub fn group(name: &str) -> Option<Vec<Self>> {
+        if name == "all" {
+            return Some(Self::ALL.to_vec());
+        }
+        let rules: Vec<Self> = Self::ALL
+            .into_iter()
+            .filter(|rule| {
+                rule.name() == name
+                    || rule
+                        .name()
+                        .strip_prefix(name)
+                        .is_some_and(|rest| rest.starts_with('-'))
+            })
+            .collect();
+        (!rules.is_empty()).then_some(rules)
+    }
+
+    pub fn enabled_by_default(&self) -> bool {
+        !matches!(self, LintRule::UnusedParameter | LintRule::Shadowing)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LintConfig {
+    enabled: HashMap<LintRule, bool>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self {
+            enabled: HashMap::new(),
+        }
+    }
+
+    pub fn set(&mut self, rule: LintRule, enabled: bool) {
+        self.enabled.insert(rule, enabled);
+    }
+
+    pub fn is_enabled(&self, rule: LintRule) -> bool {
+        self.enabled
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.enabled_by_default())
+    }
+
+    pub fn parse(&mut self, config: &str) -> Result<(), String> {
+        for (i, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected 'rule = true|false'", i + 1));
+            };
+            let name = name.trim().trim_matches('"');
+            let rule = LintRule::from_name(name)
+                .ok_or_else(|| format!("line {}: unknown rule '{}'", i + 1, name))?;
+            let enabled = match value.trim() {
+                "true" => true,
+                "false" => false,
+                value => {
+                    return Err(format!(
+                        "line {}: expected true or false, found '{}'",
+                        i + 1,
+                        value
+                    ))
+                }
+            };
+            self.set(rule, enabled);
+        }
+        Ok(())
+    }
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Suppressions {
+    lines: HashMap<usize, Vec<LintRule>>,
+}
+
+impl Suppressions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_source(src: &str) -> Self {
+        let mut suppressions = Self::new();
+        let mut scanner = Scanner::with_comments(src);
+        for token in scanner.scan_tokens().iter().flatten() {
+            if token.token_type != TokenType::Comment {
+                continue;
+            }
+            if let Some(rules) = ignored_rules(&token.lexeme) {
+                suppressions.suppress(token.line + 1, rules);
+            }
+        }
+        suppressions
+    }
+
+    pub fn suppress(&mut self, line: usize, rules: impl IntoIterator<Item = LintRule>) {
+        self.lines.entry(line).or_default().extend(rules);
+    }
+
+    pub fn suppresses(&self, rule: LintRule, line: usize) -> bool {
+        self.lines
+            .get(&line)
+            .is_some_and(|rules| rules.contains(&rule))
+    }
+}
+
+fn ignored_rules(comment: &str) -> Option<Vec<LintRule>> {
+    let names = comment
+        .trim_start_matches('/')
+        .trim()
+        .strip_prefix("lox-ignore(")?
+        .strip_suffix(')')?;
+    Some(
+        names
+            .split(',')
+            .filter_map(|name| LintRule::group(name.trim()))
+            .flatten()
+            .collect(),
+    )
+}
+
+#[derive(Clone, Debug)]
+pub struct LintWarning {
+    pub rule: LintRule,
+    pub token: Token,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum BindingKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+}
+
+struct Binding {
+    token: Token,
+    kind: BindingKind,
+    used: bool,
+}
+
+pub struct Linter<'a> {
+    config: &'a LintConfig,
+    scopes: Vec<HashMap<String, Binding>>,
+    globals: HashSet<String>,
+    imports: bool,
+    suppressions: Suppressions,
+    warnings: Vec<LintWarning>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(config: &'a LintConfig) -> Self {
+        Self {
+            config,
+            scopes: vec![],
+            globals: HashSet::new(),
+            imports: false,
+            suppressions: Suppressions::new(),
+            warnings: vec![],
+        }
+    }
+
+    pub fn with_globals(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        self.globals.extend(names);
+        self
+    }
+
+    pub fn with_suppressions(mut self, suppressions: Suppressions) -> Self {
+        self.suppressions = suppressions;
+        self
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn lint(mut self, stmts: &[Stmt]) -> Vec<LintWarning> {
+        for stmt in stmts {
+            match stmt {
+                Stmt::Var(stmt) => self.globals.insert(stmt.name.lexeme.clone()),
+                Stmt::Function(stmt) => self.globals.insert(stmt.name.lexeme.clone()),
+                Stmt::Class(stmt) => self.globals.insert(stmt.name.lexeme.clone()),
+                Stmt::Import(_) => {
+                    self.imports = true;
+                    false
+                }
+                _ => false,
+            };
+        }
+        self.block(stmts);
+        self.warnings
+            .sort_by_key(|warning| (warning.token.line, warning.token.column));
+        self.warnings
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) {
+        let mut returned: Option<&Token> = None;
+        for stmt in stmts {
+            if let Some(keyword) = returned.take() {
+                self.warn(
+                    LintRule::UnreachableCode,
+                    keyword,
+                    format!("Code after '{}' is unreachable.", keyword.lexeme),
+                );
+            }
+            match stmt {
+                Stmt::Return(stmt) => returned = Some(&stmt._keyword),
+                Stmt::Break(stmt) => returned = Some(&stmt.keyword),
+                Stmt::Continue(stmt) => returned = Some(&stmt.keyword),
+                _ => (),
+            }
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(stmt) => {
+                self.begin_scope();
+                self.block(&stmt.statements);
+                self.end_scope();
+            }
+            Stmt::Class(stmt) => {
+                self.declare(&stmt.name, BindingKind::Class);
+                if let Some(superclass) = &stmt.superclass {
+                    self.expr(superclass);
+                }
+                for method in &stmt.methods {
+                    self.function(method);
+                }
+            }
+            Stmt::Expression(stmt) => self.expr(&stmt.expression),
+            Stmt::Function(stmt) => {
+                self.declare(&stmt.name, BindingKind::Function);
+                self.function(stmt);
+            }
+            Stmt::If(stmt) => {
+                self.expr(&stmt.condition);
+                self.stmt(&stmt.then_branch);
+                if let Some(else_branch) = &stmt.else_branch {
+                    self.stmt(else_branch);
+                }
+            }
+            Stmt::Print(stmt) => self.expr(&stmt.expression),
+            Stmt::Return(stmt) => {
+                if let Some(value) = &stmt.value {
+                    self.expr(value);
+                }
+            }
+            Stmt::While(stmt) => {
+                self.expr(&stmt.condition);
+                self.stmt(&stmt.body);
+            }
+            Stmt::Var(stmt) => {
+                if let Some(initializer) = &stmt.initializer {
+                    self.expr(initializer);
+                }
+                self.declare(&stmt.name, BindingKind::Variable);
+            }
+            Stmt::Break(_) | Stmt::Continue(_) | Stmt::Import(_) | Stmt::Macro(_) => (),
+        }
+    }
+
+    fn function(&mut self, stmt: &FunctionStmt) {
+        self.begin_scope();
+        for param in &stmt.params {
+            self.declare(param, BindingKind::Parameter);
+        }
+        self.block(&stmt.body);
+        self.end_scope();
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign(expr) => {
+                if let Expr::Variable(value) = expr.value.as_ref() {
+                    if value.name.lexeme == expr.name.lexeme {
+                        self.warn(
+                            LintRule::SelfAssignment,
+                            &expr.name,
+                            format!("'{}' is assigned to itself.", expr.name.lexeme),
+                        );
+                    }
+                }
+                self.expr(&expr.value);
+                self.resolve(&expr.name, false);
+            }
+            Expr::Binary(expr) => {
+                self.expr(&expr.left);
+                self.expr(&expr.right);
+            }
+            Expr::Call(expr) => {
+                self.expr(&expr.callee);
+                for argument in &expr.arguments {
+                    self.expr(argument);
+                }
+            }
+            Expr::Get(expr) => self.expr(&expr.object),
+            Expr::Grouping(expr) => self.expr(&expr.expression),
+            Expr::Interpolation(expr) => {
+                for part in &expr.parts {
+                    self.expr(part);
+                }
+            }
+            Expr::Literal(_) | Expr::Super(_) | Expr::This(_) => (),
+            Expr::Logical(expr) => {
+                self.expr(&expr.left);
+                self.expr(&expr.right);
+            }
+            Expr::Set(expr) => {
+                self.expr(&expr.object);
+                self.expr(&expr.value);
+            }
+            Expr::Unary(expr) => self.expr(&expr.right),
+            Expr::Variable(expr) => self.resolve(&expr.name, true),
+        }
+    }
+
+    fn declare(&mut self, name: &Token, kind: BindingKind) {
+        if self.scopes.is_empty() {
+            return;
+        }
+        let shadows = self
+            .scopes
+            .iter()
+            .rev()
+            .skip(1)
+            .any(|scope| scope.contains_key(&name.lexeme))
+            || self.globals.contains(&name.lexeme);
+        if shadows {
+            self.warn(
+                LintRule::Shadowing,
+                name,
+                format!("'{}' shadows a variable in an outer scope.", name.lexeme),
+            );
+        }
+        let binding = Binding {
+            token: name.clone(),
+            kind,
+            used: false,
+        };
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), binding);
+        }
+    }
+
+    fn resolve(&mut self, name: &Token, read: bool) {
+        if !name.global {
+            for scope in self.scopes.iter_mut().rev() {
+                if let Some(binding) = scope.get_mut(&name.lexeme) {
+                    binding.used |= read;
+                    return;
+                }
+            }
+        }
+        if !self.imports && !self.globals.contains(&name.lexeme) {
+            self.warn(
+                LintRule::UndefinedVariable,
+                name,
+                format!("'{}' is never defined.", name.lexeme),
+            );
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        for binding in scope.into_values().filter(|binding| !binding.used) {
+            let (rule, message) = match binding.kind {
+                BindingKind::Variable => (
+                    LintRule::UnusedVariable,
+                    format!("Local variable '{}' is never used.", binding.token.lexeme),
+                ),
+                BindingKind::Function => (
+                    LintRule::UnusedVariable,
+                    format!("Local function '{}' is never used.", binding.token.lexeme),
+                ),
+                BindingKind::Class => (
+                    LintRule::UnusedVariable,
+                    format!("Local class '{}' is never used.", binding.token.lexeme),
+                ),
+                BindingKind::Parameter => (
+                    LintRule::UnusedParameter,
+                    format!("Parameter '{}' is never used.", binding.token.lexeme),
+                ),
+            };
+            self.warn(rule, &binding.token, message);
+        }
+    }
+
+    fn warn(&mut self, rule: LintRule, token: &Token, message: String) {
+        if self.config.is_enabled(rule)
+            && token.expansion.is_none()
+            && !self.suppressions.suppresses(rule, token.line)
+        {
+            self.warnings.push(LintWarning {
+                rule,
+                token: token.clone(),
+                message,
+            });
+        }
+    }
+}
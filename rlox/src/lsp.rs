@@ -0,0 +1,341 @@
+use std::{collections::HashMap, io};
+
+use serde_json::{json, Value};
+
+use crate::{
+    bindings::BindingTable,
+    diagnostic::{Diagnostic, Severity},
+    formatter::Formatter,
+    generate_ast::Stmt,
+    highlight::{semantic_tokens, SemanticKind},
+    interpreter::Interpreter,
+    lint::{LintConfig, Linter, Suppressions},
+    macros,
+    parser::Parser,
+    resolver,
+    scanner::Scanner,
+    token::Token,
+    token_type::TokenType,
+    transport::{read_message, write_message},
+};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+pub struct LanguageServer {
+    documents: HashMap<String, String>,
+    globals: Vec<String>,
+}
+
+impl LanguageServer {
+    pub fn new() -> Self {
+        Self {
+            documents: HashMap::new(),
+            globals: Interpreter::new()
+                .bindings()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect(),
+        }
+    }
+
+    pub fn serve(mut self) {
+        let mut stdin = io::stdin().lock();
+        while let Some(message) = read_message(&mut stdin) {
+            let params = &message["params"];
+            let result = match message["method"].as_str().unwrap_or_default() {
+                "exit" => return,
+                "initialize" => Ok(json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "definitionProvider": true,
+                        "hoverProvider": true,
+                        "renameProvider": true,
+                        "documentFormattingProvider": true,
+                        "semanticTokensProvider": {
+                            "legend": {
+                                "tokenTypes": SemanticKind::ALL.map(|kind| kind.name()),
+                                "tokenModifiers": [],
+                            },
+                            "full": true,
+                        },
+                    },
+                    "serverInfo": { "name": "rlox", "version": env!("CARGO_PKG_VERSION") },
+                })),
+                "shutdown" => Ok(Value::Null),
+                "textDocument/didOpen" => {
+                    let document = &params["textDocument"];
+                    self.update(&document["uri"], &document["text"]);
+                    continue;
+                }
+                "textDocument/didChange" => {
+                    if let Some(change) = params["contentChanges"].as_array().and_then(|c| c.last())
+                    {
+                        self.update(&params["textDocument"]["uri"], &change["text"]);
+                    }
+                    continue;
+                }
+                "textDocument/didClose" => {
+                    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                    self.documents.remove(uri);
+                    notify(
+                        "textDocument/publishDiagnostics",
+                        json!({ "uri": uri, "diagnostics": [] }),
+                    );
+                    continue;
+                }
+                "textDocument/definition" => Ok(self.definition(params)),
+                "textDocument/hover" => Ok(self.hover(params)),
+                "textDocument/rename" => self.rename(params),
+                "textDocument/formatting" => Ok(self.formatting(params)),
+                "textDocument/semanticTokens/full" => Ok(self.semantic_tokens(params)),
+                _ if message.get("id").is_none() => continue,
+                method => Err((METHOD_NOT_FOUND, format!("Unknown method '{}'.", method))),
+            };
+            let response = match result {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": message["id"], "result": result }),
+                Err((code, message_text)) => json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": { "code": code, "message": message_text },
+                }),
+            };
+            write_message(&response);
+        }
+    }
+
+    fn update(&mut self, uri: &Value, text: &Value) {
+        let (Some(uri), Some(text)) = (uri.as_str(), text.as_str()) else {
+            return;
+        };
+        let diagnostics: Vec<Value> = diagnostics(text, &self.globals)
+            .iter()
+            .map(lsp_diagnostic)
+            .collect();
+        notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        );
+        self.documents.insert(uri.into(), text.into());
+    }
+
+    fn document<'a>(&'a self, params: &'a Value) -> Option<(&'a str, &'a str)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let text = self.documents.get(uri)?;
+        Some((uri, text))
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let Some((uri, text)) = self.document(params) else {
+            return Value::Null;
+        };
+        let Some(stmts) = parse(text) else {
+            return Value::Null;
+        };
+        let (line, column) = position(&params["position"]);
+        match BindingTable::new(&stmts).declaration_at(line, column) {
+            Some(declaration) => json!({ "uri": uri, "range": range(&declaration.token) }),
+            None => Value::Null,
+        }
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((_, text)) = self.document(params) else {
+            return Value::Null;
+        };
+        let Some(stmts) = parse(text) else {
+            return Value::Null;
+        };
+        let (line, column) = position(&params["position"]);
+        let bindings = BindingTable::new(&stmts);
+        let Some(declaration) = bindings.declaration_at(line, column) else {
+            return Value::Null;
+        };
+        let mut value = format!("```lox\n{}\n```", declaration.signature);
+        if let Some(doc) = doc_comment(text, declaration.token.line) {
+            value.push_str("\n\n");
+            value.push_str(&doc);
+        }
+        json!({ "contents": { "kind": "markdown", "value": value } })
+    }
+
+    fn rename(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let new_name = params["newName"].as_str().unwrap_or_default();
+        if !is_identifier(new_name) {
+            return Err((
+                INVALID_PARAMS,
+                format!("'{}' is not a valid identifier.", new_name),
+            ));
+        }
+        let Some((uri, text)) = self.document(params) else {
+            return Ok(Value::Null);
+        };
+        let Some(stmts) = parse(text) else {
+            return Ok(Value::Null);
+        };
+        let (line, column) = position(&params["position"]);
+        let edits: Vec<Value> = BindingTable::new(&stmts)
+            .occurrences_at(line, column)
+            .iter()
+            .map(|token| json!({ "range": range(token), "newText": new_name }))
+            .collect();
+        if edits.is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(json!({ "changes": { uri: edits } }))
+    }
+
+    fn semantic_tokens(&self, params: &Value) -> Value {
+        let Some((_, text)) = self.document(params) else {
+            return Value::Null;
+        };
+        let mut data = vec![];
+        let (mut line, mut column) = (1, 1);
+        for token in semantic_tokens(text) {
+            let delta_line = token.line - line;
+            let delta_column = if delta_line == 0 {
+                token.column - column
+            } else {
+                token.column - 1
+            };
+            let kind = SemanticKind::ALL
+                .iter()
+                .position(|kind| *kind == token.kind);
+            data.extend([delta_line, delta_column, token.length, kind.unwrap_or(0), 0]);
+            (line, column) = (token.line, token.column);
+        }
+        json!({ "data": data })
+    }
+
+    fn formatting(&self, params: &Value) -> Value {
+        let Some((_, text)) = self.document(params) else {
+            return Value::Null;
+        };
+        if parse(text).is_none() {
+            return Value::Null;
+        }
+        let indent_width = params["options"]["tabSize"].as_u64().unwrap_or(4) as usize;
+        let mut scanner = Scanner::with_comments(text);
+        let tokens: Vec<Token> = scanner.scan_tokens().iter().flatten().cloned().collect();
+        let formatted = Formatter::new(indent_width).format(&tokens);
+        json!([{
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": text.lines().count(), "character": 0 },
+            },
+            "newText": formatted,
+        }])
+    }
+}
+
+impl Default for LanguageServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn notify(method: &str, params: Value) {
+    write_message(&json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+}
+
+fn parse(text: &str) -> Option<Vec<Stmt>> {
+    let mut scanner = Scanner::new(text);
+    let tokens = scanner.scan_tokens();
+    if tokens.iter().any(|token| token.is_err()) {
+        return None;
+    }
+    Parser::new(tokens.iter().flatten().collect()).parse().ok()
+}
+
+fn diagnostics(text: &str, globals: &[String]) -> Vec<Diagnostic> {
+    let mut scanner = Scanner::new(text);
+    let tokens = scanner.scan_tokens();
+    let mut diagnostics: Vec<Diagnostic> = tokens
+        .iter()
+        .filter_map(|token| token.as_ref().err())
+        .map(|err| Diagnostic::new(Severity::Error, &err.2, err.0, err.1))
+        .collect();
+    match Parser::new(tokens.iter().flatten().collect())
+        .parse()
+        .and_then(macros::expand)
+        .and_then(resolver::resolve)
+    {
+        Ok(stmts) => diagnostics.extend(
+            Linter::new(&LintConfig::new())
+                .with_globals(globals.iter().cloned())
+                .with_suppressions(Suppressions::from_source(text))
+                .lint(&stmts)
+                .iter()
+                .map(|warning| {
+                    Diagnostic::at_token(Severity::Warning, &warning.message, &warning.token)
+                        .with_code(warning.rule.name())
+                }),
+        ),
+        Err(errors) => diagnostics.extend(
+            errors
+                .iter()
+                .map(|err| Diagnostic::at_token(Severity::Error, &err.1, &err.0)),
+        ),
+    }
+    diagnostics
+}
+
+fn lsp_diagnostic(diagnostic: &Diagnostic) -> Value {
+    let line = diagnostic.line.saturating_sub(1);
+    let character = diagnostic.column.saturating_sub(1);
+    json!({
+        "range": {
+            "start": { "line": line, "character": character },
+            "end": { "line": line, "character": character + diagnostic.length },
+        },
+        "severity": match diagnostic.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+        },
+        "code": diagnostic.code,
+        "source": "rlox",
+        "message": diagnostic.message,
+    })
+}
+
+fn position(position: &Value) -> (usize, usize) {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+    (line + 1, character + 1)
+}
+
+fn range(token: &Token) -> Value {
+    let line = token.line - 1;
+    let character = token.column - 1;
+    json!({
+        "start": { "line": line, "character": character },
+        "end": { "line": line, "character": character + token.lexeme.chars().count() },
+    })
+}
+
+fn doc_comment(text: &str, line: usize) -> Option<String> {
+    let lines: Vec<&str> = text.lines().take(line - 1).collect();
+    let doc: Vec<&str> = lines
+        .iter()
+        .rev()
+        .map(|line| line.trim())
+        .take_while(|line| line.starts_with("//"))
+        .map(|line| line.trim_start_matches('/').trim())
+        .collect();
+    if doc.is_empty() {
+        return None;
+    }
+    Some(doc.into_iter().rev().collect::<Vec<_>>().join("\n"))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut scanner = Scanner::new(name);
+    match scanner.scan_tokens().as_slice() {
+        [Ok(token), Ok(eof)] => {
+            token.token_type == TokenType::Identifier
+                && token.lexeme == name
+                && eof.token_type == TokenType::Eof
+        }
+        _ => false,
+    }
+}
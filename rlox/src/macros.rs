@@ -0,0 +1,422 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    generate_ast::{BlockStmt, Expr, FunctionStmt, MacroStmt, Stmt},
+    jit::JitCache,
+    sync::Shared,
+    token::Token,
+    token_type::TokenType,
+    LoxParseError,
+};
+
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+/// Replaces statement-position calls of top-level `macro` declarations with
+/// their bodies. Locals declared inside a body are renamed so that they can't
+/// capture or shadow names at the call site, other names in a body are bound
+/// to globals, and every token taken from a body remembers the call it was
+/// expanded from.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub fn expand(stmts: Vec<Stmt>) -> Result<Vec<Stmt>, Vec<LoxParseError>> {
+    if !stmts.iter().any(|stmt| matches!(stmt, Stmt::Macro(_))) {
+        return Ok(stmts);
+    }
+    let mut expander = Expander::default();
+    let mut expanded = vec![];
+    for stmt in stmts {
+        match stmt {
+            Stmt::Macro(stmt) => {
+                expander
+                    .macros
+                    .insert(stmt.name.lexeme.clone(), Shared::new(stmt));
+            }
+            stmt => expanded.push(expander.stmt(stmt, 0)),
+        }
+    }
+    if !expander.errors.is_empty() {
+        return Err(expander.errors);
+    }
+    Ok(expanded)
+}
+
+#[derive(Default)]
+struct Expander {
+    macros: HashMap<String, Shared<MacroStmt>>,
+    expansions: usize,
+    errors: Vec<LoxParseError>,
+}
+
+impl Expander {
+    fn stmts(&mut self, stmts: Vec<Stmt>, depth: usize) -> Vec<Stmt> {
+        stmts
+            .into_iter()
+            .map(|stmt| self.stmt(stmt, depth))
+            .collect()
+    }
+
+    fn stmt(&mut self, stmt: Stmt, depth: usize) -> Stmt {
+        match stmt {
+            Stmt::Expression(stmt) => {
+                if let Expr::Call(call) = &stmt.expression {
+                    if let Expr::Variable(callee) = call.callee.as_ref() {
+                        if let Some(definition) = self.macros.get(&callee.name.lexeme).cloned() {
+                            call.arguments
+                                .iter()
+                                .for_each(|argument| self.expr(argument));
+                            return self.call(&definition, &callee.name, &call.arguments, depth);
+                        }
+                    }
+                }
+                self.expr(&stmt.expression);
+                Stmt::Expression(stmt)
+            }
+            Stmt::Block(mut stmt) => {
+                stmt.statements = self.stmts(stmt.statements, depth);
+                Stmt::Block(stmt)
+            }
+            Stmt::Class(mut stmt) => {
+                for method in &mut stmt.methods {
+                    method.body = self.stmts(std::mem::take(&mut method.body), depth);
+                }
+                Stmt::Class(stmt)
+            }
+            Stmt::Function(mut stmt) => {
+                stmt.body = self.stmts(stmt.body, depth);
+                Stmt::Function(stmt)
+            }
+            Stmt::If(mut stmt) => {
+                self.expr(&stmt.condition);
+                stmt.then_branch = Box::new(self.stmt(*stmt.then_branch, depth));
+                stmt.else_branch = stmt
+                    .else_branch
+                    .map(|branch| Box::new(self.stmt(*branch, depth)));
+                Stmt::If(stmt)
+            }
+            Stmt::While(mut stmt) => {
+                self.expr(&stmt.condition);
+                stmt.body = Box::new(self.stmt(*stmt.body, depth));
+                if let Some(increment) = &stmt.increment {
+                    self.expr(increment);
+                }
+                Stmt::While(stmt)
+            }
+            Stmt::Print(stmt) => {
+                self.expr(&stmt.expression);
+                Stmt::Print(stmt)
+            }
+            Stmt::Return(stmt) => {
+                if let Some(value) = &stmt.value {
+                    self.expr(value);
+                }
+                Stmt::Return(stmt)
+            }
+            Stmt::Var(stmt) => {
+                if let Some(initializer) = &stmt.initializer {
+                    self.expr(initializer);
+                }
+                Stmt::Var(stmt)
+            }
+            stmt @ (Stmt::Break(_) | Stmt::Continue(_) | Stmt::Import(_) | Stmt::Macro(_)) => stmt,
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign(expr) => self.expr(&expr.value),
+            Expr::Binary(expr) => {
+                self.expr(&expr.left);
+                self.expr(&expr.right);
+            }
+            Expr::Call(expr) => {
+                if let Expr::Variable(callee) = expr.callee.as_ref() {
+                    if self.macros.contains_key(&callee.name.lexeme) {
+                        self.errors.push(LoxParseError(
+                            callee.name.clone(),
+                            format!(
+                                "Macro '{}' can only be called as a statement.",
+                                callee.name.lexeme
+                            ),
+                        ));
+                    }
+                }
+                self.expr(&expr.callee);
+                expr.arguments
+                    .iter()
+                    .for_each(|argument| self.expr(argument));
+            }
+            Expr::Get(expr) => self.expr(&expr.object),
+            Expr::Grouping(expr) => self.expr(&expr.expression),
+            Expr::Interpolation(expr) => expr.parts.iter().for_each(|part| self.expr(part)),
+            Expr::Logical(expr) => {
+                self.expr(&expr.left);
+                self.expr(&expr.right);
+            }
+            Expr::Set(expr) => {
+                self.expr(&expr.object);
+                self.expr(&expr.value);
+            }
+            Expr::Unary(expr) => self.expr(&expr.right),
+            Expr::Literal(_) | Expr::Super(_) | Expr::This(_) | Expr::Variable(_) => (),
+        }
+    }
+
+    fn call(
+        &mut self,
+        definition: &MacroStmt,
+        site: &Token,
+        arguments: &[Expr],
+        depth: usize,
+    ) -> Stmt {
+        let empty = Stmt::Block(BlockStmt::new(vec![]));
+        if arguments.len() != definition.params.len() {
+            self.errors.push(LoxParseError(
+                site.clone(),
+                format!(
+                    "Macro '{}' expects {} arguments but got {}.",
+                    definition.name.lexeme,
+                    definition.params.len(),
+                    arguments.len()
+                ),
+            ));
+            return empty;
+        }
+        if depth >= MAX_EXPANSION_DEPTH {
+            self.errors.push(LoxParseError(
+                site.clone(),
+                format!(
+                    "Macro expansion of '{}' is too deep.",
+                    definition.name.lexeme
+                ),
+            ));
+            return empty;
+        }
+
+        self.expansions += 1;
+        let mut declared = HashSet::new();
+        declarations(&definition.body, &mut declared);
+        let mut substitution = Substitution {
+            renames: declared
+                .into_iter()
+                .map(|name| {
+                    let renamed = format!("{}#{}", name, self.expansions);
+                    (name, renamed)
+                })
+                .collect(),
+            arguments: definition
+                .params
+                .iter()
+                .map(|param| param.lexeme.clone())
+                .zip(arguments.iter().cloned())
+                .collect(),
+            site: Shared::new(site.clone()),
+            errors: vec![],
+        };
+        let mut body = definition.body.clone();
+        body.iter_mut().for_each(|stmt| substitution.stmt(stmt));
+        self.errors.append(&mut substitution.errors);
+
+        let body = self.stmts(body, depth + 1);
+        Stmt::Block(BlockStmt::new(body))
+    }
+}
+
+fn declarations(stmts: &[Stmt], names: &mut HashSet<String>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Var(stmt) => {
+                names.insert(stmt.name.lexeme.clone());
+            }
+            Stmt::Function(stmt) => {
+                names.insert(stmt.name.lexeme.clone());
+                names.extend(stmt.params.iter().map(|param| param.lexeme.clone()));
+                declarations(&stmt.body, names);
+            }
+            Stmt::Class(stmt) => {
+                names.insert(stmt.name.lexeme.clone());
+                for method in &stmt.methods {
+                    names.extend(method.params.iter().map(|param| param.lexeme.clone()));
+                    declarations(&method.body, names);
+                }
+            }
+            Stmt::Block(stmt) => declarations(&stmt.statements, names),
+            Stmt::If(stmt) => {
+                declarations(std::slice::from_ref(&stmt.then_branch), names);
+                if let Some(branch) = &stmt.else_branch {
+                    declarations(std::slice::from_ref(branch), names);
+                }
+            }
+            Stmt::While(stmt) => declarations(std::slice::from_ref(&stmt.body), names),
+            _ => (),
+        }
+    }
+}
+
+struct Substitution {
+    renames: HashMap<String, String>,
+    arguments: HashMap<String, Expr>,
+    site: Shared<Token>,
+    errors: Vec<LoxParseError>,
+}
+
+impl Substitution {
+    fn token(&self, token: &mut Token) {
+        if token.token_type == TokenType::Identifier {
+            if let Some(renamed) = self.renames.get(&token.lexeme) {
+                token.lexeme = renamed.clone();
+            }
+        }
+        token.expansion = Some(self.site.clone());
+    }
+
+    fn stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Block(stmt) => stmt.statements.iter_mut().for_each(|stmt| self.stmt(stmt)),
+            Stmt::Expression(stmt) => self.expr(&mut stmt.expression),
+            Stmt::Class(stmt) => {
+                self.token(&mut stmt.name);
+                if let Some(superclass) = &mut stmt.superclass {
+                    self.expr(superclass);
+                }
+                for method in &mut stmt.methods {
+                    // Method and property names are looked up on the
+                    // instance, not in scope, so they are never renamed.
+                    method.name.expansion = Some(self.site.clone());
+                    self.function(method);
+                }
+            }
+            Stmt::Function(stmt) => {
+                self.token(&mut stmt.name);
+                self.function(stmt);
+            }
+            Stmt::If(stmt) => {
+                self.token(&mut stmt.keyword);
+                self.expr(&mut stmt.condition);
+                self.stmt(&mut stmt.then_branch);
+                if let Some(branch) = &mut stmt.else_branch {
+                    self.stmt(branch);
+                }
+            }
+            Stmt::Print(stmt) => {
+                self.token(&mut stmt.keyword);
+                self.expr(&mut stmt.expression);
+            }
+            Stmt::Return(stmt) => {
+                self.token(&mut stmt._keyword);
+                if let Some(value) = &mut stmt.value {
+                    self.expr(value);
+                }
+            }
+            Stmt::While(stmt) => {
+                self.token(&mut stmt.keyword);
+                self.expr(&mut stmt.condition);
+                self.stmt(&mut stmt.body);
+                if let Some(increment) = &mut stmt.increment {
+                    self.expr(increment);
+                }
+            }
+            Stmt::Var(stmt) => {
+                self.token(&mut stmt.name);
+                if let Some(initializer) = &mut stmt.initializer {
+                    self.expr(initializer);
+                }
+            }
+            Stmt::Break(stmt) => self.token(&mut stmt.keyword),
+            Stmt::Continue(stmt) => self.token(&mut stmt.keyword),
+            Stmt::Import(stmt) => {
+                self.token(&mut stmt.keyword);
+                self.token(&mut stmt.name);
+            }
+            Stmt::Macro(_) => (),
+        }
+    }
+
+    /// Renames a variable the body declares. Any other name is free in the
+    /// body, and as macros are only declared at the top level, it can only
+    /// mean a global.
+    fn variable(&self, name: &mut Token) {
+        name.global = !self.renames.contains_key(&name.lexeme);
+        self.token(name);
+    }
+
+    fn function(&mut self, stmt: &mut FunctionStmt) {
+        stmt.params.iter_mut().for_each(|param| self.token(param));
+        stmt.jit = JitCache::default();
+        stmt.body.iter_mut().for_each(|stmt| self.stmt(stmt));
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Variable(variable) => {
+                if !self.renames.contains_key(&variable.name.lexeme) {
+                    if let Some(argument) = self.arguments.get(&variable.name.lexeme) {
+                        *expr = argument.clone();
+                        return;
+                    }
+                }
+                self.variable(&mut variable.name);
+            }
+            Expr::Assign(assign) => {
+                if !self.renames.contains_key(&assign.name.lexeme) {
+                    match self.arguments.get(&assign.name.lexeme) {
+                        Some(Expr::Variable(argument)) => assign.name = argument.name.clone(),
+                        Some(_) => {
+                            self.token(&mut assign.name);
+                            self.errors.push(LoxParseError(
+                                assign.name.clone(),
+                                format!(
+                                    "Macro parameter '{}' is assigned, so its argument must be a variable.",
+                                    assign.name.lexeme
+                                ),
+                            ));
+                        }
+                        None => self.variable(&mut assign.name),
+                    }
+                } else {
+                    self.token(&mut assign.name);
+                }
+                self.expr(&mut assign.value);
+            }
+            Expr::Binary(expr) => {
+                self.expr(&mut expr.left);
+                self.token(&mut expr.operator);
+                self.expr(&mut expr.right);
+            }
+            Expr::Call(expr) => {
+                self.expr(&mut expr.callee);
+                self.token(&mut expr.paren);
+                expr.arguments
+                    .iter_mut()
+                    .for_each(|argument| self.expr(argument));
+            }
+            Expr::Get(expr) => {
+                self.expr(&mut expr.object);
+                expr.name.expansion = Some(self.site.clone());
+            }
+            Expr::Grouping(expr) => self.expr(&mut expr.expression),
+            Expr::Interpolation(expr) => {
+                self.token(&mut expr.quote);
+                expr.parts.iter_mut().for_each(|part| self.expr(part));
+            }
+            Expr::Logical(expr) => {
+                self.expr(&mut expr.left);
+                self.token(&mut expr.operator);
+                self.expr(&mut expr.right);
+            }
+            Expr::Set(expr) => {
+                self.expr(&mut expr.object);
+                expr.name.expansion = Some(self.site.clone());
+                self.expr(&mut expr.value);
+            }
+            Expr::Super(expr) => {
+                self.token(&mut expr.keyword);
+                expr.method.expansion = Some(self.site.clone());
+            }
+            Expr::This(expr) => self.token(&mut expr.keyword),
+            Expr::Unary(expr) => {
+                self.token(&mut expr.operator);
+                self.expr(&mut expr.right);
+            }
+            Expr::Literal(_) => (),
+        }
+    }
+}
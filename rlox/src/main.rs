@@ -1,19 +1,442 @@
-use std::env::args;
+use std::{
+    cmp::Reverse,
+    fs,
+    io::{self, IsTerminal, Read},
+    path::{Path, PathBuf},
+    process,
+};
 
-use rlox::Lox;
+use clap::{CommandFactory, Parser};
+use cli::{
+    parse_capability_names, parse_color, parse_error_format, seconds_to_duration, Cli, Command,
+    GlobalArgs, LintArgs, LintFormat, RunArgs,
+};
+use config::Config;
+use rlox::{
+    install_panic_hook, json_string, ColorChoice, ErrorCode, ErrorFormat, InterpreterOptions,
+    LanguageServer, LintConfig, LintRule, Lox, Trace, Verbosity,
+};
+use test_runner::TestRunner;
+
+mod cli;
+mod config;
+mod test_runner;
 
 fn main() {
-    match args().len() {
-        2 => {
-            let mut lox = Lox::new();
-            lox.run_file(args().nth(1).unwrap());
+    install_panic_hook();
+    let cli = Cli::parse();
+    cli.check_conflicts();
+    if let Some(code) = &cli.explain {
+        explain(code);
+        return;
+    }
+    let config = Config::load().unwrap_or_else(|err| invalid_config(&err));
+    let mut lox = new_lox(&cli.global, &config);
+    match cli.command {
+        None => run(lox, cli.run),
+        Some(Command::Run(args)) => run(lox, args),
+        Some(Command::Repl) => lox.run_prompt(),
+        Some(Command::Serve { listen }) => serve(lox, &listen),
+        Some(Command::Debug { script, replay }) => debug(lox, &script, replay.as_deref()),
+        Some(Command::Dap) => lox.serve_dap(),
+        Some(Command::Lsp) => LanguageServer::new().serve(),
+        Some(Command::Fmt { files, check }) => fmt(lox, files, check),
+        Some(Command::Lint(args)) => lint(lox, warnings(&cli.global, &config), args),
+        Some(Command::Test { paths, filter }) => test(&paths, filter, cli.global.quiet),
+        Some(Command::Compile { .. }) => unavailable("compile"),
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(shell, &mut Cli::command(), "rlox", &mut io::stdout())
+        }
+        Some(Command::Manpage) => manpage(),
+    }
+}
+
+fn explain(code: &str) {
+    match ErrorCode::find(code) {
+        Some(error_code) => println!("{}\n\n{}", error_code.code, error_code.explanation),
+        None => {
+            eprintln!("'{}' is not a valid error code.", code);
+            process::exit(1);
+        }
+    }
+}
+
+fn new_lox(global: &GlobalArgs, config: &Config) -> Lox {
+    let diagnostics = &config.diagnostics;
+    let error_format = match (global.error_format, &diagnostics.error_format) {
+        (Some(error_format), _) => error_format,
+        (None, Some(name)) => parse_error_format(name).unwrap_or_else(|err| invalid_config(&err)),
+        (None, None) => ErrorFormat::Human,
+    };
+    let color = match (global.color, &diagnostics.color) {
+        (Some(color), _) => color,
+        (None, Some(name)) => parse_color(name).unwrap_or_else(|err| invalid_config(&err)),
+        (None, None) => ColorChoice::Auto,
+    };
+
+    let mut lox = Lox::new();
+    lox.set_error_format(error_format);
+    lox.set_color(color);
+    lox.set_warnings(warnings(global, config));
+    lox.set_deny_warnings(global.deny_warnings || diagnostics.deny_warnings == Some(true));
+    lox.set_options(sandbox(global, config));
+    lox.set_strict(global.strict);
+    if let Some(max_errors) = global.max_errors.or(diagnostics.max_errors) {
+        lox.set_max_errors(max_errors);
+    }
+    lox.set_verbosity(verbosity(global));
+    if let Some(indent_width) = config.format.indent_width {
+        lox.set_indent_width(indent_width);
+    }
+    if let Some(prompt) = &config.repl.prompt {
+        lox.set_prompt(prompt);
+    }
+    lox.set_prelude(config.prelude());
+    lox
+}
+
+fn sandbox(global: &GlobalArgs, config: &Config) -> InterpreterOptions {
+    let sandbox = &config.sandbox;
+    let mut options = InterpreterOptions::new();
+    options.max_steps = global.max_steps.or(sandbox.max_steps);
+    options.max_duration = match (global.max_duration, sandbox.max_duration) {
+        (Some(max_duration), _) => Some(max_duration),
+        (None, Some(seconds)) => {
+            Some(seconds_to_duration(seconds).unwrap_or_else(|err| invalid_config(&err)))
+        }
+        (None, None) => None,
+    };
+    options.max_call_depth = global.max_call_depth.or(sandbox.max_call_depth);
+    let capabilities = match (&global.capabilities, &sandbox.capabilities) {
+        (Some(capabilities), _) => capabilities.0.clone(),
+        (None, Some(names)) => parse_capability_names(names.iter().map(String::as_str))
+            .unwrap_or_else(|err| invalid_config(&err)),
+        (None, None) => return options,
+    };
+    options.capabilities = capabilities.into_iter().collect();
+    options
+}
+
+fn invalid_config(err: &str) -> ! {
+    eprintln!("Invalid configuration: {}", err);
+    process::exit(64);
+}
+
+fn verbosity(global: &GlobalArgs) -> Verbosity {
+    match (global.quiet, global.verbose) {
+        (true, _) => Verbosity::Quiet,
+        (false, 0) => Verbosity::Normal,
+        (false, 1) => Verbosity::Verbose,
+        (false, _) => Verbosity::Debug,
+    }
+}
+
+fn warnings(global: &GlobalArgs, settings: &Config) -> LintConfig {
+    let mut config = LintConfig::new();
+    let mut configured: Vec<(Vec<LintRule>, bool)> = settings
+        .warnings
+        .iter()
+        .map(|(name, enabled)| match LintRule::group(name) {
+            Some(rules) => (rules, *enabled),
+            None => invalid_config(&format!("unknown lint rule or group '{}'", name)),
+        })
+        .collect();
+    configured.sort_by_key(|(rules, _)| Reverse(rules.len()));
+    for (rules, enabled) in configured {
+        for rule in rules {
+            config.set(rule, enabled);
+        }
+    }
+    for rule in global.warn.iter().flat_map(|group| &group.0) {
+        config.set(*rule, true);
+    }
+    for rule in global.allow.iter().flat_map(|group| &group.0) {
+        config.set(*rule, false);
+    }
+    config
+}
+
+fn exit_on_error(lox: &Lox) {
+    if lox.had_error() {
+        process::exit(65);
+    }
+    if lox.had_runtime_error() {
+        process::exit(70);
+    }
+}
+
+fn run(mut lox: Lox, args: RunArgs) {
+    lox.set_literate(args.literate);
+    if let Some(trace) = &args.record {
+        record(lox, &args.scripts, trace);
+        return;
+    }
+    let dump = args.tokens || args.ast.is_some();
+    let eval = args.eval.is_some();
+    if args.scripts.len() > 1 {
+        if dump {
+            eprintln!("--tokens and --ast take a single script.");
+            process::exit(64);
+        }
+        for path in &args.scripts {
+            let name = path.to_string_lossy().to_string();
+            if let Err(err) = lox.run_file(name.clone()) {
+                eprintln!("Could not read '{}': {}", name, err);
+                process::exit(66);
+            }
+            exit_on_error(&lox);
+        }
+        return;
+    }
+    let (name, src) = match (args.eval, args.scripts.into_iter().next()) {
+        (Some(src), _) => ("<eval>".to_string(), src),
+        (None, Some(path)) if path == Path::new("-") => ("<stdin>".to_string(), read_stdin()),
+        (None, Some(path)) if path.is_dir() && !dump => {
+            if let Err(err) = lox.run_project(&path) {
+                eprintln!("Could not run project: {}", err);
+                process::exit(66);
+            }
+            exit_on_error(&lox);
+            return;
+        }
+        (None, Some(path)) => {
+            let name = path.to_string_lossy().to_string();
+            if !dump {
+                if let Err(err) = lox.run_file(name.clone()) {
+                    eprintln!("Could not read '{}': {}", name, err);
+                    process::exit(66);
+                }
+                exit_on_error(&lox);
+                return;
+            }
+            match Lox::read_file(&name) {
+                Ok(src) => (name, src),
+                Err(err) => {
+                    eprintln!("Could not read '{}': {}", name, err);
+                    process::exit(66);
+                }
+            }
         }
-        1 => {
-            let mut lox = Lox::new();
+        (None, None) if !io::stdin().is_terminal() => ("<stdin>".to_string(), read_stdin()),
+        (None, None) if !dump => {
             lox.run_prompt();
+            return;
         }
-        _ => {
-            println!("Usage: rlox [script]");
+        (None, None) => {
+            eprintln!("No input to dump; pass a script, -e <code>, or -.");
+            process::exit(64);
         }
+    };
+
+    if args.tokens {
+        lox.dump_tokens(&name, &src);
+    } else if let Some(format) = args.ast {
+        lox.dump_ast(&name, &src, format);
+    } else if eval {
+        lox.run_eval(&src);
+        exit_on_error(&lox);
+    } else {
+        lox.run_named(&name, &src);
+        exit_on_error(&lox);
+    }
+}
+
+fn record(mut lox: Lox, scripts: &[PathBuf], trace: &Path) {
+    let [script] = scripts else {
+        eprintln!("--record takes a single script.");
+        process::exit(64);
+    };
+    let recorder = lox.record();
+    let name = script.to_string_lossy().to_string();
+    if let Err(err) = lox.run_file(name.clone()) {
+        eprintln!("Could not read '{}': {}", name, err);
+        process::exit(66);
+    }
+    if let Err(err) = fs::write(trace, recorder.trace().to_text()) {
+        eprintln!("Could not write '{}': {}", trace.display(), err);
+        process::exit(74);
     }
+    exit_on_error(&lox);
+}
+
+fn debug(mut lox: Lox, script: &Path, replay: Option<&Path>) {
+    let name = script.to_string_lossy();
+    let src = match fs::read_to_string(script) {
+        Ok(src) => src,
+        Err(err) => {
+            eprintln!("Could not read '{}': {}", name, err);
+            process::exit(66);
+        }
+    };
+    match replay {
+        Some(path) => {
+            let trace = fs::read_to_string(path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| Trace::parse(&text));
+            match trace {
+                Ok(trace) => lox.debug_replay(&name, &src, &trace),
+                Err(err) => {
+                    eprintln!("Could not load trace '{}': {}", path.display(), err);
+                    process::exit(66);
+                }
+            }
+        }
+        None => lox.debug(&name, &src),
+    }
+    exit_on_error(&lox);
+}
+
+fn fmt(mut lox: Lox, files: Vec<PathBuf>, check: bool) {
+    if files.is_empty() || files == [Path::new("-")] {
+        let src = read_stdin();
+        match lox.format("<stdin>", &src) {
+            Some(formatted) if check => {
+                if formatted != src {
+                    process::exit(1);
+                }
+            }
+            Some(formatted) => print!("{}", formatted),
+            None => process::exit(65),
+        }
+        return;
+    }
+
+    let mut failed = false;
+    let mut changed = false;
+    for file in files {
+        let name = file.to_string_lossy();
+        let src = match fs::read_to_string(&file) {
+            Ok(src) => src,
+            Err(err) => {
+                eprintln!("Could not read '{}': {}", name, err);
+                failed = true;
+                continue;
+            }
+        };
+        let Some(formatted) = lox.format(&name, &src) else {
+            failed = true;
+            continue;
+        };
+        if formatted == src {
+            continue;
+        }
+        changed = true;
+        if check {
+            println!("Would reformat: {}", name);
+        } else if let Err(err) = fs::write(&file, formatted) {
+            eprintln!("Could not write '{}': {}", name, err);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(65);
+    }
+    if check && changed {
+        process::exit(1);
+    }
+}
+
+fn lint(mut lox: Lox, mut config: LintConfig, args: LintArgs) {
+    if args.list_rules {
+        for rule in LintRule::ALL {
+            let default = if rule.enabled_by_default() {
+                "enabled"
+            } else {
+                "disabled"
+            };
+            println!("{:<20}{}", rule.name(), default);
+        }
+        return;
+    }
+
+    if let Some(path) = &args.config {
+        let parsed = fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| config.parse(&contents));
+        if let Err(err) = parsed {
+            eprintln!("Invalid lint config '{}': {}", path.display(), err);
+            process::exit(64);
+        }
+    }
+    for rule in args.enable {
+        config.set(rule, true);
+    }
+    for rule in args.disable {
+        config.set(rule, false);
+    }
+
+    let mut failed = false;
+    let mut warned = false;
+    for file in &args.files {
+        let name = file.to_string_lossy();
+        let src = match fs::read_to_string(file) {
+            Ok(src) => src,
+            Err(err) => {
+                eprintln!("Could not read '{}': {}", name, err);
+                failed = true;
+                continue;
+            }
+        };
+        let Some(warnings) = lox.lint(&name, &src, &config) else {
+            failed = true;
+            continue;
+        };
+        for warning in warnings {
+            warned = true;
+            match args.format {
+                LintFormat::Text => lox.report_lint_warning(&warning),
+                LintFormat::Json => println!(
+                    "{{\"file\":{},\"line\":{},\"column\":{},\"rule\":{},\"message\":{}}}",
+                    json_string(&name),
+                    warning.token.line,
+                    warning.token.column,
+                    json_string(warning.rule.name()),
+                    json_string(&warning.message)
+                ),
+            }
+        }
+    }
+    if failed {
+        process::exit(65);
+    }
+    if warned {
+        process::exit(1);
+    }
+}
+
+fn test(paths: &[PathBuf], filter: Option<String>, quiet: bool) {
+    match TestRunner::new(filter, quiet).run(paths) {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("Could not run tests: {}", err);
+            process::exit(74);
+        }
+    }
+}
+
+fn manpage() {
+    let man = clap_mangen::Man::new(Cli::command());
+    if let Err(err) = man.render(&mut io::stdout()) {
+        eprintln!("Could not write man page: {}", err);
+        process::exit(74);
+    }
+}
+
+fn read_stdin() -> String {
+    let mut src = String::new();
+    io::stdin().read_to_string(&mut src).expect("read stdin");
+    src
+}
+
+fn serve(mut lox: Lox, address: &str) {
+    if let Err(err) = lox.serve(address) {
+        eprintln!("Could not serve on '{}': {}", address, err);
+        process::exit(69);
+    }
+}
+
+fn unavailable(command: &str) -> ! {
+    eprintln!("The '{}' command is not available yet.", command);
+    process::exit(1);
 }
@@ -0,0 +1,54 @@
+use std::{fs, path::PathBuf};
+
+use crate::{options::Capability, sync::MaybeSync};
+
+pub trait ModuleLoader: MaybeSync {
+    fn resolve(&self, name: &str) -> Result<String, String>;
+
+    fn load(&self, path: &str) -> Result<String, String>;
+
+    /// The sandbox capability a script needs to import through this loader.
+    fn capability(&self) -> Option<Capability> {
+        None
+    }
+}
+
+pub struct FileSystemLoader {
+    root: PathBuf,
+}
+
+impl FileSystemLoader {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl ModuleLoader for FileSystemLoader {
+    fn resolve(&self, name: &str) -> Result<String, String> {
+        let mut path = self.root.join(name);
+        if path.extension().is_none() {
+            path.set_extension("lox");
+        }
+        if !path.is_file() {
+            return Err(format!("no module found at '{}'", path.display()));
+        }
+        let root = self.root.canonicalize().map_err(|err| err.to_string())?;
+        let path = path.canonicalize().map_err(|err| err.to_string())?;
+        if !path.starts_with(&root) {
+            return Err(format!(
+                "'{}' is outside the module root '{}'",
+                path.display(),
+                root.display()
+            ));
+        }
+        Ok(path.to_string_lossy().into())
+    }
+
+    fn load(&self, path: &str) -> Result<String, String> {
+        fs::read_to_string(path).map_err(|err| err.to_string())
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::Filesystem)
+    }
+}
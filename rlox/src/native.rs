@@ -0,0 +1,328 @@
+use std::{
+    fmt::Debug,
+    io::{BufRead, Read},
+};
+
+#[cfg(feature = "graphemes")]
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    callable::LoxCallable,
+    clock::ClockSource,
+    interpreter::{Interpreter, LoxRuntimeException},
+    number::{Number, Numeric},
+    options::Capability,
+    random::RandomSource,
+    sync::{Lock, MaybeSync, Reader, Shared},
+    token::{Object, Token},
+    LoxRuntimeError,
+};
+
+pub type NativeResult = Result<Object, String>;
+
+#[cfg(not(feature = "sync"))]
+type NativeFn = dyn Fn(&[Object]) -> NativeResult;
+#[cfg(feature = "sync")]
+type NativeFn = dyn Fn(&[Object]) -> NativeResult + Send + Sync;
+
+pub(crate) type Input = Shared<Lock<Box<Reader>>>;
+
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: usize,
+    pub capability: Option<Capability>,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Object]) -> NativeResult + MaybeSync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            arity,
+            capability: None,
+            function: Box::new(function),
+        }
+    }
+
+    pub fn requires(mut self, capability: Capability) -> Self {
+        self.capability = Some(capability);
+        self
+    }
+
+    pub fn call(&self, arguments: &[Object]) -> NativeResult {
+        (self.function)(arguments)
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxRuntimeException> {
+        if let Some(capability) = self.capability {
+            if !interpreter.options().allows(capability) {
+                return LoxRuntimeException::throw_err(
+                    paren.clone(),
+                    &format!(
+                        "'{}' is not permitted by the sandbox policy ({}).",
+                        self.name,
+                        capability.name()
+                    ),
+                );
+            }
+        }
+        (self.function)(&arguments)
+            .map_err(|message| LoxRuntimeError(paren.clone(), message).into())
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+pub(crate) fn read_line(input: &Input) -> NativeFunction {
+    let input = input.clone();
+    NativeFunction::new("readLine", 0, move |_| {
+        let mut line = String::new();
+        match input.borrow_mut().read_line(&mut line) {
+            Ok(0) => Ok(Object::None),
+            Ok(_) => {
+                let len = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(len);
+                Ok(line.into())
+            }
+            Err(err) => Err(format!("Could not read input: {}.", err)),
+        }
+    })
+    .requires(Capability::Stdin)
+}
+
+pub(crate) fn read_all(input: &Input) -> NativeFunction {
+    let input = input.clone();
+    NativeFunction::new("readAll", 0, move |_| {
+        let mut text = String::new();
+        match input.borrow_mut().read_to_string(&mut text) {
+            Ok(_) => Ok(text.into()),
+            Err(err) => Err(format!("Could not read input: {}.", err)),
+        }
+    })
+    .requires(Capability::Stdin)
+}
+
+pub(crate) fn clock(clock: &ClockSource) -> NativeFunction {
+    let clock = clock.clone();
+    NativeFunction::new("clock", 0, move |_| Ok(clock.borrow_mut().now().into()))
+}
+
+pub(crate) fn random(random: &RandomSource) -> NativeFunction {
+    let random = random.clone();
+    NativeFunction::new("random", 0, move |_| Ok(random.borrow_mut().next().into()))
+}
+
+pub(crate) fn len() -> NativeFunction {
+    NativeFunction::new("len", 1, |arguments| {
+        let string = string_argument(&arguments[0])?;
+        Ok((characters(string).len() as f64).into())
+    })
+}
+
+pub(crate) fn char_at() -> NativeFunction {
+    NativeFunction::new("charAt", 2, |arguments| {
+        let characters = characters(string_argument(&arguments[0])?);
+        let index = index_argument(&arguments[1])?;
+        match characters.get(index) {
+            Some(character) => Ok(character.to_string().into()),
+            None => Err(out_of_range(index, characters.len())),
+        }
+    })
+}
+
+pub(crate) fn substring() -> NativeFunction {
+    NativeFunction::new("substring", 3, |arguments| {
+        let characters = characters(string_argument(&arguments[0])?);
+        let start = index_argument(&arguments[1])?;
+        let end = index_argument(&arguments[2])?;
+        if end > characters.len() {
+            return Err(out_of_range(end, characters.len()));
+        }
+        if start > end {
+            return Err(format!("Start index {} is after end index {}.", start, end));
+        }
+        Ok(characters[start..end].concat().into())
+    })
+}
+
+pub(crate) fn is_nan() -> NativeFunction {
+    NativeFunction::new("isNan", 1, |arguments| {
+        Ok(number_argument(&arguments[0])?.is_nan().into())
+    })
+}
+
+pub(crate) fn is_finite() -> NativeFunction {
+    NativeFunction::new("isFinite", 1, |arguments| {
+        Ok(number_argument(&arguments[0])?.is_finite().into())
+    })
+}
+
+#[cfg(not(feature = "graphemes"))]
+fn characters(string: &str) -> Vec<&str> {
+    string
+        .char_indices()
+        .map(|(i, c)| &string[i..i + c.len_utf8()])
+        .collect()
+}
+
+#[cfg(feature = "graphemes")]
+fn characters(string: &str) -> Vec<&str> {
+    string.graphemes(true).collect()
+}
+
+fn string_argument(value: &Object) -> Result<&str, String> {
+    match value {
+        Object::String(string) => Ok(string),
+        value => Err(format!("Expected a string. Got {}.", value.describe())),
+    }
+}
+
+fn number_argument(value: &Object) -> Result<&Number, String> {
+    match value {
+        Object::Num(n) => Ok(n),
+        value => Err(format!("Expected a number. Got {}.", value.describe())),
+    }
+}
+
+fn index_argument(value: &Object) -> Result<usize, String> {
+    match value {
+        Object::Num(n) if n.to_float() >= 0.0 && n.to_float().fract() == 0.0 => {
+            Ok(n.to_float() as usize)
+        }
+        value => Err(format!(
+            "Index must be a non-negative integer. Got {}.",
+            value.describe()
+        )),
+    }
+}
+
+fn out_of_range(index: usize, len: usize) -> String {
+    format!(
+        "Index {} is out of range for a string of length {}.",
+        index, len
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn len_counts_characters_not_bytes() {
+        assert_eq!(len().call(&["日本語".into()]), Ok(3.0.into()));
+        assert_eq!(len().call(&["a😀b".into()]), Ok(3.0.into()));
+        assert_eq!(len().call(&["".into()]), Ok(0.0.into()));
+    }
+
+    #[test]
+    fn char_at_returns_whole_characters() {
+        assert_eq!(
+            char_at().call(&["日本語".into(), 1.0.into()]),
+            Ok("本".into())
+        );
+        assert_eq!(
+            char_at().call(&["a😀b".into(), 1.0.into()]),
+            Ok("😀".into())
+        );
+    }
+
+    #[test]
+    fn char_at_rejects_bad_indices() {
+        assert_eq!(
+            char_at().call(&["日本語".into(), 3.0.into()]),
+            Err("Index 3 is out of range for a string of length 3.".into())
+        );
+        assert_eq!(
+            char_at().call(&["日本語".into(), 1.5.into()]),
+            Err("Index must be a non-negative integer. Got number (1.5).".into())
+        );
+        assert_eq!(
+            char_at().call(&["日本語".into(), (-1.0).into()]),
+            Err("Index must be a non-negative integer. Got number (-1).".into())
+        );
+    }
+
+    #[test]
+    fn substring_slices_by_character() {
+        assert_eq!(
+            substring().call(&["こんにちは😀".into(), 2.0.into(), 6.0.into()]),
+            Ok("にちは😀".into())
+        );
+        assert_eq!(
+            substring().call(&["日本語".into(), 3.0.into(), 3.0.into()]),
+            Ok("".into())
+        );
+    }
+
+    #[test]
+    fn substring_rejects_bad_indices() {
+        assert_eq!(
+            substring().call(&["😀😀".into(), 0.0.into(), 3.0.into()]),
+            Err("Index 3 is out of range for a string of length 2.".into())
+        );
+        assert_eq!(
+            substring().call(&["😀😀".into(), 2.0.into(), 1.0.into()]),
+            Err("Start index 2 is after end index 1.".into())
+        );
+        assert_eq!(
+            substring().call(&["😀😀".into(), 0.5.into(), 1.0.into()]),
+            Err("Index must be a non-negative integer. Got number (0.5).".into())
+        );
+    }
+
+    #[test]
+    fn characters_splits_on_code_points() {
+        assert_eq!(characters("中文😀"), ["中", "文", "😀"]);
+    }
+
+    #[cfg(not(feature = "graphemes"))]
+    #[test]
+    fn clusters_count_each_code_point() {
+        assert_eq!(characters("👍🏽"), ["👍", "🏽"]);
+        assert_eq!(len().call(&["👨‍👩‍👧".into()]), Ok(5.0.into()));
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn clusters_count_as_one_character() {
+        assert_eq!(characters("👍🏽!"), ["👍🏽", "!"]);
+        assert_eq!(len().call(&["👨‍👩‍👧".into()]), Ok(1.0.into()));
+        assert_eq!(
+            char_at().call(&["a👨‍👩‍👧b".into(), 1.0.into()]),
+            Ok("👨‍👩‍👧".into())
+        );
+        assert_eq!(
+            substring().call(&["👍🏽👨‍👩‍👧".into(), 1.0.into(), 2.0.into()]),
+            Ok("👨‍👩‍👧".into())
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::any::Any;
+
+use crate::{
+    native::{NativeFunction, NativeResult},
+    sync::{Lock, MaybeSend, MaybeSync, Shared},
+    token::Object,
+    userdata::{Members, UserData},
+};
+
+#[cfg(not(feature = "sync"))]
+type Constructor<T> = dyn Fn(&[Object]) -> Result<T, String>;
+#[cfg(feature = "sync")]
+type Constructor<T> = dyn Fn(&[Object]) -> Result<T, String> + Send + Sync;
+
+pub struct NativeClass<T> {
+    name: String,
+    arity: usize,
+    constructor: Box<Constructor<T>>,
+    methods: Members,
+    getters: Members,
+}
+
+impl<T: Any + MaybeSend> NativeClass<T> {
+    pub fn new(
+        name: &str,
+        arity: usize,
+        constructor: impl Fn(&[Object]) -> Result<T, String> + MaybeSync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            arity,
+            constructor: Box::new(constructor),
+            methods: Members::new(),
+            getters: Members::new(),
+        }
+    }
+
+    pub fn method(
+        mut self,
+        name: &str,
+        arity: usize,
+        method: impl Fn(&mut T, &[Object]) -> NativeResult + MaybeSync + 'static,
+    ) -> Self {
+        let class = self.name.clone();
+        let native = NativeFunction::new(name, arity, move |arguments| {
+            let this = receiver::<T>(&class, arguments)?;
+            let mut this = this.borrow_mut();
+            method(&mut this, &arguments[1..])
+        });
+        self.methods.insert(name.into(), Shared::new(native));
+        self
+    }
+
+    pub fn getter(
+        mut self,
+        name: &str,
+        getter: impl Fn(&T) -> Object + MaybeSync + 'static,
+    ) -> Self {
+        let class = self.name.clone();
+        let native = NativeFunction::new(name, 0, move |arguments| {
+            let this = receiver::<T>(&class, arguments)?;
+            let this = this.borrow();
+            Ok(getter(&this))
+        });
+        self.getters.insert(name.into(), Shared::new(native));
+        self
+    }
+
+    pub(crate) fn into_constructor(self) -> NativeFunction {
+        let Self {
+            name,
+            arity,
+            constructor,
+            methods,
+            getters,
+        } = self;
+        let class = name.clone();
+        NativeFunction::new(&name, arity, move |arguments| {
+            let value = constructor(arguments)?;
+            let data = UserData::from_parts(
+                &class,
+                Shared::new(Lock::new(value)),
+                methods.clone(),
+                getters.clone(),
+            );
+            Ok(data.into())
+        })
+    }
+}
+
+fn receiver<'a, T: Any>(class: &str, arguments: &'a [Object]) -> Result<&'a Lock<T>, String> {
+    arguments
+        .first()
+        .and_then(Object::as_user_data::<Lock<T>>)
+        .ok_or_else(|| format!("Expected a {} instance.", class))
+}
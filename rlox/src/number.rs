@@ -0,0 +1,105 @@
+// Lox numbers are `f64` by default, as in the book. The `decimal` feature
+// swaps them for arbitrary-precision decimals so that `0.1 + 0.2` is exactly
+// `0.3`; code that needs a float at the boundary goes through `Numeric`.
+
+#[cfg(not(feature = "decimal"))]
+pub type Number = f64;
+#[cfg(feature = "decimal")]
+pub type Number = bigdecimal::BigDecimal;
+
+pub trait Numeric: Sized {
+    fn from_float(value: f64) -> Self;
+
+    fn to_float(&self) -> f64;
+
+    fn checked_divide(&self, divisor: &Self) -> Option<Self>;
+
+    fn checked_remainder(&self, divisor: &Self) -> Option<Self>;
+
+    fn is_nan(&self) -> bool;
+
+    fn is_finite(&self) -> bool;
+
+    fn to_lox_string(&self) -> String;
+}
+
+#[cfg(not(feature = "decimal"))]
+pub(crate) fn special(name: &str) -> Option<Number> {
+    match name {
+        "nan" => Some(f64::NAN),
+        "inf" => Some(f64::INFINITY),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "decimal")]
+pub(crate) fn special(_name: &str) -> Option<Number> {
+    None
+}
+
+#[cfg(not(feature = "decimal"))]
+impl Numeric for f64 {
+    fn from_float(value: f64) -> Self {
+        value
+    }
+
+    fn to_float(&self) -> f64 {
+        *self
+    }
+
+    fn checked_divide(&self, divisor: &Self) -> Option<Self> {
+        Some(self / divisor)
+    }
+
+    fn checked_remainder(&self, divisor: &Self) -> Option<Self> {
+        Some(self % divisor)
+    }
+
+    fn is_nan(&self) -> bool {
+        f64::is_nan(*self)
+    }
+
+    fn is_finite(&self) -> bool {
+        f64::is_finite(*self)
+    }
+
+    fn to_lox_string(&self) -> String {
+        match self {
+            n if n.is_nan() => "nan".into(),
+            n if n.is_infinite() && n.is_sign_positive() => "inf".into(),
+            n if n.is_infinite() => "-inf".into(),
+            n => n.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl Numeric for bigdecimal::BigDecimal {
+    fn from_float(value: f64) -> Self {
+        value.to_string().parse().unwrap_or_default()
+    }
+
+    fn to_float(&self) -> f64 {
+        bigdecimal::ToPrimitive::to_f64(self).unwrap_or(f64::NAN)
+    }
+
+    fn checked_divide(&self, divisor: &Self) -> Option<Self> {
+        (!bigdecimal::Zero::is_zero(divisor)).then(|| self / divisor)
+    }
+
+    fn checked_remainder(&self, divisor: &Self) -> Option<Self> {
+        (!bigdecimal::Zero::is_zero(divisor)).then(|| self % divisor)
+    }
+
+    fn is_nan(&self) -> bool {
+        false
+    }
+
+    fn is_finite(&self) -> bool {
+        true
+    }
+
+    fn to_lox_string(&self) -> String {
+        self.normalized().to_plain_string()
+    }
+}
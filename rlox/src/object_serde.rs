@@ -0,0 +1,92 @@
+use std::fmt;
+
+use serde::{
+    de::{self, Visitor},
+    ser::Error,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{number::Numeric, token::Object};
+
+impl Serialize for Object {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Object::String(s) => serializer.serialize_str(s),
+            Object::Num(n) => serializer.serialize_f64(n.to_float()),
+            Object::Bool(b) => serializer.serialize_bool(*b),
+            Object::None => serializer.serialize_unit(),
+            Object::Fun(fun) => Err(S::Error::custom(format!(
+                "cannot serialize function '{}'",
+                fun.declaration.name.lexeme
+            ))),
+            Object::Native(native) => Err(S::Error::custom(format!(
+                "cannot serialize native function '{}'",
+                native.name
+            ))),
+            Object::Class(class) => Err(S::Error::custom(format!(
+                "cannot serialize class '{}'",
+                class.name
+            ))),
+            Object::Instance(instance) => Err(S::Error::custom(format!(
+                "cannot serialize instance of '{}'",
+                instance.class.name
+            ))),
+            Object::UserData(data) => Err(S::Error::custom(format!(
+                "cannot serialize userdata '{}'",
+                data.type_name()
+            ))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Object {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ObjectVisitor)
+    }
+}
+
+struct ObjectVisitor;
+
+impl<'de> Visitor<'de> for ObjectVisitor {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a number, string, boolean or nil")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Object, E> {
+        Ok(Object::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Object, E> {
+        Ok((v as f64).into())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Object, E> {
+        Ok((v as f64).into())
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Object, E> {
+        Ok(v.into())
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Object, E> {
+        Ok(Object::String(v.into()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Object, E> {
+        Ok(Object::String(v))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Object, E> {
+        Ok(Object::None)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Object, E> {
+        Ok(Object::None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Object, D::Error> {
+        Object::deserialize(deserializer)
+    }
+}
@@ -0,0 +1,87 @@
+use std::{collections::HashSet, time::Duration};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Capability {
+    Stdin,
+    Filesystem,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 2] = [Capability::Stdin, Capability::Filesystem];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|capability| capability.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capability::Stdin => "stdin",
+            Capability::Filesystem => "filesystem",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Usage {
+    pub statements: usize,
+    pub allocated: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct InterpreterOptions {
+    pub capabilities: HashSet<Capability>,
+    pub max_steps: Option<usize>,
+    pub max_duration: Option<Duration>,
+    pub max_call_depth: Option<usize>,
+    pub max_string_length: Option<usize>,
+    pub stdlib: bool,
+    pub strict: bool,
+}
+
+impl InterpreterOptions {
+    pub fn new() -> Self {
+        Self {
+            capabilities: Capability::ALL.into_iter().collect(),
+            max_steps: None,
+            max_duration: None,
+            max_call_depth: None,
+            max_string_length: None,
+            stdlib: true,
+            strict: false,
+        }
+    }
+
+    pub fn sandboxed() -> Self {
+        Self {
+            capabilities: HashSet::new(),
+            max_steps: Some(10_000_000),
+            max_duration: Some(Duration::from_secs(5)),
+            max_call_depth: Some(256),
+            max_string_length: Some(1 << 20),
+            stdlib: true,
+            strict: false,
+        }
+    }
+
+    pub fn allow(mut self, capability: Capability) -> Self {
+        self.capabilities.insert(capability);
+        self
+    }
+
+    pub fn deny(mut self, capability: Capability) -> Self {
+        self.capabilities.remove(&capability);
+        self
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+impl Default for InterpreterOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,36 +1,69 @@
 use crate::{
     generate_ast::{
-        AssignExpr, BinaryExpr, BlockStmt, CallExpr, Expr, ExpressionStmt, FunctionStmt,
-        GroupingExpr, IfStmt, LiteralExpr, LogicalExpr, PrintStmt, ReturnStmt, Stmt, UnaryExpr,
-        VarStmt, VariableExpr, WhileStmt,
+        AssignExpr, BinaryExpr, BlockStmt, BreakStmt, CallExpr, ClassStmt, ContinueStmt, Expr,
+        ExpressionStmt, FunctionStmt, GetExpr, GroupingExpr, IfStmt, ImportStmt, InterpolationExpr,
+        LiteralExpr, LogicalExpr, MacroStmt, PrintStmt, ReturnStmt, SetExpr, Stmt, SuperExpr,
+        ThisExpr, UnaryExpr, VarStmt, VariableExpr, WhileStmt,
     },
+    jit::JitCache,
+    shape::InlineCache,
+    suggestion::did_you_mean,
     token::{Object, Token},
     token_type::TokenType,
     LoxParseError,
 };
 
+const STATEMENT_KEYWORDS: [&str; 12] = [
+    "break", "class", "continue", "for", "fun", "if", "import", "macro", "print", "return", "var",
+    "while",
+];
+
+const MAX_NESTING: usize = 256;
+
+/// Builds statements from the tokens produced by a [`Scanner`](crate::Scanner),
+/// collecting every syntax error it can recover from.
 pub struct Parser<'a> {
     tokens: Vec<&'a Token>,
     current: usize,
+    function_depth: usize,
+    scope_depth: usize,
+    statement_nesting: usize,
+    expression_nesting: usize,
+    errors: Vec<LoxParseError>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: Vec<&'a Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            function_depth: 0,
+            scope_depth: 0,
+            statement_nesting: 0,
+            expression_nesting: 0,
+            errors: vec![],
+        }
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<LoxParseError>> {
         let mut statements = vec![];
-        let mut errors = vec![];
         while !self.is_at_end() {
             match self.declaration() {
                 Ok(stmt) => statements.push(stmt),
                 Err(e) => {
-                    errors.push(e);
+                    self.errors.push(e);
                     self.synchronize();
                 }
             }
         }
+        let errors = std::mem::take(&mut self.errors);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            statements = statements.len(),
+            errors = errors.len(),
+            "parsed"
+        );
         if !errors.is_empty() {
             return Err(errors);
         }
@@ -38,36 +71,134 @@ impl<'a> Parser<'a> {
         Ok(statements)
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn parse_expression(&mut self) -> Result<Expr, LoxParseError> {
+        let expr = self.expression()?;
+        if !self.is_at_end() {
+            return Err(LoxParseError(
+                self.peek().clone(),
+                "Expect end of expression.".into(),
+            ));
+        }
+        Ok(*expr)
+    }
+
     fn declaration(&mut self) -> Result<Stmt, LoxParseError> {
+        if self.match_type(&[TokenType::Class]) {
+            return self.nested_statement(Self::class_declaration);
+        }
         if self.match_type(&[TokenType::Fun]) {
-            return self.function();
+            return self.nested_statement(Self::function);
         }
         if self.match_type(&[TokenType::Var]) {
             return self.var_declaration();
         }
+        if self.match_type(&[TokenType::Import]) {
+            return self.import_declaration();
+        }
+        if self.match_type(&[TokenType::Macro]) {
+            return self.nested_statement(Self::macro_declaration);
+        }
         self.statement()
     }
 
+    fn class_declaration(&mut self) -> Result<Stmt, LoxParseError> {
+        let name = self
+            .consume(&TokenType::Identifier)
+            .map_err(|t| LoxParseError(t, "Expect class name.".into()))?;
+        let superclass = if self.match_type(&[TokenType::Less]) {
+            let superclass = self
+                .consume(&TokenType::Identifier)
+                .map_err(|t| LoxParseError(t, "Expect superclass name.".into()))?;
+            Some(Expr::Variable(VariableExpr::new(
+                superclass,
+                InlineCache::default(),
+            )))
+        } else {
+            None
+        };
+        self.consume(&TokenType::LeftBrace)
+            .map_err(|t| LoxParseError(t, "Expect '{' before class body.".into()))?;
+
+        let methods = self.methods()?;
+        self.consume(&TokenType::RightBrace)
+            .map_err(|t| LoxParseError(t, "Expect '}' after class body.".into()))?;
+
+        Ok(Stmt::Class(ClassStmt::new(name, superclass, methods)))
+    }
+
+    fn methods(&mut self) -> Result<Vec<FunctionStmt>, LoxParseError> {
+        let mut methods = vec![];
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function_declaration("method")?);
+        }
+        Ok(methods)
+    }
+
     fn function(&mut self) -> Result<Stmt, LoxParseError> {
+        self.function_declaration("function").map(Stmt::Function)
+    }
+
+    fn function_declaration(&mut self, kind: &str) -> Result<FunctionStmt, LoxParseError> {
         let name = self
             .consume(&TokenType::Identifier)
-            .map_err(|t| LoxParseError(t, "Expect function name.".into()))?;
-        let mut params = vec![];
+            .map_err(|t| LoxParseError(t, format!("Expect {} name.", kind)))?;
+        self.consume(&TokenType::LeftParen)
+            .map_err(|t| LoxParseError(t, format!("Expect '(' after {} name.", kind)))?;
+        let params = self.parameters()?;
+
+        self.consume(&TokenType::LeftBrace)
+            .map_err(|t| LoxParseError(t, format!("Expect '{{' before {} body.", kind)))?;
+        self.function_depth += 1;
+        let body = self.block_statement();
+        self.function_depth -= 1;
+        let body = body?;
+
+        Ok(FunctionStmt::new(name, params, body, JitCache::default()))
+    }
 
+    fn macro_declaration(&mut self) -> Result<Stmt, LoxParseError> {
+        let keyword = self.previous();
+        let name = self
+            .consume(&TokenType::Identifier)
+            .map_err(|t| LoxParseError(t, "Expect macro name.".into()))?;
         self.consume(&TokenType::LeftParen)
-            .map_err(|t| LoxParseError(t, "Expect '(' after function name.".into()))?;
+            .map_err(|t| LoxParseError(t, "Expect '(' after macro name.".into()))?;
+        let params = self.parameters()?;
+
+        self.consume(&TokenType::LeftBrace)
+            .map_err(|t| LoxParseError(t, "Expect '{' before macro body.".into()))?;
+        let body = self.block_statement()?;
+        if self.function_depth > 0 || self.scope_depth > 0 {
+            self.errors.push(LoxParseError(
+                keyword,
+                "Macros can only be declared at the top level.".into(),
+            ));
+        }
+
+        Ok(Stmt::Macro(MacroStmt::new(name, params, body)))
+    }
+
+    fn parameters(&mut self) -> Result<Vec<Token>, LoxParseError> {
+        let mut params = vec![];
         if !self.check(&TokenType::RightParen) {
             loop {
                 if params.len() >= 255 {
                     return Err(LoxParseError(
                         self.peek().clone(),
-                        "Cant't have more than 255 parameters.".into(),
+                        "Can't have more than 255 parameters.".into(),
+                    ));
+                }
+                let param = self
+                    .consume(&TokenType::Identifier)
+                    .map_err(|t| LoxParseError(t, "Expect parameter name.".into()))?;
+                if params.iter().any(|p: &Token| p.lexeme == param.lexeme) {
+                    self.errors.push(LoxParseError(
+                        param.clone(),
+                        format!("Duplicate parameter name '{}'.", param.lexeme),
                     ));
                 }
-                params.push(
-                    self.consume(&TokenType::Identifier)
-                        .map_err(|t| LoxParseError(t, "Expect parameter name.".into()))?,
-                );
+                params.push(param);
                 if !self.match_type(&[TokenType::Comma]) {
                     break;
                 }
@@ -75,12 +206,7 @@ impl<'a> Parser<'a> {
         }
         self.consume(&TokenType::RightParen)
             .map_err(|t| LoxParseError(t, "Expect ')' after parameters.".into()))?;
-
-        self.consume(&TokenType::LeftBrace)
-            .map_err(|t| LoxParseError(t, "Expect '{' before function body.".into()))?;
-        let body = self.block_statement()?;
-
-        Ok(Stmt::Function(FunctionStmt::new(name, params, body)))
+        Ok(params)
     }
 
     fn var_declaration(&mut self) -> Result<Stmt, LoxParseError> {
@@ -88,38 +214,57 @@ impl<'a> Parser<'a> {
             .consume(&TokenType::Identifier)
             .map_err(|t| LoxParseError(t, "Expect variable name.".into()))?;
 
-        let mut initializer = Box::new(Expr::Literal(LiteralExpr::new(Object::None)));
+        let mut initializer = None;
         if self.match_type(&[TokenType::Equal]) {
-            initializer = self.expression()?;
+            initializer = Some(*self.expression()?);
         }
         self.consume(&TokenType::SemiColon)
             .map_err(|t| LoxParseError(t, "Expect ';' after variable declaration.".into()))?;
-        Ok(Stmt::Var(VarStmt::new(name, *initializer)))
+        Ok(Stmt::Var(VarStmt::new(name, initializer)))
+    }
+
+    fn import_declaration(&mut self) -> Result<Stmt, LoxParseError> {
+        let keyword = self.previous();
+        let name = self
+            .consume(&TokenType::String)
+            .map_err(|t| LoxParseError(t, "Expect module name.".into()))?;
+        self.consume(&TokenType::SemiColon)
+            .map_err(|t| LoxParseError(t, "Expect ';' after module name.".into()))?;
+        Ok(Stmt::Import(ImportStmt::new(keyword, name)))
     }
 
     fn statement(&mut self) -> Result<Stmt, LoxParseError> {
-        if self.match_type(&[TokenType::Print]) {
-            return self.print_statement();
-        }
-        if self.match_type(&[TokenType::If]) {
-            return self.if_statement();
-        }
-        if self.match_type(&[TokenType::While]) {
-            return self.while_statement();
-        }
-        if self.match_type(&[TokenType::For]) {
-            return self.for_statement();
-        }
-        if self.match_type(&[TokenType::Return]) {
-            return self.return_statement();
-        }
-        if self.match_type(&[TokenType::LeftBrace]) {
-            return Ok(Stmt::Block(BlockStmt::new(self.block_statement()?)));
-        }
-        self.expression_statement()
+        self.nested_statement(|parser| {
+            if parser.match_type(&[TokenType::Print]) {
+                return parser.print_statement();
+            }
+            if parser.match_type(&[TokenType::If]) {
+                return parser.if_statement();
+            }
+            if parser.match_type(&[TokenType::While]) {
+                return parser.while_statement();
+            }
+            if parser.match_type(&[TokenType::For]) {
+                return parser.for_statement();
+            }
+            if parser.match_type(&[TokenType::Break]) {
+                return parser.break_statement();
+            }
+            if parser.match_type(&[TokenType::Continue]) {
+                return parser.continue_statement();
+            }
+            if parser.match_type(&[TokenType::Return]) {
+                return parser.return_statement();
+            }
+            if parser.match_type(&[TokenType::LeftBrace]) {
+                return Ok(Stmt::Block(BlockStmt::new(parser.block_statement()?)));
+            }
+            parser.expression_statement()
+        })
     }
 
     fn if_statement(&mut self) -> Result<Stmt, LoxParseError> {
+        let keyword = self.previous();
         self.consume(&TokenType::LeftParen)
             .map_err(|t| LoxParseError(t, "Expect '(' after 'if'.".into()))?;
         let condition = self.expression()?;
@@ -131,10 +276,16 @@ impl<'a> Parser<'a> {
         if self.match_type(&[TokenType::Else]) {
             else_branch = Some(Box::new(self.statement()?));
         }
-        Ok(Stmt::If(IfStmt::new(*condition, then_branch, else_branch)))
+        Ok(Stmt::If(IfStmt::new(
+            keyword,
+            *condition,
+            then_branch,
+            else_branch,
+        )))
     }
 
     fn while_statement(&mut self) -> Result<Stmt, LoxParseError> {
+        let keyword = self.previous();
         self.consume(&TokenType::LeftParen)
             .map_err(|t| LoxParseError(t, "Expect '(' after 'while'.".into()))?;
         let condition = self.expression()?;
@@ -143,15 +294,16 @@ impl<'a> Parser<'a> {
 
         let body = Box::new(self.statement()?);
 
-        Ok(Stmt::While(WhileStmt::new(*condition, body)))
+        Ok(Stmt::While(WhileStmt::new(keyword, *condition, body, None)))
     }
 
     fn for_statement(&mut self) -> Result<Stmt, LoxParseError> {
+        let keyword = self.previous();
         self.consume(&TokenType::LeftParen)
             .map_err(|t| LoxParseError(t, "Expect '(' after 'for'.".into()))?;
 
         let initializer;
-        if self.check(&TokenType::SemiColon) {
+        if self.match_type(&[TokenType::SemiColon]) {
             initializer = None;
         } else if self.match_type(&[TokenType::Var]) {
             initializer = Some(self.var_declaration()?);
@@ -166,26 +318,21 @@ impl<'a> Parser<'a> {
             .map_err(|t| LoxParseError(t, "Expect ';' after loop condition.".into()))?;
 
         let mut increment = None;
-        if !self.check(&TokenType::SemiColon) {
+        if !self.check(&TokenType::RightParen) {
             increment = Some(self.expression()?);
         }
         self.consume(&TokenType::RightParen)
             .map_err(|t| LoxParseError(t, "Expect ')' after for closure.".into()))?;
 
-        let mut body = self.statement()?;
+        let body = Box::new(self.statement()?);
 
-        if let Some(increment) = increment {
-            body = Stmt::Block(BlockStmt::new(vec![
-                body,
-                Stmt::Expression(ExpressionStmt::new(*increment)),
-            ]));
-        }
-        if let Some(condition) = condition {
-            body = Stmt::While(WhileStmt::new(*condition, Box::new(body)));
-        } else {
-            let condition = Expr::Literal(LiteralExpr::new(Object::Bool(true)));
-            body = Stmt::While(WhileStmt::new(condition, Box::new(body)));
-        }
+        // The increment stays on the loop rather than at the end of the body
+        // so that `continue` still runs it.
+        let condition = match condition {
+            Some(condition) => *condition,
+            None => Expr::Literal(LiteralExpr::new(Object::Bool(true))),
+        };
+        let mut body = Stmt::While(WhileStmt::new(keyword, condition, body, increment));
         if let Some(initializer) = initializer {
             body = Stmt::Block(BlockStmt::new(vec![initializer, body]));
         }
@@ -193,6 +340,20 @@ impl<'a> Parser<'a> {
         Ok(body)
     }
 
+    fn break_statement(&mut self) -> Result<Stmt, LoxParseError> {
+        let keyword = self.previous();
+        self.consume(&TokenType::SemiColon)
+            .map_err(|token| LoxParseError(token, "Expect ';' after 'break'.".into()))?;
+        Ok(Stmt::Break(BreakStmt::new(keyword)))
+    }
+
+    fn continue_statement(&mut self) -> Result<Stmt, LoxParseError> {
+        let keyword = self.previous();
+        self.consume(&TokenType::SemiColon)
+            .map_err(|token| LoxParseError(token, "Expect ';' after 'continue'.".into()))?;
+        Ok(Stmt::Continue(ContinueStmt::new(keyword)))
+    }
+
     fn return_statement(&mut self) -> Result<Stmt, LoxParseError> {
         let keyword = self.previous();
         let mut value = None;
@@ -206,35 +367,57 @@ impl<'a> Parser<'a> {
     }
 
     fn print_statement(&mut self) -> Result<Stmt, LoxParseError> {
+        let keyword = self.previous();
         let value = self.expression()?;
 
         match self.consume(&TokenType::SemiColon) {
-            Ok(_) => Ok(Stmt::Print(PrintStmt::new(*value))),
-            Err(token) => Err(LoxParseError(token, "Expect ';' after value".into())),
+            Ok(_) => Ok(Stmt::Print(PrintStmt::new(keyword, *value))),
+            Err(token) => Err(LoxParseError(token, "Expect ';' after value.".into())),
         }
     }
 
     fn block_statement(&mut self) -> Result<Vec<Stmt>, LoxParseError> {
+        self.scope_depth += 1;
+        let statements = self.block();
+        self.scope_depth -= 1;
+        statements
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, LoxParseError> {
         let mut statements = vec![];
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-            statements.push(self.declaration()?);
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(err) => {
+                    self.errors.push(err);
+                    if !self.check(&TokenType::RightBrace) {
+                        self.synchronize();
+                    }
+                }
+            }
         }
         match self.consume(&TokenType::RightBrace) {
             Ok(_) => Ok(statements),
-            Err(t) => Err(LoxParseError(t, "Expected '}' after block.".into())),
+            Err(t) => Err(LoxParseError(t, "Expect '}' after block.".into())),
         }
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, LoxParseError> {
-        let expr = self.expression()?;
+        let start = self.peek().clone();
+        let expr = self
+            .expression()
+            .map_err(|err| suggest_keyword(&start, err))?;
         match self.consume(&TokenType::SemiColon) {
             Ok(_) => Ok(Stmt::Expression(ExpressionStmt::new(*expr))),
-            Err(token) => Err(LoxParseError(token, "Expect ';' after expression".into())),
+            Err(token) => Err(suggest_keyword(
+                &start,
+                LoxParseError(token, "Expect ';' after expression.".into()),
+            )),
         }
     }
 
     fn expression(&mut self) -> Result<Box<Expr>, LoxParseError> {
-        self.assignment()
+        self.nested_expression(Self::assignment)
     }
 
     fn assignment(&mut self) -> Result<Box<Expr>, LoxParseError> {
@@ -246,7 +429,16 @@ impl<'a> Parser<'a> {
 
             match *expr {
                 Expr::Variable(var) => {
-                    return Ok(Box::new(Expr::Assign(AssignExpr::new(var.name, value))));
+                    return Ok(Box::new(Expr::Assign(AssignExpr::new(
+                        var.name,
+                        value,
+                        InlineCache::default(),
+                    ))));
+                }
+                Expr::Get(get) => {
+                    return Ok(Box::new(Expr::Set(SetExpr::new(
+                        get.object, get.name, value,
+                    ))));
                 }
                 _ => return Err(LoxParseError(equals, "Invalid assignment target.".into())),
             }
@@ -311,7 +503,7 @@ impl<'a> Parser<'a> {
 
     fn factor(&mut self) -> Result<Box<Expr>, LoxParseError> {
         let mut expr = self.unary()?;
-        while self.match_type(&[TokenType::Star, TokenType::Slash]) {
+        while self.match_type(&[TokenType::Star, TokenType::Slash, TokenType::Percent]) {
             let operator = self.previous();
             let right = self.unary()?;
             expr = Box::new(Expr::Binary(BinaryExpr::new(expr, operator, right)));
@@ -322,7 +514,7 @@ impl<'a> Parser<'a> {
     fn unary(&mut self) -> Result<Box<Expr>, LoxParseError> {
         if self.match_type(&[TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous();
-            let right = self.unary()?;
+            let right = self.nested_expression(Self::unary)?;
             return Ok(Box::new(Expr::Unary(UnaryExpr::new(operator, right))));
         }
         self.call()
@@ -334,6 +526,11 @@ impl<'a> Parser<'a> {
         loop {
             if self.match_type(&[TokenType::LeftParen]) {
                 expr = self.finish_call(expr)?;
+            } else if self.match_type(&[TokenType::Dot]) {
+                let name = self
+                    .consume(&TokenType::Identifier)
+                    .map_err(|t| LoxParseError(t, "Expect property name after '.'.".into()))?;
+                expr = Box::new(Expr::Get(GetExpr::new(expr, name)));
             } else {
                 break;
             }
@@ -346,7 +543,15 @@ impl<'a> Parser<'a> {
 
         if !self.check(&TokenType::RightParen) {
             loop {
-                arguments.push(*self.expression()?);
+                match self.expression() {
+                    Ok(argument) => arguments.push(*argument),
+                    Err(err) => {
+                        if !self.skip_argument() {
+                            return Err(err);
+                        }
+                        self.errors.push(err);
+                    }
+                }
                 if arguments.len() >= 255 {
                     return Err(LoxParseError(
                         self.peek().clone(),
@@ -371,30 +576,91 @@ impl<'a> Parser<'a> {
             TokenType::False => LiteralExpr::new(Object::Bool(false)),
             TokenType::True => LiteralExpr::new(Object::Bool(true)),
             TokenType::Nil => LiteralExpr::new(Object::None),
-            TokenType::Number => LiteralExpr::new(Object::Num(self.peek().literal.num().unwrap())),
-            TokenType::String => {
+            TokenType::Number => LiteralExpr::new(self.peek().literal.clone()),
+            // The rest of an interpolated string after its `}` isn't an expression.
+            TokenType::String if !self.peek().lexeme.starts_with('}') => {
                 LiteralExpr::new(Object::String(self.peek().literal.str().unwrap()))
             }
+            TokenType::Interpolation => return self.interpolation(),
             TokenType::LeftParen => {
                 self.current += 1;
                 let expr = self.expression()?;
                 match self.consume(&TokenType::RightParen) {
                     Ok(_) => return Ok(Box::new(Expr::Grouping(GroupingExpr::new(expr)))),
-                    Err(t) => return Err(LoxParseError(t, "Expecte ')' after expression.".into())),
+                    Err(t) => return Err(LoxParseError(t, "Expect ')' after expression.".into())),
                 }
             }
+            TokenType::This => {
+                self.current += 1;
+                return Ok(Box::new(Expr::This(ThisExpr::new(
+                    self.previous(),
+                    InlineCache::default(),
+                ))));
+            }
+            TokenType::Super => {
+                self.current += 1;
+                let keyword = self.previous();
+                self.consume(&TokenType::Dot)
+                    .map_err(|t| LoxParseError(t, "Expect '.' after 'super'.".into()))?;
+                let method = self
+                    .consume(&TokenType::Identifier)
+                    .map_err(|t| LoxParseError(t, "Expect superclass method name.".into()))?;
+                return Ok(Box::new(Expr::Super(SuperExpr::new(
+                    keyword,
+                    method,
+                    InlineCache::default(),
+                ))));
+            }
             TokenType::Identifier => {
                 self.current += 1;
-                return Ok(Box::new(Expr::Variable(VariableExpr::new(self.previous()))));
+                return Ok(Box::new(Expr::Variable(VariableExpr::new(
+                    self.previous(),
+                    InlineCache::default(),
+                ))));
             }
             _ => {
-                return Err(LoxParseError(self.advance(), "Expect expression.".into()));
+                return Err(LoxParseError(
+                    self.peek().clone(),
+                    "Expect expression.".into(),
+                ));
             }
         };
         self.current += 1;
         Ok(Box::new(Expr::Literal(literal)))
     }
 
+    fn interpolation(&mut self) -> Result<Box<Expr>, LoxParseError> {
+        let quote = self.advance();
+        let mut parts = vec![];
+        let mut piece = quote.clone();
+        loop {
+            let text = piece.literal.str().unwrap();
+            if !text.is_empty() {
+                parts.push(Expr::Literal(LiteralExpr::new(Object::String(text))));
+            }
+            if piece.token_type == TokenType::String {
+                break;
+            }
+            parts.push(*self.expression()?);
+            piece = match self.peek().token_type {
+                TokenType::Interpolation | TokenType::String
+                    if self.peek().lexeme.starts_with('}') =>
+                {
+                    self.advance()
+                }
+                _ => {
+                    return Err(LoxParseError(
+                        self.peek().clone(),
+                        "Expect '}' after interpolated expression.".into(),
+                    ))
+                }
+            };
+        }
+        Ok(Box::new(Expr::Interpolation(InterpolationExpr::new(
+            quote, parts,
+        ))))
+    }
+
     fn peek(&self) -> &Token {
         self.tokens.get(self.current).unwrap()
     }
@@ -439,6 +705,73 @@ impl<'a> Parser<'a> {
         (**t).clone()
     }
 
+    fn skip_argument(&mut self) -> bool {
+        let mut depth = 0;
+        while !self.is_at_end() {
+            match self.peek().token_type {
+                TokenType::Comma if depth == 0 => return true,
+                TokenType::RightParen if depth == 0 => return true,
+                TokenType::SemiColon | TokenType::LeftBrace | TokenType::RightBrace
+                    if depth == 0 =>
+                {
+                    return false
+                }
+                TokenType::LeftParen => depth += 1,
+                TokenType::RightParen => depth -= 1,
+                _ => (),
+            }
+            self.advance();
+        }
+        false
+    }
+
+    fn nested_statement(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<Stmt, LoxParseError>,
+    ) -> Result<Stmt, LoxParseError> {
+        if self.statement_nesting >= MAX_NESTING {
+            self.errors.push(LoxParseError(
+                self.peek().clone(),
+                "Statement too deeply nested.".into(),
+            ));
+            self.skip_statement();
+            return Ok(Stmt::Block(BlockStmt::new(vec![])));
+        }
+        self.statement_nesting += 1;
+        let stmt = parse(self);
+        self.statement_nesting -= 1;
+        stmt
+    }
+
+    fn nested_expression(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<Box<Expr>, LoxParseError>,
+    ) -> Result<Box<Expr>, LoxParseError> {
+        if self.expression_nesting >= MAX_NESTING {
+            return Err(LoxParseError(
+                self.peek().clone(),
+                "Expression too deeply nested.".into(),
+            ));
+        }
+        self.expression_nesting += 1;
+        let expr = parse(self);
+        self.expression_nesting -= 1;
+        expr
+    }
+
+    fn skip_statement(&mut self) {
+        let mut depth = 0;
+        while !self.is_at_end() {
+            match self.advance().token_type {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth <= 1 => return,
+                TokenType::RightBrace => depth -= 1,
+                TokenType::SemiColon if depth == 0 => return,
+                _ => (),
+            }
+        }
+    }
+
     fn synchronize(&mut self) {
         self.advance();
         while !self.is_at_end() {
@@ -446,10 +779,15 @@ impl<'a> Parser<'a> {
                 return;
             }
             match self.peek().token_type {
-                TokenType::Class
+                TokenType::RightBrace
+                | TokenType::Break
+                | TokenType::Class
+                | TokenType::Continue
                 | TokenType::For
                 | TokenType::Fun
                 | TokenType::If
+                | TokenType::Import
+                | TokenType::Macro
                 | TokenType::Print
                 | TokenType::Return
                 | TokenType::Var
@@ -460,3 +798,19 @@ impl<'a> Parser<'a> {
         }
     }
 }
+
+fn suggest_keyword(start: &Token, err: LoxParseError) -> LoxParseError {
+    if start.token_type != TokenType::Identifier {
+        return err;
+    }
+    match did_you_mean(&start.lexeme, STATEMENT_KEYWORDS) {
+        Some(keyword) => LoxParseError(
+            err.0,
+            format!(
+                "{} Did you mean '{}' instead of '{}'?",
+                err.1, keyword, start.lexeme
+            ),
+        ),
+        None => err,
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{scanner::Scanner, sync::Shared, token::Token, LoxScanError};
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Preprocessed {
+    pub source: String,
+    pub lines: Vec<usize>,
+}
+
+impl Preprocessed {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            lines: vec![],
+        }
+    }
+
+    pub fn with_lines(mut self, lines: Vec<usize>) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    /// Keeps only the lines of fenced ```` ```lox ```` (or `~~~lox`) code
+    /// blocks, so the blocks of a Markdown document run as one script while
+    /// diagnostics point at the document's own lines.
+    pub fn from_markdown(markdown: &str) -> Self {
+        let mut source = String::new();
+        let mut lines = vec![];
+        let mut fence: Option<(char, usize, bool)> = None;
+        for (index, line) in markdown.lines().enumerate() {
+            let trimmed = line.trim_start();
+            let indented = line.len() - trimmed.len() > 3;
+            match fence {
+                Some((marker, length, lox)) => {
+                    let closing = trimmed.trim_end();
+                    if !indented && closing.len() >= length && closing.chars().all(|c| c == marker)
+                    {
+                        fence = None;
+                    } else if lox {
+                        source.push_str(line);
+                        source.push('\n');
+                        lines.push(index + 1);
+                    }
+                }
+                None if !indented => {
+                    let Some(marker) = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))
+                    else {
+                        continue;
+                    };
+                    let length = trimmed.chars().take_while(|&c| c == marker).count();
+                    let info = trimmed[length..].trim();
+                    if length >= 3 && !(marker == '`' && info.contains('`')) {
+                        let lox = info.split_whitespace().next() == Some("lox");
+                        fence = Some((marker, length, lox));
+                    }
+                }
+                None => (),
+            }
+        }
+        Self::new(source).with_lines(lines)
+    }
+
+    fn original_line(&self, line: usize) -> usize {
+        line.checked_sub(1)
+            .and_then(|index| self.lines.get(index))
+            .copied()
+            .unwrap_or(line)
+    }
+
+    pub(crate) fn scan(&self, source_name: Option<&str>) -> Vec<Result<Token, LoxScanError>> {
+        let source_name: Option<Shared<str>> = source_name.map(Shared::from);
+        let mut scanner = Scanner::new(&self.source);
+        let mut tokens = scanner.scan_tokens().clone();
+        for token in &mut tokens {
+            match token {
+                Ok(token) => {
+                    token.line = self.original_line(token.line);
+                    token.source = source_name.clone();
+                }
+                Err(err) => err.0 = self.original_line(err.0),
+            }
+        }
+        tokens
+    }
+}
+
+impl From<String> for Preprocessed {
+    fn from(source: String) -> Self {
+        Self::new(source)
+    }
+}
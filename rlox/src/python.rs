@@ -0,0 +1,104 @@
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyTypeError},
+    prelude::*,
+    types::{PyBool, PyFloat, PyInt, PyString, PyTuple},
+};
+
+use crate::{number::Numeric, token::Object, Lox as Interpreter};
+
+create_exception!(rlox, LoxError, PyException);
+
+fn to_python(py: Python<'_>, value: Object) -> PyResult<Py<PyAny>> {
+    let value = match value {
+        Object::String(s) => PyString::new(py, &s).into_any().unbind(),
+        Object::Num(n) => PyFloat::new(py, n.to_float()).into_any().unbind(),
+        Object::Bool(b) => PyBool::new(py, b).to_owned().into_any().unbind(),
+        Object::None => py.None(),
+        Object::Fun(_)
+        | Object::Native(_)
+        | Object::Class(_)
+        | Object::Instance(_)
+        | Object::UserData(_) => {
+            return Err(PyTypeError::new_err(format!(
+                "Cannot convert {} '{}' to a Python value.",
+                value.type_name(),
+                value
+            )))
+        }
+    };
+    Ok(value)
+}
+
+fn from_python(value: &Bound<'_, PyAny>) -> PyResult<Object> {
+    if value.is_none() {
+        Ok(Object::None)
+    } else if value.is_instance_of::<PyBool>() {
+        Ok(Object::Bool(value.extract()?))
+    } else if value.is_instance_of::<PyInt>() || value.is_instance_of::<PyFloat>() {
+        Ok(value.extract::<f64>()?.into())
+    } else if value.is_instance_of::<PyString>() {
+        Ok(Object::String(value.extract()?))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "Cannot convert Python value of type '{}' to a Lox value.",
+            value.get_type().name()?
+        )))
+    }
+}
+
+#[pyclass(unsendable)]
+struct Lox {
+    lox: Interpreter,
+}
+
+#[pymethods]
+impl Lox {
+    #[new]
+    fn new() -> Self {
+        Self {
+            lox: Interpreter::new(),
+        }
+    }
+
+    fn run(&mut self, source: &str) -> PyResult<()> {
+        self.lox
+            .run_source(source)
+            .map(|_| ())
+            .map_err(|err| LoxError::new_err(err.to_string()))
+    }
+
+    fn eval(&mut self, py: Python<'_>, expr: &str) -> PyResult<Py<PyAny>> {
+        let value = self
+            .lox
+            .interpreter()
+            .eval_expr(expr)
+            .map_err(|err| LoxError::new_err(err.to_string()))?;
+        to_python(py, value)
+    }
+
+    fn define_native(&mut self, name: &str, arity: usize, function: Py<PyAny>) {
+        self.lox.define_native(name, arity, move |arguments| {
+            Python::attach(|py| {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| to_python(py, argument.clone()))
+                    .collect::<PyResult<Vec<_>>>()
+                    .map_err(|err| err.to_string())?;
+                let arguments = PyTuple::new(py, arguments).map_err(|err| err.to_string())?;
+                function
+                    .bind(py)
+                    .call1(arguments)
+                    .and_then(|value| from_python(&value))
+                    .map_err(|err| err.to_string())
+            })
+        });
+    }
+}
+
+#[pymodule]
+fn rlox(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Lox>()?;
+    m.add("LoxError", m.py().get_type::<LoxError>())?;
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use crate::{
+    clock,
+    sync::{Lock, MaybeSend, Shared},
+};
+
+pub trait Random: MaybeSend {
+    fn next(&mut self) -> f64;
+}
+
+pub struct SeededRandom {
+    // Zero until seeded; `new` never produces it.
+    state: u64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self {
+            state: (state ^ (state >> 31)).max(1),
+        }
+    }
+
+    /// Seeds from the current time when the first number is drawn, so
+    /// interpreters that never call `random()` don't read the clock.
+    pub fn from_time() -> Self {
+        Self { state: 0 }
+    }
+}
+
+impl Random for SeededRandom {
+    fn next(&mut self) -> f64 {
+        if self.state == 0 {
+            *self = Self::new(clock::since_epoch().to_bits());
+        }
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl<F: FnMut() -> f64 + MaybeSend> Random for F {
+    fn next(&mut self) -> f64 {
+        self()
+    }
+}
+
+pub(crate) type RandomSource = Shared<Lock<Box<dyn Random>>>;
@@ -0,0 +1,50 @@
+pub enum Command {
+    Help,
+    Env,
+    Load(String),
+    Type(String),
+    Time(String),
+    Save(String),
+    Tokens,
+    Ast(String),
+    Reset,
+    Quit,
+    Unknown(String),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let rest = line.strip_prefix(':')?;
+        let (name, arg) = match rest.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (rest, ""),
+        };
+        let command = match name {
+            "help" | "h" => Command::Help,
+            "env" => Command::Env,
+            "load" if !arg.is_empty() => Command::Load(arg.into()),
+            "type" if !arg.is_empty() => Command::Type(arg.into()),
+            "time" if !arg.is_empty() => Command::Time(arg.into()),
+            "save" if !arg.is_empty() => Command::Save(arg.into()),
+            "tokens" => Command::Tokens,
+            "ast" => Command::Ast(arg.into()),
+            "reset" => Command::Reset,
+            "quit" | "q" => Command::Quit,
+            _ => Command::Unknown(line.into()),
+        };
+        Some(command)
+    }
+}
+
+pub const HELP: &str = "\
+:help         show this message
+:env          list the current bindings and their values
+:load <file>  run a file in the current session
+:type <expr>  evaluate an expression and show the kind of its value
+:time <code>  run code and report elapsed time and executed statements
+:save <file>  write the successfully executed inputs of this session to a file
+:tokens       show how the previous input was scanned
+:ast [FORMAT] show how the previous input was parsed: sexpr (default), json or dot
+:reset        discard all bindings and start a fresh interpreter
+:quit         exit the REPL";
@@ -0,0 +1,169 @@
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    interpreter::{DebugHook, Interpreter},
+    random::{Random, SeededRandom},
+    sync::{Lock, Shared},
+};
+
+const HEADER: &str = "rlox-trace 1";
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Trace {
+    pub clock: Vec<f64>,
+    pub random: Vec<f64>,
+    pub input: Vec<u8>,
+    pub statements: Vec<usize>,
+}
+
+impl Trace {
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", HEADER);
+        for now in &self.clock {
+            text.push_str(&format!("clock {}\n", now));
+        }
+        for value in &self.random {
+            text.push_str(&format!("random {}\n", value));
+        }
+        for line in String::from_utf8_lossy(&self.input).split_inclusive('\n') {
+            text.push_str(&format!("input {}\n", escape(line)));
+        }
+        for line in &self.statements {
+            text.push_str(&format!("statement {}\n", line));
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate();
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err(format!("missing '{}' header", HEADER));
+        }
+        let mut trace = Trace::default();
+        for (number, line) in lines {
+            let invalid = || format!("invalid trace entry on line {}", number + 1);
+            let (kind, value) = line.split_once(' ').ok_or_else(invalid)?;
+            match kind {
+                "clock" => trace.clock.push(value.parse().map_err(|_| invalid())?),
+                "random" => trace.random.push(value.parse().map_err(|_| invalid())?),
+                "input" => trace
+                    .input
+                    .extend(unescape(value).ok_or_else(invalid)?.bytes()),
+                "statement" => trace.statements.push(value.parse().map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(trace)
+    }
+
+    pub(crate) fn install(&self, interpreter: &mut Interpreter) {
+        let mut clock = self.clock.clone().into_iter();
+        let mut random = self.random.clone().into_iter();
+        interpreter.set_clock(Box::new(move || clock.next().unwrap_or(0.0)));
+        interpreter.set_random(Box::new(move || random.next().unwrap_or(0.0)));
+        interpreter.set_input(Box::new(Cursor::new(self.input.clone())));
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+#[derive(Clone)]
+pub struct Recorder {
+    trace: Shared<Lock<Trace>>,
+}
+
+impl Recorder {
+    pub(crate) fn install(interpreter: &mut Interpreter) -> Self {
+        let recorder = Self {
+            trace: Shared::new(Lock::new(Trace::default())),
+        };
+
+        let trace = recorder.trace.clone();
+        let mut clock = SystemClock;
+        interpreter.set_clock(Box::new(move || {
+            let now = clock.now();
+            trace.borrow_mut().clock.push(now);
+            now
+        }));
+        let trace = recorder.trace.clone();
+        let mut random = SeededRandom::from_time();
+        interpreter.set_random(Box::new(move || {
+            let value = random.next();
+            trace.borrow_mut().random.push(value);
+            value
+        }));
+        interpreter.set_input(Box::new(RecordingInput {
+            input: BufReader::new(io::stdin()),
+            trace: recorder.trace.clone(),
+        }));
+        interpreter.set_hook(Some(Box::new(recorder.clone())));
+        recorder
+    }
+
+    pub fn trace(&self) -> Trace {
+        self.trace.borrow().clone()
+    }
+}
+
+impl DebugHook for Recorder {
+    fn before_statement(&mut self, _interpreter: &mut Interpreter, line: usize) -> bool {
+        self.trace.borrow_mut().statements.push(line);
+        true
+    }
+}
+
+struct RecordingInput<R> {
+    input: R,
+    trace: Shared<Lock<Trace>>,
+}
+
+impl<R: BufRead> Read for RecordingInput<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.input.read(buf)?;
+        self.record(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for RecordingInput<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.input.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Ok(buffer) = self.input.fill_buf() {
+            let consumed = buffer[..amt.min(buffer.len())].to_vec();
+            self.record(&consumed);
+        }
+        self.input.consume(amt);
+    }
+}
+
+impl<R> RecordingInput<R> {
+    fn record(&self, bytes: &[u8]) {
+        self.trace.borrow_mut().input.extend_from_slice(bytes);
+    }
+}
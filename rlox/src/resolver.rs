@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use crate::{
+    generate_ast::{Expr, FunctionStmt, Stmt},
+    shape::{Depth, InlineCache},
+    token::Token,
+    LoxParseError,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionKind {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassKind {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Records how many scopes lie between every local variable reference and
+/// its declaration, so the interpreter can go straight to the right
+/// environment. Names that aren't declared in any enclosing block or function
+/// are looked up in the global scope.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub fn resolve(stmts: Vec<Stmt>) -> Result<Vec<Stmt>, Vec<LoxParseError>> {
+    let mut resolver = Resolver {
+        scopes: vec![],
+        function: FunctionKind::None,
+        class: ClassKind::None,
+        loops: 0,
+        errors: vec![],
+    };
+    for stmt in &stmts {
+        resolver.stmt(stmt);
+    }
+    if !resolver.errors.is_empty() {
+        return Err(resolver.errors);
+    }
+    Ok(stmts)
+}
+
+struct Resolver {
+    // Whether each name in a scope has finished its initializer yet.
+    scopes: Vec<HashMap<String, bool>>,
+    function: FunctionKind,
+    class: ClassKind,
+    loops: usize,
+    errors: Vec<LoxParseError>,
+}
+
+impl Resolver {
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(stmt) => {
+                self.scopes.push(HashMap::new());
+                for stmt in &stmt.statements {
+                    self.stmt(stmt);
+                }
+                self.scopes.pop();
+            }
+            Stmt::Break(stmt) => {
+                if self.loops == 0 {
+                    self.error(&stmt.keyword, "Can't use 'break' outside of a loop.");
+                }
+            }
+            Stmt::Continue(stmt) => {
+                if self.loops == 0 {
+                    self.error(&stmt.keyword, "Can't use 'continue' outside of a loop.");
+                }
+            }
+            Stmt::Class(stmt) => {
+                let enclosing = self.class;
+                self.class = ClassKind::Class;
+                self.define(&stmt.name);
+                if let Some(superclass) = &stmt.superclass {
+                    if let Expr::Variable(superclass) = superclass {
+                        if superclass.name.lexeme == stmt.name.lexeme {
+                            self.error(&superclass.name, "A class can't inherit from itself.");
+                        }
+                    }
+                    self.class = ClassKind::Subclass;
+                    self.expr(superclass);
+                    self.scopes
+                        .push(HashMap::from([("super".to_string(), true)]));
+                }
+                self.scopes
+                    .push(HashMap::from([("this".to_string(), true)]));
+                for method in &stmt.methods {
+                    let kind = match method.name.lexeme.as_str() {
+                        "init" => FunctionKind::Initializer,
+                        _ => FunctionKind::Method,
+                    };
+                    self.function(method, kind);
+                }
+                self.scopes.pop();
+                if stmt.superclass.is_some() {
+                    self.scopes.pop();
+                }
+                self.class = enclosing;
+            }
+            Stmt::Expression(stmt) => self.expr(&stmt.expression),
+            Stmt::Function(stmt) => {
+                self.define(&stmt.name);
+                self.function(stmt, FunctionKind::Function);
+            }
+            Stmt::If(stmt) => {
+                self.expr(&stmt.condition);
+                self.stmt(&stmt.then_branch);
+                if let Some(else_branch) = &stmt.else_branch {
+                    self.stmt(else_branch);
+                }
+            }
+            Stmt::Print(stmt) => self.expr(&stmt.expression),
+            Stmt::Return(stmt) => {
+                if self.function == FunctionKind::None {
+                    self.error(&stmt._keyword, "Can't return from top-level code.");
+                }
+                if let Some(value) = &stmt.value {
+                    if self.function == FunctionKind::Initializer {
+                        self.error(&stmt._keyword, "Can't return a value from an initializer.");
+                    }
+                    self.expr(value);
+                }
+            }
+            Stmt::While(stmt) => {
+                self.expr(&stmt.condition);
+                self.loops += 1;
+                self.stmt(&stmt.body);
+                self.loops -= 1;
+                if let Some(increment) = &stmt.increment {
+                    self.expr(increment);
+                }
+            }
+            Stmt::Var(stmt) => {
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert(stmt.name.lexeme.clone(), false);
+                }
+                if let Some(initializer) = &stmt.initializer {
+                    self.expr(initializer);
+                }
+                self.define(&stmt.name);
+            }
+            Stmt::Import(_) | Stmt::Macro(_) => (),
+        }
+    }
+
+    fn function(&mut self, stmt: &FunctionStmt, kind: FunctionKind) {
+        let enclosing = self.function;
+        let loops = std::mem::take(&mut self.loops);
+        self.function = kind;
+        self.scopes.push(HashMap::new());
+        for param in &stmt.params {
+            self.define(param);
+        }
+        for stmt in &stmt.body {
+            self.stmt(stmt);
+        }
+        self.scopes.pop();
+        self.function = enclosing;
+        self.loops = loops;
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign(expr) => {
+                self.expr(&expr.value);
+                self.resolve(&expr.name, &expr.cache);
+            }
+            Expr::Binary(expr) => {
+                self.expr(&expr.left);
+                self.expr(&expr.right);
+            }
+            Expr::Call(expr) => {
+                self.expr(&expr.callee);
+                for argument in &expr.arguments {
+                    self.expr(argument);
+                }
+            }
+            Expr::Get(expr) => self.expr(&expr.object),
+            Expr::Grouping(expr) => self.expr(&expr.expression),
+            Expr::Interpolation(expr) => {
+                for part in &expr.parts {
+                    self.expr(part);
+                }
+            }
+            Expr::Literal(_) => (),
+            Expr::Logical(expr) => {
+                self.expr(&expr.left);
+                self.expr(&expr.right);
+            }
+            Expr::Set(expr) => {
+                self.expr(&expr.object);
+                self.expr(&expr.value);
+            }
+            Expr::Super(expr) => {
+                match self.class {
+                    ClassKind::None => {
+                        self.error(&expr.keyword, "Can't use 'super' outside of a class.")
+                    }
+                    ClassKind::Class => self.error(
+                        &expr.keyword,
+                        "Can't use 'super' in a class with no superclass.",
+                    ),
+                    ClassKind::Subclass => (),
+                }
+                self.resolve(&expr.keyword, &expr.cache);
+            }
+            Expr::This(expr) => {
+                if self.class == ClassKind::None {
+                    self.error(&expr.keyword, "Can't use 'this' outside of a class.");
+                }
+                self.resolve(&expr.keyword, &expr.cache);
+            }
+            Expr::Unary(expr) => self.expr(&expr.right),
+            Expr::Variable(expr) => {
+                if self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(&expr.name.lexeme))
+                    == Some(&false)
+                {
+                    self.error(
+                        &expr.name,
+                        "Can't read local variable in its own initializer.",
+                    );
+                }
+                self.resolve(&expr.name, &expr.cache);
+            }
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+
+    fn resolve(&mut self, name: &Token, cache: &InlineCache) {
+        if name.global {
+            cache.resolve(Depth::Global);
+            return;
+        }
+        let depth = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.lexeme));
+        cache.resolve(match depth {
+            Some(depth) => Depth::Local(depth),
+            None => Depth::Global,
+        });
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors
+            .push(LoxParseError(token.clone(), message.into()));
+    }
+}
@@ -1,15 +1,22 @@
 use crate::{
+    number::{self, Number},
     token::{Object, Token},
     token_type::TokenType,
     LoxScanError,
 };
 
+/// Splits Lox source text into tokens. Lexical errors are kept in place of
+/// the tokens they affect so scanning continues to the end of the input.
 pub struct Scanner<'a> {
     source: &'a str,
     tokens: Vec<Result<Token, LoxScanError>>,
     start: usize,
     current: usize,
     line: usize,
+    line_start: usize,
+    keep_comments: bool,
+    // How many unclosed '{' each open `${` has seen, innermost last.
+    interpolations: Vec<usize>,
 }
 
 impl<'a> Scanner<'a> {
@@ -20,9 +27,20 @@ impl<'a> Scanner<'a> {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            keep_comments: false,
+            interpolations: vec![],
         }
     }
 
+    pub fn with_comments(source: &'a str) -> Self {
+        Self {
+            keep_comments: true,
+            ..Self::new(source)
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub fn scan_tokens(&mut self) -> &Vec<Result<Token, LoxScanError>> {
         while !self.is_at_end() {
             self.start = self.current;
@@ -34,7 +52,14 @@ impl<'a> Scanner<'a> {
             "".into(),
             Object::None,
             self.line,
+            self.column(self.current),
         )));
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            tokens = self.tokens.len(),
+            errors = self.tokens.iter().filter(|token| token.is_err()).count(),
+            "scanned"
+        );
         &self.tokens
     }
 
@@ -43,11 +68,27 @@ impl<'a> Scanner<'a> {
         match c {
             '(' => self.add_token(TokenType::LeftParen),
             ')' => self.add_token(TokenType::RightParen),
-            '{' => self.add_token(TokenType::LeftBrace),
-            '}' => self.add_token(TokenType::RightBrace),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LeftBrace);
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.string();
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    self.add_token(TokenType::RightBrace);
+                }
+                None => self.add_token(TokenType::RightBrace),
+            },
             ',' => self.add_token(TokenType::Comma),
             '.' => self.add_token(TokenType::Dot),
             '-' => self.add_token(TokenType::Minus),
+            '%' => self.add_token(TokenType::Percent),
             '+' => self.add_token(TokenType::Plus),
             '*' => self.add_token(TokenType::Star),
             ';' => self.add_token(TokenType::SemiColon),
@@ -85,30 +126,49 @@ impl<'a> Scanner<'a> {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    if self.keep_comments {
+                        self.add_token(TokenType::Comment);
+                    }
+                } else if self.match_token('*') {
+                    self.block_comment();
                 } else {
                     self.add_token(TokenType::Slash);
                 }
             }
             ' ' | '\t' | '\r' => (),
-            '\n' => self.line += 1,
+            '\n' => self.new_line(),
 
             '"' => self.string(),
 
             _ => {
                 if c.is_ascii_digit() {
                     self.number()
-                } else if c.is_alphabetic() {
+                } else if c.is_alphabetic() || c == '_' {
                     self.identifier();
                 } else {
-                    self.tokens.push(Err(LoxScanError(
-                        self.line,
-                        "Unexpected character.".to_string(),
-                    )))
+                    self.unexpected(c);
                 }
             }
         };
     }
 
+    fn unexpected(&mut self, c: char) {
+        let mut text = c.to_string();
+        while !self.is_at_end() && is_unexpected(self.peek()) {
+            text.push(self.advance());
+        }
+        let message = if text.chars().count() == 1 {
+            format!("Unexpected character '{}'.", text)
+        } else {
+            format!("Unexpected characters '{}'.", text)
+        };
+        self.tokens.push(Err(LoxScanError(
+            self.line,
+            self.column(self.start),
+            message,
+        )))
+    }
+
     fn identifier(&mut self) {
         while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
             self.advance();
@@ -116,6 +176,8 @@ impl<'a> Scanner<'a> {
         let text = self.source[self.start..self.current].to_string();
         if let Some(keyword) = self.keywords(&text) {
             self.add_token(keyword);
+        } else if let Some(value) = number::special(&text) {
+            self.add_token_with_literal(TokenType::Number, Object::Num(value));
         } else {
             self.add_token_with_literal(TokenType::Identifier, Object::String(text));
         }
@@ -131,61 +193,94 @@ impl<'a> Scanner<'a> {
                 self.advance();
             }
         }
-        let num: f64 = self.source[self.start..self.current].parse().unwrap();
+        let num: Number = self.source[self.start..self.current].parse().unwrap();
         self.add_token_with_literal(TokenType::Number, Object::Num(num));
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-        self.source
-            .chars()
-            .nth(self.current + 1)
-            .expect("peek_next in scanner")
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
+    // Scans the rest of a string literal, or the piece of one that follows the
+    // `}` closing an interpolation. A piece that ends in `${` becomes an
+    // Interpolation token and the expression after it is scanned as code.
     fn string(&mut self) {
-        while self.peek() != '"' && !self.is_at_end() && self.peek() != '\n' {
-            self.advance();
+        // Strings may span lines, so the token keeps where it started.
+        let line = self.line;
+        let column = self.column(self.start);
+        while self.peek() != '"' && !self.is_at_end() {
+            if self.peek() == '$' && self.peek_next() == '{' {
+                self.current += 2;
+                self.interpolations.push(0);
+                let value = self.source[self.start + 1..self.current - 2].to_string();
+                self.add_token_at(
+                    TokenType::Interpolation,
+                    Object::String(value),
+                    line,
+                    column,
+                );
+                return;
+            }
+            if self.advance() == '\n' {
+                self.new_line();
+            }
         }
-        if self.is_at_end() || self.peek() == '\n' {
+        if self.is_at_end() {
             self.tokens.push(Err(LoxScanError(
-                self.line,
+                line,
+                column,
                 "Unterminated string.".to_string(),
             )));
             return;
         }
         self.advance();
         let value = self.source[self.start + 1..self.current - 1].to_string();
-        self.add_token_with_literal(TokenType::String, Object::String(value));
+        self.add_token_at(TokenType::String, Object::String(value), line, column);
+    }
+
+    fn block_comment(&mut self) {
+        let line = self.line;
+        let column = self.column(self.start);
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                self.tokens.push(Err(LoxScanError(
+                    line,
+                    column,
+                    "Unterminated block comment.".to_string(),
+                )));
+                return;
+            }
+            match self.advance() {
+                '/' if self.match_token('*') => depth += 1,
+                '*' if self.match_token('/') => depth -= 1,
+                '\n' => self.new_line(),
+                _ => (),
+            }
+        }
+        if self.keep_comments {
+            self.add_token_at(TokenType::Comment, Object::None, line, column);
+        }
     }
 
     fn peek(&mut self) -> char {
-        self.source
-            .chars()
-            .nth(self.current)
-            .expect("peek in scanner")
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn match_token(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
+        if self.is_at_end() || self.peek() != expected {
             return false;
         }
-        if self.source.chars().nth(self.current).unwrap() != expected {
-            return false;
-        }
-        self.current += 1;
+        self.current += expected.len_utf8();
         true
     }
 
     fn advance(&mut self) -> char {
-        let c = self
-            .source
+        let c = self.source[self.current..]
             .chars()
-            .nth(self.current)
+            .next()
             .expect("advance in scanner");
-        self.current += 1;
+        self.current += c.len_utf8();
         c
     }
 
@@ -194,9 +289,22 @@ impl<'a> Scanner<'a> {
     }
 
     fn add_token_with_literal(&mut self, token_type: TokenType, literal: Object) {
+        self.add_token_at(token_type, literal, self.line, self.column(self.start));
+    }
+
+    fn add_token_at(&mut self, token_type: TokenType, literal: Object, line: usize, column: usize) {
         let text = self.source[self.start..self.current].to_string();
         self.tokens
-            .push(Ok(Token::new(token_type, text, literal, self.line)));
+            .push(Ok(Token::new(token_type, text, literal, line, column)));
+    }
+
+    fn column(&self, offset: usize) -> usize {
+        self.source[self.line_start..offset].chars().count() + 1
+    }
+
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
     }
 
     fn is_at_end(&self) -> bool {
@@ -206,12 +314,16 @@ impl<'a> Scanner<'a> {
     fn keywords(&self, identifier: &str) -> Option<TokenType> {
         match identifier {
             "and" => Some(TokenType::And),
+            "break" => Some(TokenType::Break),
             "class" => Some(TokenType::Class),
+            "continue" => Some(TokenType::Continue),
             "else" => Some(TokenType::Else),
             "false" => Some(TokenType::False),
             "for" => Some(TokenType::For),
             "fun" => Some(TokenType::Fun),
             "if" => Some(TokenType::If),
+            "import" => Some(TokenType::Import),
+            "macro" => Some(TokenType::Macro),
             "nil" => Some(TokenType::Nil),
             "or" => Some(TokenType::Or),
             "print" => Some(TokenType::Print),
@@ -225,3 +337,7 @@ impl<'a> Scanner<'a> {
         }
     }
 }
+
+fn is_unexpected(c: char) -> bool {
+    !(c.is_whitespace() || c.is_alphanumeric() || c == '_' || "(){},.-+*;!=<>/\"".contains(c))
+}
@@ -0,0 +1,141 @@
+use std::{
+    io::{self, BufReader, Read, Write},
+    net::TcpListener,
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+
+use crate::{
+    cancel::CancellationHandle,
+    sync::{Reader, Writer},
+};
+
+// `rlox serve` runs the REPL for one client at a time. A client that hangs up
+// in the middle of a command must not take the server down with it, so a
+// failed read ends the session like end of input, and a failed write cancels
+// whatever is running and drops the rest of its output.
+
+pub(crate) enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+pub(crate) struct Session {
+    pub peer: String,
+    pub input: Box<Reader>,
+    pub output: Box<Writer>,
+    pub diagnostic_output: Box<Writer>,
+}
+
+impl Listener {
+    /// Binds `unix:PATH` to a Unix socket and anything else to a TCP address.
+    pub fn bind(address: &str) -> io::Result<Self> {
+        #[cfg(unix)]
+        if let Some(path) = address.strip_prefix("unix:") {
+            return UnixListener::bind(path).map(Listener::Unix);
+        }
+        TcpListener::bind(address).map(Listener::Tcp)
+    }
+
+    pub fn accept(&self, cancellation: &CancellationHandle) -> io::Result<Session> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, peer) = listener.accept()?;
+                Ok(Session {
+                    peer: peer.to_string(),
+                    input: Box::new(BufReader::new(Connection::new(
+                        stream.try_clone()?,
+                        cancellation,
+                    ))),
+                    output: Box::new(Connection::new(stream.try_clone()?, cancellation)),
+                    diagnostic_output: Box::new(Connection::new(stream, cancellation)),
+                })
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                let (stream, _) = listener.accept()?;
+                Ok(Session {
+                    peer: "a Unix socket client".into(),
+                    input: Box::new(BufReader::new(Connection::new(
+                        stream.try_clone()?,
+                        cancellation,
+                    ))),
+                    output: Box::new(Connection::new(stream.try_clone()?, cancellation)),
+                    diagnostic_output: Box::new(Connection::new(stream, cancellation)),
+                })
+            }
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix(listener) = self {
+            if let Some(path) = listener
+                .local_addr()
+                .ok()
+                .and_then(|address| address.as_pathname().map(|path| path.to_owned()))
+            {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+struct Connection<S> {
+    stream: S,
+    cancellation: CancellationHandle,
+    closed: bool,
+}
+
+impl<S> Connection<S> {
+    fn new(stream: S, cancellation: &CancellationHandle) -> Self {
+        Self {
+            stream,
+            cancellation: cancellation.clone(),
+            closed: false,
+        }
+    }
+
+    fn close(&mut self) {
+        if !self.closed {
+            self.closed = true;
+            self.cancellation.cancel();
+        }
+    }
+}
+
+impl<S: Read> Read for Connection<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.closed {
+            return Ok(0);
+        }
+        match self.stream.read(buf) {
+            Ok(read) => Ok(read),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => Err(err),
+            Err(_) => {
+                self.closed = true;
+                Ok(0)
+            }
+        }
+    }
+}
+
+impl<S: Write> Write for Connection<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.closed && self.stream.write_all(buf).is_err() {
+            self.close();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.closed && self.stream.flush().is_err() {
+            self.close();
+        }
+        Ok(())
+    }
+}
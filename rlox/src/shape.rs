@@ -0,0 +1,134 @@
+use std::{collections::HashMap, fmt::Debug, ops::Deref};
+
+use crate::sync::{Lock, Shared};
+
+// Environments that declare the same names in the same order share one
+// `Shape`, found by following transitions from the root shape of their
+// interpreter. An inline cache remembers the shapes it walked through to find
+// a variable, so a later lookup only has to compare pointers on the way to the
+// slot instead of hashing the name in every scope. Variables the resolver has
+// already placed, either a fixed number of scopes out or in the global scope,
+// skip the walk and only remember the shape of the environment they live in.
+
+pub struct Shape {
+    slots: HashMap<String, usize>,
+    transitions: Lock<HashMap<String, Shared<Shape>>>,
+}
+
+impl Shape {
+    pub fn root() -> Shared<Self> {
+        Shared::new(Self {
+            slots: HashMap::new(),
+            transitions: Lock::new(HashMap::new()),
+        })
+    }
+
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        self.slots.get(name).copied()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = (&String, usize)> {
+        self.slots.iter().map(|(name, slot)| (name, *slot))
+    }
+
+    pub fn with(shape: &Shared<Self>, name: &str) -> Shared<Self> {
+        let mut transitions = shape.transitions.borrow_mut();
+        if let Some(next) = transitions.get(name) {
+            return next.clone();
+        }
+        let mut slots = shape.slots.clone();
+        slots.insert(name.into(), shape.slots.len());
+        let next = Shared::new(Self {
+            slots,
+            transitions: Lock::new(HashMap::new()),
+        });
+        transitions.insert(name.into(), next.clone());
+        next
+    }
+}
+
+impl PartialEq for Shape {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other) || self.slots == other.slots
+    }
+}
+
+impl Debug for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shape")
+            .field("slots", &self.slots)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Resolution {
+    pub shapes: Vec<Shared<Shape>>,
+    pub slot: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Depth {
+    Local(usize),
+    Global,
+}
+
+impl Depth {
+    pub fn outer(self) -> Self {
+        match self {
+            Depth::Local(depth) => Depth::Local(depth - 1),
+            Depth::Global => Depth::Global,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct InlineCache {
+    depth: Lock<Option<Depth>>,
+    resolution: Shared<Lock<Option<Resolution>>>,
+}
+
+impl InlineCache {
+    pub(crate) fn get(&self) -> impl Deref<Target = Option<Resolution>> + '_ {
+        self.resolution.borrow()
+    }
+
+    pub(crate) fn set(&self, resolution: Resolution) {
+        *self.resolution.borrow_mut() = Some(resolution);
+    }
+
+    pub(crate) fn depth(&self) -> Option<Depth> {
+        *self.depth.borrow()
+    }
+
+    pub(crate) fn resolve(&self, depth: Depth) {
+        *self.depth.borrow_mut() = Some(depth);
+    }
+
+    pub(crate) fn slot_in(&self, shape: &Shared<Shape>) -> Option<usize> {
+        match &*self.get() {
+            Some(resolution)
+                if resolution.shapes.len() == 1 && Shared::ptr_eq(&resolution.shapes[0], shape) =>
+            {
+                Some(resolution.slot)
+            }
+            _ => None,
+        }
+    }
+}
+
+// Copies of a macro body are resolved separately, so each keeps its own depth.
+impl Clone for InlineCache {
+    fn clone(&self) -> Self {
+        Self {
+            depth: Lock::new(self.depth()),
+            resolution: self.resolution.clone(),
+        }
+    }
+}
+
+impl Debug for InlineCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InlineCache").finish_non_exhaustive()
+    }
+}
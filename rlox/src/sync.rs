@@ -0,0 +1,124 @@
+// With the `sync` feature, values and environments are shared through `Arc`
+// and `Mutex` so that `Lox` is `Send` and `Sync` and can live behind a
+// `Mutex` or `RwLock` in a multi-threaded host. Hooks, sinks and writers
+// handed to it must then be `Send + Sync` too. Every variable lookup then takes a lock and every
+// clone of a function value is an atomic increment, which makes scripts
+// noticeably slower than with the default `Rc`/`RefCell` build, so only
+// enable it when the interpreter has to cross threads.
+
+#[cfg(not(feature = "sync"))]
+mod imp {
+    use std::{
+        cell::{Ref, RefCell, RefMut},
+        io::{BufRead, Write},
+    };
+
+    use crate::{diagnostic::Diagnostic, options::Usage, preprocess::Preprocessed, token::Object};
+
+    pub type Shared<T> = std::rc::Rc<T>;
+
+    pub type Writer = dyn Write;
+
+    pub type Reader = dyn BufRead;
+
+    pub type PrintHook = dyn FnMut(&Object);
+
+    pub type DiagnosticSink = dyn FnMut(&Diagnostic);
+
+    pub type Meter = dyn FnMut(&Usage) -> bool;
+
+    pub type Preprocessor = dyn Fn(&str) -> Preprocessed;
+
+    pub trait MaybeSend {}
+
+    impl<T: ?Sized> MaybeSend for T {}
+
+    pub trait MaybeSync {}
+
+    impl<T: ?Sized> MaybeSync for T {}
+
+    #[derive(Debug, Default)]
+    pub struct Lock<T>(RefCell<T>);
+
+    impl<T> Lock<T> {
+        pub fn new(value: T) -> Self {
+            Self(RefCell::new(value))
+        }
+
+        pub fn borrow(&self) -> Ref<'_, T> {
+            self.0.borrow()
+        }
+
+        pub fn borrow_mut(&self) -> RefMut<'_, T> {
+            self.0.borrow_mut()
+        }
+
+        pub fn into_inner(self) -> T {
+            self.0.into_inner()
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+mod imp {
+    use std::{
+        io::{BufRead, Write},
+        sync::{Mutex, MutexGuard},
+    };
+
+    use crate::{diagnostic::Diagnostic, options::Usage, preprocess::Preprocessed, token::Object};
+
+    pub type Shared<T> = std::sync::Arc<T>;
+
+    pub type Writer = dyn Write + Send + Sync;
+
+    pub type Reader = dyn BufRead + Send;
+
+    pub type PrintHook = dyn FnMut(&Object) + Send;
+
+    pub type DiagnosticSink = dyn FnMut(&Diagnostic) + Send + Sync;
+
+    pub type Meter = dyn FnMut(&Usage) -> bool + Send + Sync;
+
+    pub type Preprocessor = dyn Fn(&str) -> Preprocessed + Send + Sync;
+
+    pub trait MaybeSend: Send {}
+
+    impl<T: Send + ?Sized> MaybeSend for T {}
+
+    pub trait MaybeSync: Send + Sync {}
+
+    impl<T: Send + Sync + ?Sized> MaybeSync for T {}
+
+    #[derive(Debug, Default)]
+    pub struct Lock<T>(Mutex<T>);
+
+    impl<T> Lock<T> {
+        pub fn new(value: T) -> Self {
+            Self(Mutex::new(value))
+        }
+
+        pub fn borrow(&self) -> MutexGuard<'_, T> {
+            self.0.lock().expect("lock")
+        }
+
+        pub fn borrow_mut(&self) -> MutexGuard<'_, T> {
+            self.0.lock().expect("lock")
+        }
+
+        pub fn into_inner(self) -> T {
+            self.0.into_inner().expect("lock")
+        }
+    }
+}
+
+pub use imp::{
+    DiagnosticSink, Lock, MaybeSend, MaybeSync, Meter, Preprocessor, PrintHook, Reader, Shared,
+    Writer,
+};
+
+impl<T: PartialEq> PartialEq for Lock<T> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other) || *self.borrow() == *other.borrow()
+    }
+}
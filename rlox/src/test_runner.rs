@@ -0,0 +1,162 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+struct Expectations {
+    output: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl Expectations {
+    fn parse(src: &str) -> Self {
+        let mut output = vec![];
+        let mut errors = vec![];
+        for line in src.lines() {
+            let Some((_, comment)) = line.split_once("//") else {
+                continue;
+            };
+            let comment = comment.trim();
+            if let Some(expected) = comment.strip_prefix("expect:") {
+                output.push(expected.trim().to_string());
+            } else if let Some(expected) = comment.strip_prefix("expect runtime error:") {
+                errors.push(expected.trim().to_string());
+            } else if let Some(expected) = comment.strip_prefix("expect error:") {
+                errors.push(expected.trim().to_string());
+            }
+        }
+        Self { output, errors }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.output.is_empty() && self.errors.is_empty()
+    }
+}
+
+pub struct TestRunner {
+    filter: Option<String>,
+    quiet: bool,
+    passed: usize,
+    failed: usize,
+}
+
+impl TestRunner {
+    pub fn new(filter: Option<String>, quiet: bool) -> Self {
+        Self {
+            filter,
+            quiet,
+            passed: 0,
+            failed: 0,
+        }
+    }
+
+    pub fn run(&mut self, paths: &[PathBuf]) -> io::Result<bool> {
+        let mut files = vec![];
+        if paths.is_empty() {
+            collect(Path::new("."), &mut files)?;
+        }
+        for path in paths {
+            collect(path, &mut files)?;
+        }
+        files.sort();
+
+        for file in files {
+            let name = file.to_string_lossy().to_string();
+            if self
+                .filter
+                .as_ref()
+                .is_some_and(|filter| !name.contains(filter))
+            {
+                continue;
+            }
+            let src = fs::read_to_string(&file)?;
+            let expectations = Expectations::parse(&src);
+            let is_test = name.ends_with("_test.lox");
+            if expectations.is_empty() && !is_test {
+                continue;
+            }
+            self.run_file(&name, &expectations)?;
+        }
+
+        println!();
+        println!("{} passed, {} failed", self.passed, self.failed);
+        Ok(self.failed == 0)
+    }
+
+    fn run_file(&mut self, name: &str, expectations: &Expectations) -> io::Result<()> {
+        let output = Command::new(env::current_exe()?)
+            .args(["--allow", "all", name])
+            .output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let actual: Vec<&str> = stdout.lines().collect();
+
+        let mut failures = vec![];
+        if actual != expectations.output {
+            failures.push(diff(&expectations.output, &actual));
+        }
+        for error in &expectations.errors {
+            if !stderr.contains(error.as_str()) {
+                failures.push(format!("    missing error: {}", error));
+            }
+        }
+        if expectations.errors.is_empty() && !stderr.is_empty() {
+            let errors: Vec<String> = stderr
+                .lines()
+                .map(|line| format!("    unexpected error: {}", line))
+                .collect();
+            failures.push(errors.join("\n"));
+        }
+
+        if failures.is_empty() {
+            if !self.quiet {
+                println!("PASS {}", name);
+            }
+            self.passed += 1;
+        } else {
+            println!("FAIL {}", name);
+            for failure in failures {
+                println!("{}", failure);
+            }
+            self.failed += 1;
+        }
+        Ok(())
+    }
+}
+
+fn collect(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect(&path, files)?;
+        } else if path.extension().is_some_and(|extension| extension == "lox") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn diff(expected: &[String], actual: &[&str]) -> String {
+    let mut lines = vec![];
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(expected), Some(actual)) if expected == actual => {
+                lines.push(format!("      {}", expected))
+            }
+            (expected, actual) => {
+                if let Some(expected) = expected {
+                    lines.push(format!("    - {}", expected));
+                }
+                if let Some(actual) = actual {
+                    lines.push(format!("    + {}", actual));
+                }
+            }
+        }
+    }
+    lines.join("\n")
+}
@@ -1,6 +1,18 @@
-use std::fmt::Display;
+use std::{any::Any, error::Error, fmt::Display};
 
-use crate::{environment::Environment, generate_ast::FunctionStmt, token_type::TokenType};
+#[cfg(feature = "json")]
+use serde_json::Value;
+
+use crate::{
+    callable::{LoxCallable, LoxFunction},
+    class::{LoxClass, LoxInstance},
+    generate_ast::FunctionStmt,
+    native::NativeFunction,
+    number::{Number, Numeric},
+    sync::Shared,
+    token_type::TokenType,
+    userdata::UserData,
+};
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Token {
@@ -8,14 +20,24 @@ pub struct Token {
     pub lexeme: String,
     pub literal: Object,
     pub line: usize,
+    pub column: usize,
+    pub source: Option<Shared<str>>,
+    pub expansion: Option<Shared<Token>>,
+    /// Set on names a macro body uses without declaring them, which always
+    /// refer to globals however the call site's locals are named.
+    pub global: bool,
 }
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum Object {
     String(String),
-    Num(f64),
+    Num(Number),
     Bool(bool),
-    Fun(Box<FunctionStmt>, Environment),
+    Fun(Shared<LoxFunction>),
+    Native(Shared<NativeFunction>),
+    Class(Shared<LoxClass>),
+    Instance(Shared<LoxInstance>),
+    UserData(Shared<UserData>),
     None,
 }
 
@@ -26,12 +48,22 @@ impl PartialEq for FunctionStmt {
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, literal: Object, line: usize) -> Self {
+    pub fn new(
+        token_type: TokenType,
+        lexeme: String,
+        literal: Object,
+        line: usize,
+        column: usize,
+    ) -> Self {
         Self {
             token_type,
             lexeme,
             literal,
             line,
+            column,
+            source: None,
+            expansion: None,
+            global: false,
         }
     }
 }
@@ -46,9 +78,13 @@ impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
             Object::String(s) => s.to_string(),
-            Object::Num(n) => n.to_string(),
+            Object::Num(n) => n.to_lox_string(),
             Object::Bool(b) => b.to_string(),
-            Object::Fun(stmt, _) => stmt.name.to_string(),
+            Object::Fun(fun) => fun.declaration.name.to_string(),
+            Object::Native(native) => native.name.clone(),
+            Object::Class(class) => class.name.clone(),
+            Object::Instance(instance) => format!("{} instance", instance.class.name),
+            Object::UserData(data) => data.type_name().into(),
             Object::None => "[None]".to_string(),
         };
         write!(f, "{}", str)
@@ -56,31 +92,203 @@ impl Display for Object {
 }
 
 impl Object {
-    pub fn num(&self) -> Result<f64, ()> {
+    pub fn type_name(&self) -> &'static str {
         match self {
-            Object::Num(n) => Ok(*n),
-            _ => Err(()),
+            Object::String(_) => "string",
+            Object::Num(_) => "number",
+            Object::Bool(_) => "boolean",
+            Object::Fun(_) | Object::Native(_) => "function",
+            Object::Class(_) => "class",
+            Object::Instance(_) => "instance",
+            Object::UserData(_) => "userdata",
+            Object::None => "nil",
         }
     }
 
-    pub fn str(&self) -> Result<String, ()> {
+    pub fn describe(&self) -> String {
         match self {
-            Object::String(str) => Ok(str.into()),
-            _ => Err(()),
+            Object::String(s) if s.chars().count() > 20 => {
+                format!("string (\"{}...\")", s.chars().take(17).collect::<String>())
+            }
+            Object::String(s) => format!("string (\"{}\")", s),
+            Object::Fun(fun) => format!("function ({})", fun.declaration.name.lexeme),
+            Object::Instance(instance) => format!("{} instance", instance.class.name),
+            Object::None => "nil".into(),
+            value => format!("{} ({})", value.type_name(), value),
+        }
+    }
+
+    fn conversion_error(&self, expected: &'static str) -> ConversionError {
+        ConversionError {
+            expected,
+            found: self.type_name(),
         }
     }
 
-    pub fn arity(&self) -> Result<usize, ()> {
+    pub fn as_user_data<T: Any>(&self) -> Option<&T> {
         match self {
-            Object::Fun(stmt, _) => Ok(stmt.params.len()),
+            Object::UserData(data) => data.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<Value, ConversionError> {
+        match self {
+            Object::String(s) => Ok(Value::String(s.clone())),
+            Object::Num(n) => {
+                Ok(serde_json::Number::from_f64(n.to_float()).map_or(Value::Null, Value::Number))
+            }
+            Object::Bool(b) => Ok(Value::Bool(*b)),
+            Object::None => Ok(Value::Null),
+            _ => Err(self.conversion_error(JSON_TYPES)),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(value: &Value) -> Result<Object, ConversionError> {
+        match value {
+            Value::String(s) => Ok(Object::String(s.clone())),
+            Value::Number(n) => Ok(n.as_f64().unwrap_or(f64::NAN).into()),
+            Value::Bool(b) => Ok(Object::Bool(*b)),
+            Value::Null => Ok(Object::None),
+            Value::Array(_) => Err(ConversionError {
+                expected: JSON_TYPES,
+                found: "array",
+            }),
+            Value::Object(_) => Err(ConversionError {
+                expected: JSON_TYPES,
+                found: "object",
+            }),
+        }
+    }
+
+    pub(crate) fn num(&self) -> Result<&Number, ()> {
+        match self {
+            Object::Num(n) => Ok(n),
             _ => Err(()),
         }
     }
 
-    pub fn get_closure(&mut self) -> Result<&mut Environment, ()> {
+    pub(crate) fn str(&self) -> Result<String, ()> {
         match self {
-            Object::Fun(_, env) => Ok(env),
+            Object::String(str) => Ok(str.into()),
             _ => Err(()),
         }
     }
+
+    pub(crate) fn callable(&self) -> Option<Shared<dyn LoxCallable>> {
+        match self {
+            Object::Fun(fun) => Some(fun.clone()),
+            Object::Native(native) => Some(native.clone()),
+            Object::Class(class) => Some(Shared::new(class.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+const JSON_TYPES: &str = "string, number, boolean or nil";
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ConversionError {
+    pub expected: &'static str,
+    pub found: &'static str,
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Expected {} but got {}.", self.expected, self.found)
+    }
+}
+
+impl Error for ConversionError {}
+
+impl From<ConversionError> for String {
+    fn from(value: ConversionError) -> Self {
+        value.to_string()
+    }
+}
+
+impl From<f64> for Object {
+    fn from(value: f64) -> Self {
+        Object::Num(Number::from_float(value))
+    }
+}
+
+impl From<bool> for Object {
+    fn from(value: bool) -> Self {
+        Object::Bool(value)
+    }
+}
+
+impl From<String> for Object {
+    fn from(value: String) -> Self {
+        Object::String(value)
+    }
+}
+
+impl From<&str> for Object {
+    fn from(value: &str) -> Self {
+        Object::String(value.into())
+    }
+}
+
+impl From<UserData> for Object {
+    fn from(value: UserData) -> Self {
+        Object::UserData(Shared::new(value))
+    }
+}
+
+impl<T: Into<Object>> From<Option<T>> for Object {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => value.into(),
+            None => Object::None,
+        }
+    }
+}
+
+impl TryFrom<Object> for f64 {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Num(n) => Ok(n.to_float()),
+            value => Err(value.conversion_error("number")),
+        }
+    }
+}
+
+impl TryFrom<Object> for bool {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Bool(b) => Ok(b),
+            value => Err(value.conversion_error("boolean")),
+        }
+    }
+}
+
+impl TryFrom<Object> for String {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::String(s) => Ok(s),
+            value => Err(value.conversion_error("string")),
+        }
+    }
+}
+
+impl<T: TryFrom<Object, Error = ConversionError>> TryFrom<Object> for Option<T> {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::None => Ok(None),
+            value => T::try_from(value).map(Some),
+        }
+    }
 }
@@ -10,6 +10,7 @@ pub enum TokenType {
     Comma,
     Dot,
     Minus,
+    Percent,
     Plus,
     SemiColon,
     Slash,
@@ -28,16 +29,21 @@ pub enum TokenType {
     // リテラル
     Identifier,
     String,
+    Interpolation,
     Number,
 
     // キーワード
     And,
+    Break,
     Class,
+    Continue,
     Else,
     False,
     Fun,
     For,
     If,
+    Import,
+    Macro,
     Nil,
     Or,
     Print,
@@ -48,6 +54,7 @@ pub enum TokenType {
     Var,
     While,
 
+    Comment,
     Eof,
 }
 
@@ -61,6 +68,7 @@ impl Display for TokenType {
             TokenType::Comma => "Comma",
             TokenType::Dot => "Dot",
             TokenType::Minus => "Minus",
+            TokenType::Percent => "Percent",
             TokenType::Plus => "Plus",
             TokenType::SemiColon => "SemiColon",
             TokenType::Slash => "Slash",
@@ -75,14 +83,19 @@ impl Display for TokenType {
             TokenType::LessEqual => "LessEqual",
             TokenType::Identifier => "Identifier",
             TokenType::String => "String",
+            TokenType::Interpolation => "Interpolation",
             TokenType::Number => "Number",
             TokenType::And => "And",
+            TokenType::Break => "Break",
             TokenType::Class => "Class",
+            TokenType::Continue => "Continue",
             TokenType::Else => "Else",
             TokenType::False => "False",
             TokenType::Fun => "Fun",
             TokenType::For => "For",
             TokenType::If => "If",
+            TokenType::Import => "Import",
+            TokenType::Macro => "Macro",
             TokenType::Nil => "Nil",
             TokenType::Or => "Or",
             TokenType::Print => "Print",
@@ -92,6 +105,7 @@ impl Display for TokenType {
             TokenType::True => "True",
             TokenType::Var => "Var",
             TokenType::While => "While",
+            TokenType::Comment => "Comment",
             TokenType::Eof => "EOF",
         };
         write!(f, "{}", str)
@@ -0,0 +1,112 @@
+use std::{any::Any, collections::HashMap, fmt::Debug};
+
+use crate::{
+    native::{NativeFunction, NativeResult},
+    sync::{MaybeSync, Shared},
+    token::Object,
+};
+
+#[cfg(not(feature = "sync"))]
+pub(crate) type AnyValue = dyn Any;
+#[cfg(feature = "sync")]
+pub(crate) type AnyValue = dyn Any + Send + Sync;
+
+pub(crate) type Members = HashMap<String, Shared<NativeFunction>>;
+
+pub struct UserData {
+    type_name: String,
+    value: Shared<AnyValue>,
+    methods: Members,
+    getters: Members,
+}
+
+impl UserData {
+    pub fn new(type_name: &str, value: impl Any + MaybeSync) -> Self {
+        Self::from_parts(
+            type_name,
+            Shared::new(value),
+            Members::new(),
+            Members::new(),
+        )
+    }
+
+    pub(crate) fn from_parts(
+        type_name: &str,
+        value: Shared<AnyValue>,
+        methods: Members,
+        getters: Members,
+    ) -> Self {
+        Self {
+            type_name: type_name.into(),
+            value,
+            methods,
+            getters,
+        }
+    }
+
+    pub fn with_method(
+        mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Object]) -> NativeResult + MaybeSync + 'static,
+    ) -> Self {
+        let method = NativeFunction::new(name, arity, function);
+        self.methods.insert(name.into(), Shared::new(method));
+        self
+    }
+
+    pub fn with_getter(
+        mut self,
+        name: &str,
+        function: impl Fn(&[Object]) -> NativeResult + MaybeSync + 'static,
+    ) -> Self {
+        let getter = NativeFunction::new(name, 0, function);
+        self.getters.insert(name.into(), Shared::new(getter));
+        self
+    }
+
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    pub fn method(&self, name: &str) -> Option<Shared<NativeFunction>> {
+        self.methods.get(name).cloned()
+    }
+
+    pub fn getter(&self, name: &str) -> Option<Shared<NativeFunction>> {
+        self.getters.get(name).cloned()
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+}
+
+/// Turns a native method, which takes its receiver as the first argument,
+/// into a function of the remaining arguments.
+pub(crate) fn bind(method: &Shared<NativeFunction>, receiver: Object) -> NativeFunction {
+    let function = method.clone();
+    let bound = NativeFunction::new(&method.name, method.arity, move |arguments| {
+        let mut with_receiver = vec![receiver.clone()];
+        with_receiver.extend_from_slice(arguments);
+        function.call(&with_receiver)
+    });
+    match method.capability {
+        Some(capability) => bound.requires(capability),
+        None => bound,
+    }
+}
+
+impl PartialEq for UserData {
+    fn eq(&self, other: &Self) -> bool {
+        Shared::ptr_eq(&self.value, &other.value)
+    }
+}
+
+impl Debug for UserData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserData")
+            .field("type_name", &self.type_name)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{InterpreterOptions, Lox};
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+pub struct RunResult {
+    output: String,
+    diagnostics: String,
+}
+
+#[wasm_bindgen]
+impl RunResult {
+    #[wasm_bindgen(getter)]
+    pub fn output(&self) -> String {
+        self.output.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn diagnostics(&self) -> String {
+        self.diagnostics.clone()
+    }
+}
+
+#[wasm_bindgen]
+pub fn run(source: &str) -> RunResult {
+    let output = SharedBuffer::default();
+    let diagnostics = SharedBuffer::default();
+    let mut lox = Lox::new();
+    lox.set_output(Box::new(output.clone()));
+    lox.set_diagnostic_output(Box::new(diagnostics.clone()));
+    // `Instant::now` isn't available on wasm32-unknown-unknown, so the
+    // playground relies on the step limit instead of a time limit.
+    lox.set_options(InterpreterOptions {
+        max_duration: None,
+        ..InterpreterOptions::sandboxed()
+    });
+    lox.run_named("<playground>", source);
+    RunResult {
+        output: output.contents(),
+        diagnostics: diagnostics.contents(),
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::{Arc, Mutex};
+
+use rlox::{DebugHook, Interpreter, Lox, Progress};
+
+struct Lines(Arc<Mutex<Vec<usize>>>);
+
+impl DebugHook for Lines {
+    fn before_statement(&mut self, _: &mut Interpreter, line: usize) -> bool {
+        self.0.lock().unwrap().push(line);
+        true
+    }
+}
+
+const FOR_LOOP: &str = "var sum = 0;
+for (var i = 0; i < 2;
+     i = i + 1)
+  sum = sum + i;";
+
+// The increment runs on the line of the `for` keyword, after each pass
+// through the body.
+const FOR_LOOP_LINES: [usize; 7] = [1, 2, 2, 4, 2, 4, 2];
+
+fn record(lox: &mut Lox) -> Arc<Mutex<Vec<usize>>> {
+    let lines = Arc::new(Mutex::new(vec![]));
+    lox.interpreter()
+        .set_hook(Some(Box::new(Lines(lines.clone()))));
+    lines
+}
+
+#[test]
+fn for_increment_is_attributed_to_the_for_line() {
+    let mut lox = Lox::new();
+    let lines = record(&mut lox);
+    lox.run_source(FOR_LOOP).unwrap();
+    assert_eq!(*lines.lock().unwrap(), FOR_LOOP_LINES);
+}
+
+#[test]
+fn stepped_for_increment_is_attributed_to_the_for_line() {
+    let mut lox = Lox::new();
+    let lines = record(&mut lox);
+    let mut execution = lox.prepare(FOR_LOOP).unwrap();
+    while lox.interpreter().step(&mut execution, 1).unwrap() == Progress::Running {}
+    assert_eq!(*lines.lock().unwrap(), FOR_LOOP_LINES);
+}
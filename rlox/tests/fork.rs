@@ -0,0 +1,120 @@
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use rlox::{Lox, Object, Progress};
+
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+const COUNTER: &str = "var counter = 0;
+fun inc() {
+  counter = counter + 1;
+  return counter;
+}";
+
+#[test]
+fn forked_functions_use_the_fork_globals() {
+    let mut lox = Lox::new();
+    lox.run_source(COUNTER).unwrap();
+
+    let mut child = lox.interpreter().fork();
+    child.set_global("counter", 100.0);
+    assert_eq!(child.eval_expr("inc()").unwrap(), Object::from(101.0));
+    assert_eq!(child.eval_expr("inc()").unwrap(), Object::from(102.0));
+    assert_eq!(child.get_global("counter"), Some(Object::from(102.0)));
+
+    assert_eq!(
+        lox.interpreter().get_global("counter"),
+        Some(Object::from(0.0))
+    );
+    assert_eq!(lox.run_source("inc();").unwrap(), Object::from(1.0));
+    assert_eq!(child.get_global("counter"), Some(Object::from(102.0)));
+}
+
+#[test]
+fn stepping_in_a_fork_leaves_the_parent_alone() {
+    let mut parent = Lox::new();
+    parent.run_source(COUNTER).unwrap();
+
+    let mut child = Lox::new();
+    *child.interpreter() = parent.interpreter().fork();
+    let mut execution = child
+        .prepare("for (var i = 0; i < 3; i = i + 1) inc(); var done = true;")
+        .unwrap();
+    while child.interpreter().step(&mut execution, 1).unwrap() == Progress::Running {}
+
+    assert_eq!(
+        child.interpreter().get_global("counter"),
+        Some(Object::from(3.0))
+    );
+    assert_eq!(
+        parent.interpreter().get_global("counter"),
+        Some(Object::from(0.0))
+    );
+    assert_eq!(parent.interpreter().get_global("done"), None);
+}
+
+#[test]
+fn forks_copy_instances_and_closures() {
+    let mut lox = Lox::new();
+    lox.run_source(
+        "class Config { init() { this.mode = \"safe\"; this.self = this; } }
+         var cfg = Config();
+         fun counter() {
+           var count = 0;
+           fun tick() { count = count + 1; return count; }
+           return tick;
+         }
+         var tick = counter();
+         var alias = tick;
+         tick();",
+    )
+    .unwrap();
+
+    let mut child = lox.interpreter().fork();
+    child.eval_expr("cfg.mode = \"hacked\"").unwrap();
+    child.eval_expr("tick()").unwrap();
+    assert_eq!(child.eval_expr("alias()").unwrap(), Object::from(3.0));
+    assert_eq!(
+        child.eval_expr("cfg.self.mode").unwrap(),
+        Object::from("hacked")
+    );
+    assert_eq!(
+        child.eval_expr("cfg.self == cfg").unwrap(),
+        Object::from(true)
+    );
+    assert_eq!(
+        child.eval_expr("cfg.self.self").unwrap(),
+        child.eval_expr("cfg").unwrap()
+    );
+
+    assert_eq!(lox.run_source("cfg.mode;").unwrap(), Object::from("safe"));
+    assert_eq!(lox.run_source("tick();").unwrap(), Object::from(2.0));
+}
+
+#[test]
+fn forks_share_the_output() {
+    let output = Output::default();
+    let mut lox = Lox::with_output(output.clone());
+    let mut child = Lox::new();
+    *child.interpreter() = lox.interpreter().fork();
+    child.run_source("print \"from the child\";").unwrap();
+    lox.run_source("print \"from the parent\";").unwrap();
+    let bytes = output.0.lock().unwrap().clone();
+    assert_eq!(
+        String::from_utf8(bytes).unwrap(),
+        "from the child\nfrom the parent\n"
+    );
+}
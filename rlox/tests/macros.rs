@@ -0,0 +1,59 @@
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use rlox::Lox;
+
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn run(src: &str) -> String {
+    let output = Output::default();
+    let mut lox = Lox::with_output(output.clone());
+    lox.run_source(src).unwrap();
+    let bytes = output.0.lock().unwrap().clone();
+    String::from_utf8(bytes).unwrap()
+}
+
+#[test]
+fn free_names_in_a_macro_refer_to_globals() {
+    let src = r#"
+        var x = "global";
+        macro show() { print x; }
+        macro reset() { x = "reset"; }
+        {
+          var x = "local";
+          show();
+          reset();
+          print x;
+        }
+        print x;
+    "#;
+    assert_eq!(run(src), "global\nlocal\nreset\n");
+}
+
+#[test]
+fn locals_declared_in_a_macro_are_renamed() {
+    let src = r#"
+        macro swap(a, b) { var tmp = a; a = b; b = tmp; }
+        {
+          var tmp = 1;
+          var other = 2;
+          swap(tmp, other);
+          print tmp;
+          print other;
+        }
+    "#;
+    assert_eq!(run(src), "2\n1\n");
+}
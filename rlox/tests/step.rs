@@ -0,0 +1,112 @@
+use rlox::{Lox, Object, Progress};
+
+fn global(lox: &mut Lox, name: &str) -> Option<Object> {
+    lox.interpreter().get_global(name)
+}
+
+#[test]
+fn pauses_inside_a_loop_and_resumes() {
+    let mut lox = Lox::new();
+    let mut execution = lox
+        .prepare("var i = 0; while (i < 10) { i = i + 1; } var done = true;")
+        .unwrap();
+
+    // `var`, `while`, then the body's block and the assignment in it.
+    let progress = lox.interpreter().step(&mut execution, 4).unwrap();
+    assert_eq!(progress, Progress::Running);
+    assert_eq!(global(&mut lox, "i"), Some(Object::from(1.0)));
+
+    let progress = lox.interpreter().step(&mut execution, 6).unwrap();
+    assert_eq!(progress, Progress::Running);
+    assert_eq!(global(&mut lox, "i"), Some(Object::from(4.0)));
+    assert_eq!(global(&mut lox, "done"), None);
+
+    while lox.interpreter().step(&mut execution, 1).unwrap() == Progress::Running {}
+    assert_eq!(global(&mut lox, "i"), Some(Object::from(10.0)));
+    assert_eq!(global(&mut lox, "done"), Some(Object::from(true)));
+}
+
+#[test]
+fn pauses_inside_a_call_and_resumes() {
+    let mut lox = Lox::new();
+    let mut execution = lox
+        .prepare(
+            "var log = \"\";
+             fun count(n) {
+               for (var i = 0; i < n; i = i + 1) log = log + \"${i}\";
+               return n;
+             }
+             var result = count(3) + count(2);",
+        )
+        .unwrap();
+
+    let mut depths = vec![];
+    loop {
+        let progress = lox.interpreter().step(&mut execution, 1).unwrap();
+        depths.push(lox.interpreter().frames().len());
+        if progress == Progress::Finished {
+            break;
+        }
+    }
+    // Between steps the interpreter is back at the top level, even when the
+    // execution stopped inside `count`.
+    assert!(depths.iter().all(|&depth| depth == 1));
+    assert!(depths.len() > 10);
+    assert_eq!(global(&mut lox, "log"), Some(Object::from("01201")));
+    assert_eq!(global(&mut lox, "result"), Some(Object::from(5.0)));
+}
+
+#[test]
+fn break_and_continue_leave_their_scopes() {
+    let mut lox = Lox::new();
+    let mut execution = lox
+        .prepare(
+            "var sum = 0;
+             var i = 0;
+             while (true) {
+               var next = i + 1;
+               i = next;
+               if (i > 5) { var stop = true; break; }
+               if (i == 3) continue;
+               sum = sum + i;
+             }
+             class Counter {
+               init(start) { this.count = start; }
+               add(n) { this.count = this.count + n; return this; }
+             }
+             var counter = Counter(sum).add(1).add(2).count;",
+        )
+        .unwrap();
+
+    while lox.interpreter().step(&mut execution, 2).unwrap() == Progress::Running {}
+    assert_eq!(global(&mut lox, "sum"), Some(Object::from(12.0)));
+    assert_eq!(global(&mut lox, "counter"), Some(Object::from(15.0)));
+    assert_eq!(global(&mut lox, "next"), None);
+}
+
+#[test]
+fn runtime_errors_end_the_execution() {
+    let mut lox = Lox::new();
+    let mut execution = lox
+        .prepare("fun fail() { return 1 + nil; } var before = 1; fail(); var after = 2;")
+        .unwrap();
+
+    let err = loop {
+        match lox.interpreter().step(&mut execution, 1) {
+            Ok(Progress::Running) => (),
+            Ok(Progress::Finished) => panic!("the call should fail"),
+            Err(err) => break err,
+        }
+    };
+    assert_eq!(
+        err.message(),
+        "Operands must be two numbers or two strings. Got number (1) and nil."
+    );
+    assert_eq!(lox.interpreter().frames().len(), 1);
+    assert_eq!(
+        lox.interpreter().step(&mut execution, 1).unwrap(),
+        Progress::Finished
+    );
+    assert_eq!(global(&mut lox, "before"), Some(Object::from(1.0)));
+    assert_eq!(global(&mut lox, "after"), None);
+}
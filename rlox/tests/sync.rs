@@ -0,0 +1,11 @@
+#![cfg(feature = "sync")]
+
+use rlox::{Interpreter, Lox};
+
+fn is_send_and_sync<T: Send + Sync>() {}
+
+#[test]
+fn lox_can_be_shared_between_threads() {
+    is_send_and_sync::<Lox>();
+    is_send_and_sync::<Interpreter>();
+}